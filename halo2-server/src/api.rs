@@ -1,94 +1,171 @@
-use actix::Addr;
-use actix_web::{get, post, web, HttpResponse, Responder};
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, Instant};
 use zkp::serialization::serialized_proof::SerializedProof;
 
-use crate::state::{GetProof, SetPendingJob, SetProof, SnarkState, StateActor, PROOF_TTL};
+use crate::{
+    circuits::DEFAULT_CIRCUIT_ID,
+    job_queue::{JobQueue, JobStatus},
+    snark_processor::parse_instances,
+    state::SnarkState,
+};
 
+/// Selects the circuit of a request with `?circuit_id=<id>`. The wrap2 circuit is used if it
+/// is omitted.
+#[derive(Deserialize)]
+struct CircuitQuery {
+    circuit_id: Option<String>,
+}
+
+impl CircuitQuery {
+    fn circuit_id(&self) -> &str {
+        self.circuit_id.as_deref().unwrap_or(DEFAULT_CIRCUIT_ID)
+    }
+}
+
+fn unknown_circuit(circuit_id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(format!("unknown circuit {}", circuit_id))
+}
+
+#[get("/circuits")]
+async fn circuits(state: web::Data<SnarkState>) -> impl Responder {
+    HttpResponse::Ok().json(state.circuit_ids())
+}
+
+/// Proves a proof of the circuit and returns the result once it is done. The proof runs as a
+/// job of the queue, so it shares the workers and the queue limit with `/proof-job`.
 #[post("/prove")]
-async fn prove(state: web::Data<SnarkState>, proof: web::Json<SerializedProof>) -> impl Responder {
+async fn prove(
+    state: web::Data<SnarkState>,
+    job_queue: web::Data<JobQueue>,
+    query: web::Query<CircuitQuery>,
+    proof: web::Json<SerializedProof>,
+) -> impl Responder {
+    let circuit_id = query.circuit_id();
+    if !state.has_circuit(circuit_id) {
+        return unknown_circuit(circuit_id);
+    }
     let proof = proof.into_inner();
-    let proof_hex = state.prove(proof);
-    HttpResponse::Ok().json(proof_hex)
+    if proof.0.is_empty() {
+        return HttpResponse::BadRequest().json("proof is empty");
+    }
+    if let Err(e) = state.validate(circuit_id, &proof) {
+        return HttpResponse::BadRequest().json(e.to_string());
+    }
+    let job = match job_queue.submit(circuit_id, proof) {
+        Ok(job) => job,
+        Err(e) => return HttpResponse::ServiceUnavailable().json(e.to_string()),
+    };
+    let job = match web::block(move || job_queue.wait(&job.job_id)).await {
+        Ok(Some(job)) => job,
+        Ok(None) => return HttpResponse::InternalServerError().json("job not found"),
+        Err(e) => return HttpResponse::InternalServerError().json(e.to_string()),
+    };
+    match (job.status, job.result) {
+        (JobStatus::Done, Some(proof_result)) => HttpResponse::Ok().json(proof_result),
+        (JobStatus::Cancelled, _) => HttpResponse::Conflict().json("the job was cancelled"),
+        _ => HttpResponse::BadRequest().json(job.error.unwrap_or_default()),
+    }
 }
 
+/// A halo2 proof and its instances, in the format of `ProofResult`.
 #[derive(Serialize, Deserialize)]
-struct ProofJobResponseData {
-    proof: Option<String>,
-    elapsed_time: String,
+pub struct VerifyInput {
+    pub proof: String,
+    pub instance: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+struct VerifyOutput {
+    valid: bool,
+    error: Option<String>,
+}
+
+/// Verifies a halo2 proof in the format returned by `/prove`. A malformed proof or instance
+/// is a bad request, while a proof that does not verify is reported as invalid.
+#[post("/verify")]
+async fn verify(
+    state: web::Data<SnarkState>,
+    query: web::Query<CircuitQuery>,
+    input: web::Json<VerifyInput>,
+) -> impl Responder {
+    let circuit_id = query.circuit_id();
+    if !state.has_circuit(circuit_id) {
+        return unknown_circuit(circuit_id);
+    }
+    let proof = match hex::decode(input.proof.trim_start_matches("0x")) {
+        Ok(proof) => proof,
+        Err(e) => return HttpResponse::BadRequest().json(format!("invalid proof hex: {}", e)),
+    };
+    let instances = match parse_instances(&input.instance) {
+        Ok(instances) => instances,
+        Err(e) => return HttpResponse::BadRequest().json(e.to_string()),
+    };
+    match state.verify(circuit_id, &proof, &instances) {
+        Ok(()) => HttpResponse::Ok().json(VerifyOutput {
+            valid: true,
+            error: None,
+        }),
+        Err(e) => HttpResponse::Ok().json(VerifyOutput {
+            valid: false,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
+/// Queues a proof of the circuit for proving, or returns the status of its job if it has
+/// been submitted before. A new job is rejected with 503 if the queue is full.
 #[post("/proof-job")]
 async fn request_and_get_proof(
     state: web::Data<SnarkState>,
-    addr: web::Data<Addr<StateActor>>,
+    job_queue: web::Data<JobQueue>,
+    query: web::Query<CircuitQuery>,
     proof: web::Json<SerializedProof>,
 ) -> impl Responder {
+    let circuit_id = query.circuit_id();
+    if !state.has_circuit(circuit_id) {
+        return unknown_circuit(circuit_id);
+    }
     let proof = proof.into_inner();
     if proof.0.is_empty() {
         return HttpResponse::BadRequest().json("proof is empty");
     }
-
-    #[cfg(feature = "debug")]
-    {
-        use sha2::Digest;
-
-        let mut hasher = sha2::Sha256::new();
-        hasher.update(proof.0.clone());
-        let proof_digest = hasher.finalize();
-        let proof_digest_hex = hex::encode(proof_digest);
-        log::debug!("sha256 of proof: {proof_digest_hex}");
-    }
-
-    let proof_status = addr
-        .send(GetProof {
-            key: proof.0.clone(),
-        })
-        .await
-        .expect("fail to get job");
-
-    if let Some((proof_hex_opt, expiration)) = proof_status {
-        let elapsed_time = Instant::now() + Duration::from_secs(PROOF_TTL) - expiration;
-        if let Some(proof_hex) = proof_hex_opt {
-            return HttpResponse::Ok().json(ProofJobResponseData {
-                proof: Some(proof_hex),
-                elapsed_time: elapsed_time.as_secs().to_string(),
-            });
+    if let Err(e) = state.validate(circuit_id, &proof) {
+        return HttpResponse::BadRequest().json(e.to_string());
+    }
+    match job_queue.submit(circuit_id, proof) {
+        Ok(job) => {
+            log::debug!("Job {} is {:?}", job.job_id, job.status);
+            HttpResponse::Ok().json(job)
         }
+        Err(e) => HttpResponse::ServiceUnavailable().json(e.to_string()),
+    }
+}
+
+#[get("/proof-job/{job_id}")]
+async fn get_proof_job(
+    job_queue: web::Data<JobQueue>,
+    job_id: web::Path<String>,
+) -> impl Responder {
+    match job_queue.get(&job_id) {
+        Some(job) => HttpResponse::Ok().json(job),
+        None => HttpResponse::NotFound().json("job not found"),
+    }
+}
 
-        return HttpResponse::Ok().json(ProofJobResponseData {
-            proof: None,
-            elapsed_time: elapsed_time.as_secs().to_string(),
-        });
-    }
-
-    // Job is not found.
-
-    addr.send(SetPendingJob {
-        key: proof.0.clone(),
-    })
-    .await
-    .expect("fail to set job");
-    actix_web::rt::spawn(async move {
-        let proof_hex = state.prove(proof.clone());
-
-        // debug assertion
-        assert!(proof_hex.starts_with("0x"));
-
-        addr.send(SetProof {
-            key: proof.0,
-            value: proof_hex.clone(),
-        })
-        .await
-        .expect("fail to set proof");
-    });
-    println!("background job started.");
-
-    HttpResponse::Ok().json(ProofJobResponseData {
-        proof: None,
-        elapsed_time: "0".to_string(),
-    })
+#[delete("/proof-job/{job_id}")]
+async fn cancel_proof_job(
+    job_queue: web::Data<JobQueue>,
+    job_id: web::Path<String>,
+) -> impl Responder {
+    match job_queue.cancel(&job_id) {
+        Some(job) => HttpResponse::Ok().json(job),
+        None => HttpResponse::NotFound().json("job not found"),
+    }
+}
+
+#[get("/queue-status")]
+async fn queue_status(job_queue: web::Data<JobQueue>) -> impl Responder {
+    HttpResponse::Ok().json(job_queue.status())
 }
 
 #[get("/health")]
@@ -99,9 +176,14 @@ async fn health() -> impl Responder {
 pub fn api_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
+            .service(circuits)
             .service(prove)
+            .service(verify)
             .service(health)
-            .service(request_and_get_proof),
+            .service(request_and_get_proof)
+            .service(get_proof_job)
+            .service(cancel_proof_job)
+            .service(queue_status),
     );
 }
 
@@ -113,9 +195,16 @@ mod tests {
         dev::{Service, ServiceResponse},
         test, web, Error,
     };
-    use zkp::serialization::serialized_proof::SerializedProof;
+    use plonky2::field::{goldilocks_field::GoldilocksField, types::Field};
+    use std::sync::Arc;
+    use zkp::serialization::serialized_proof::{CircuitKind, SerializedProof};
 
-    use crate::{snark_processor::generate_proof_tuple_and_data, state::SnarkState};
+    use crate::{
+        config::Config,
+        job_queue::{JobInfo, JobQueue, JobQueueConfig, JobStatus, QueueStatus},
+        snark_processor::{generate_proof_tuple_and_data, ProofResult},
+        state::SnarkState,
+    };
 
     async fn post_helper<I, O>(
         app: &mut impl Service<Request, Response = ServiceResponse, Error = Error>,
@@ -136,19 +225,150 @@ mod tests {
         serde_json::from_slice(&body).unwrap()
     }
 
+    async fn get_helper<O>(
+        app: &mut impl Service<Request, Response = ServiceResponse, Error = Error>,
+        path: &str,
+    ) -> O
+    where
+        O: serde::de::DeserializeOwned,
+    {
+        let req = test::TestRequest::get().uri(path).to_request();
+        let resp = test::call_service(app, req).await;
+        assert!(resp.status().is_success());
+        let body = test::read_body(resp).await;
+        serde_json::from_slice(&body).unwrap()
+    }
+
     #[actix_web::test]
     async fn test_server_prove() {
-        let state = SnarkState::new();
+        let state = SnarkState::new(&Config::default()).unwrap();
         let app_data = web::Data::new(state);
+        let prover_state = app_data.clone().into_inner();
+        let job_queue = web::Data::new(
+            JobQueue::start(
+                JobQueueConfig {
+                    proof_dir: std::env::temp_dir()
+                        .join(format!("proofs_{}", rand::random::<u64>())),
+                    ..Default::default()
+                },
+                Arc::new(move |circuit_id, proof| prover_state.prove(circuit_id, proof)),
+            )
+            .unwrap(),
+        );
+        let mut app = actix_web::test::init_service(
+            actix_web::App::new()
+                .app_data(app_data.clone())
+                .app_data(job_queue.clone())
+                .configure(super::api_config),
+        )
+        .await;
+        let (proof_tuple, data) = generate_proof_tuple_and_data();
+        let serialized_proof =
+            SerializedProof::from_proof(CircuitKind::Wrap2, &data, &proof_tuple.0);
+        let proof_result: ProofResult = post_helper(&mut app, "/api/prove", serialized_proof).await;
+        println!("{}", proof_result.proof);
+        assert_eq!(proof_result.instance.len(), 4);
+        assert!(proof_result.public_inputs_hash.is_some());
+        let status: QueueStatus = get_helper(&mut app, "/api/queue-status").await;
+        assert_eq!(status.num_done, 1);
+
+        let output: super::VerifyOutput = post_helper(
+            &mut app,
+            "/api/verify?circuit_id=wrap2",
+            super::VerifyInput {
+                proof: proof_result.proof.clone(),
+                instance: proof_result.instance.clone(),
+            },
+        )
+        .await;
+        assert!(output.valid);
+        let mut instance = proof_result.instance.clone();
+        instance[0] = "0".to_string();
+        let output: super::VerifyOutput = post_helper(
+            &mut app,
+            "/api/verify",
+            super::VerifyInput {
+                proof: proof_result.proof,
+                instance,
+            },
+        )
+        .await;
+        assert!(!output.valid);
+    }
+
+    #[actix_web::test]
+    async fn test_server_proof_job() {
+        let app_data = web::Data::new(SnarkState::new(&Config::default()).unwrap());
+        // the proof is not generated, so that the job is still queued when it is cancelled
+        let job_queue = web::Data::new(
+            JobQueue::start(
+                JobQueueConfig {
+                    num_workers: 0,
+                    proof_dir: std::env::temp_dir()
+                        .join(format!("proofs_{}", rand::random::<u64>())),
+                    ..Default::default()
+                },
+                Arc::new(|_, _| unreachable!()),
+            )
+            .unwrap(),
+        );
         let mut app = actix_web::test::init_service(
             actix_web::App::new()
                 .app_data(app_data.clone())
+                .app_data(job_queue.clone())
                 .configure(super::api_config),
         )
         .await;
         let (proof_tuple, data) = generate_proof_tuple_and_data();
-        let serialized_proof = SerializedProof::from_proof(&data, &proof_tuple.0);
-        let proof_hex: String = post_helper(&mut app, "/api/prove", serialized_proof).await;
-        println!("{}", proof_hex);
+        let serialized_proof =
+            SerializedProof::from_proof(CircuitKind::Wrap2, &data, &proof_tuple.0);
+        let job: JobInfo = post_helper(&mut app, "/api/proof-job", &serialized_proof).await;
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.queue_position, Some(0));
+
+        let path = format!("/api/proof-job/{}", job.job_id);
+        let fetched: JobInfo = get_helper(&mut app, &path).await;
+        assert_eq!(fetched.job_id, job.job_id);
+        let status: QueueStatus = get_helper(&mut app, "/api/queue-status").await;
+        assert_eq!(status.queued, vec![job.job_id.clone()]);
+
+        let req = test::TestRequest::delete().uri(&path).to_request();
+        let cancelled: JobInfo = test::call_and_read_body_json(&mut app, req).await;
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+
+        let req = test::TestRequest::get()
+            .uri("/api/proof-job/unknown")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        let circuit_ids: Vec<String> = get_helper(&mut app, "/api/circuits").await;
+        assert_eq!(circuit_ids, vec!["wrap2".to_string()]);
+        let req = test::TestRequest::post()
+            .uri("/api/proof-job?circuit_id=unknown")
+            .set_json(&serialized_proof)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        // an invalid proof is rejected without queueing a job
+        let mut invalid_proof = proof_tuple.0.clone();
+        invalid_proof.public_inputs[0] += GoldilocksField::ONE;
+        let serialized_proof =
+            SerializedProof::from_proof(CircuitKind::Wrap2, &data, &invalid_proof);
+        let req = test::TestRequest::post()
+            .uri("/api/proof-job")
+            .set_json(&serialized_proof)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let status: QueueStatus = get_helper(&mut app, "/api/queue-status").await;
+        assert!(status.queued.is_empty());
+        let req = test::TestRequest::post()
+            .uri("/api/prove")
+            .set_json(&serialized_proof)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
     }
 }
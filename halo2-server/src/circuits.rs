@@ -0,0 +1,394 @@
+use anyhow::{bail, ensure, Context};
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{
+        circuit_data::VerifierCircuitData, config::GenericHashOut, proof::ProofWithPublicInputs,
+    },
+};
+use stark_verifier::{
+    bn254_poseidon::plonky2_config::Bn254PoseidonGoldilocksConfig, verifier_circuit::ProofTuple,
+};
+use std::path::{Path, PathBuf};
+use zkp::{
+    serialization::serialized_proof::{CircuitKind, SerializedProof},
+    verifier::verifier_data::{exported_kinds, load_verifier_data, verify_serialized_proof},
+};
+
+use crate::{
+    config::Config,
+    snark_processor::{
+        decode_wrap_public_inputs, parse_address, ProofResult, SnarkProcessor, WrapCircuits,
+    },
+};
+
+const D: usize = 2;
+type F = GoldilocksField;
+type OuterC = Bn254PoseidonGoldilocksConfig;
+
+/// The id of the built-in wrap2 circuit, which is used when a request names no circuit.
+pub const DEFAULT_CIRCUIT_ID: &str = "wrap2";
+
+const SAMPLE_PROOF_FILE: &str = "sample_proof.hex";
+const VK_ADDRESS_FILE: &str = "vk_address";
+
+/// A plonky2 circuit whose proofs are wrapped into halo2 proofs. Besides the built-in wrap2
+/// circuit, a circuit is registered by a directory `<circuits_dir>/<id>` containing its
+/// verifier data exported by `export_verifier_data`, a proof of it in `sample_proof.hex`
+/// from which the proving key is generated, and optionally the address of its deployed
+/// `Halo2VerifyingKey` contract in `vk_address`.
+pub struct CircuitSpec {
+    pub id: String,
+    pub kind: CircuitKind,
+    pub data: VerifierCircuitData<F, OuterC, D>,
+    pub vk_address: Option<[u8; 20]>,
+    // `None` for the wrap2 circuit, whose dummy proof is generated instead
+    sample_proof_path: Option<PathBuf>,
+    // the circuits of wrap2 kept to generate its dummy proof, until `release_circuits`
+    wrap_circuits: Option<WrapCircuits>,
+}
+
+impl CircuitSpec {
+    /// Builds the wrap2 circuit. The address of its verifying key contract is
+    /// `config.vk_address`.
+    pub fn wrap2(config: &Config) -> anyhow::Result<Self> {
+        let wrap_circuits = WrapCircuits::new()?;
+        let data = wrap_circuits
+            .wrap_processor
+            .wrap2_circuit
+            .data
+            .verifier_data();
+        Ok(Self {
+            id: DEFAULT_CIRCUIT_ID.to_string(),
+            kind: CircuitKind::Wrap2,
+            data,
+            vk_address: config.vk_address,
+            sample_proof_path: None,
+            wrap_circuits: Some(wrap_circuits),
+        })
+    }
+
+    /// Loads the circuit registered in `dir`.
+    pub fn load(id: &str, dir: &Path) -> anyhow::Result<Self> {
+        let kind = match exported_kinds(dir).as_slice() {
+            [kind] => *kind,
+            kinds => bail!(
+                "{} must contain the verifier data of one circuit, found {:?}",
+                dir.display(),
+                kinds
+            ),
+        };
+        let data = load_verifier_data(dir, kind)?;
+        let sample_proof_path = dir.join(SAMPLE_PROOF_FILE);
+        ensure!(
+            sample_proof_path.exists(),
+            "{} is missing, the proving key is generated from it",
+            sample_proof_path.display()
+        );
+        let vk_address_path = dir.join(VK_ADDRESS_FILE);
+        let vk_address = if vk_address_path.exists() {
+            let address = std::fs::read_to_string(&vk_address_path)?;
+            Some(parse_address(address.trim()).with_context(|| {
+                format!("{} is not a 20-byte hex address", vk_address_path.display())
+            })?)
+        } else {
+            None
+        };
+        Ok(Self {
+            id: id.to_string(),
+            kind,
+            data,
+            vk_address,
+            sample_proof_path: Some(sample_proof_path),
+            wrap_circuits: None,
+        })
+    }
+
+    /// Drops the circuits kept to generate the dummy proof, once the proving key is loaded.
+    pub fn release_circuits(&mut self) {
+        self.wrap_circuits = None;
+    }
+
+    /// The directory where the proving key of the circuit is saved.
+    pub fn key_dir(&self, config: &Config) -> PathBuf {
+        config.key_dir.join(&self.id)
+    }
+
+    /// Decodes the proof after checking that its header matches the circuit, and verifies it.
+    pub fn decode(
+        &self,
+        proof: &SerializedProof,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, OuterC, D>> {
+        verify_serialized_proof(self.kind, &self.data, proof)
+    }
+
+    /// Loads the proving key saved by `keygen`, without generating it.
+    pub fn load_keys(&self, config: &Config) -> anyhow::Result<SnarkProcessor> {
+        SnarkProcessor::load_keys(
+            &self.key_dir(config),
+            self.kind,
+            &self.data,
+            &config.srs_path,
+        )
+        .with_context(|| {
+            format!(
+                "failed to load the proving key of the {} circuit, run keygen first",
+                self.id
+            )
+        })
+    }
+
+    /// Proves the proof of the circuit with its proving key. The hash of the wrap public
+    /// inputs is decoded for a wrap2 proof.
+    pub fn prove(
+        &self,
+        snark_processor: &SnarkProcessor,
+        proof: &SerializedProof,
+    ) -> anyhow::Result<ProofResult> {
+        let proof = self.decode(proof)?;
+        let public_inputs_hash = if self.kind == CircuitKind::Wrap2 {
+            Some(decode_wrap_public_inputs(&proof.public_inputs)?)
+        } else {
+            None
+        };
+        let mut result = snark_processor.prove(proof, self.vk_address)?;
+        result.public_inputs_hash = public_inputs_hash;
+        Ok(result)
+    }
+
+    /// Returns a valid proof of the circuit with its verifier data, from which the proving
+    /// key and the Solidity verifier are generated. The dummy proof of wrap2 is proven with
+    /// the circuits built by `wrap2`, which are released afterwards.
+    pub fn sample_proof_tuple(&mut self) -> anyhow::Result<ProofTuple<F, OuterC, D>> {
+        let proof = match &self.sample_proof_path {
+            Some(path) => self
+                .decode(&read_serialized_proof(path)?)
+                .with_context(|| format!("{} is not a valid proof", path.display()))?,
+            None => {
+                let wrap_circuits = self
+                    .wrap_circuits
+                    .take()
+                    .context("the dummy proof of the wrap2 circuit is already generated")?;
+                wrap_circuits.prove_sample()?.0 .0
+            }
+        };
+        Ok((
+            proof,
+            self.data.verifier_only.clone(),
+            self.data.common.clone(),
+        ))
+    }
+}
+
+/// Returns the ids of the circuits registered in `circuits_dir`, which may not exist.
+pub fn registered_circuit_ids(circuits_dir: &Path) -> anyhow::Result<Vec<String>> {
+    if !circuits_dir.exists() {
+        return Ok(vec![]);
+    }
+    let entries = std::fs::read_dir(circuits_dir)
+        .with_context(|| format!("failed to read {}", circuits_dir.display()))?;
+    let mut ids = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let id = entry
+            .file_name()
+            .into_string()
+            .map_err(|name| anyhow::anyhow!("the circuit id {:?} is not UTF-8", name))?;
+        ensure!(
+            id != DEFAULT_CIRCUIT_ID,
+            "{} is the id of the built-in circuit",
+            id
+        );
+        ids.push(id);
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// Loads the circuit with the given id.
+pub fn load_circuit_spec(config: &Config, id: &str) -> anyhow::Result<CircuitSpec> {
+    if id == DEFAULT_CIRCUIT_ID {
+        return CircuitSpec::wrap2(config);
+    }
+    let dir = config.circuits_dir.join(id);
+    ensure!(
+        dir.is_dir(),
+        "unknown circuit {}, {} does not exist",
+        id,
+        dir.display()
+    );
+    CircuitSpec::load(id, &dir)
+}
+
+/// Loads the wrap2 circuit and the circuits registered in `config.circuits_dir`.
+pub fn load_circuit_specs(config: &Config) -> anyhow::Result<Vec<CircuitSpec>> {
+    let mut specs = vec![CircuitSpec::wrap2(config)?];
+    for id in registered_circuit_ids(&config.circuits_dir)? {
+        let spec = load_circuit_spec(config, &id)?;
+        // a job is identified by the proof alone, whose header names the circuit digest
+        let digest = spec.data.verifier_only.circuit_digest.to_bytes();
+        if let Some(other) = specs
+            .iter()
+            .find(|other| other.data.verifier_only.circuit_digest.to_bytes() == digest)
+        {
+            bail!(
+                "the circuits {} and {} are the same circuit",
+                other.id,
+                spec.id
+            );
+        }
+        specs.push(spec);
+    }
+    Ok(specs)
+}
+
+/// Reads a hex encoded proof, optionally as a JSON string.
+pub fn read_serialized_proof(path: &Path) -> anyhow::Result<SerializedProof> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let content = content.trim();
+    let json = if content.starts_with('"') {
+        content.to_string()
+    } else {
+        format!("\"{}\"", content)
+    };
+    serde_json::from_str(&json).context("the proof is not hex encoded")
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        hash::hash_types::HashOut,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::VerifierOnlyCircuitData,
+            config::GenericHashOut,
+        },
+    };
+    use stark_verifier::bn254_poseidon::plonky2_config::standard_stark_verifier_config;
+    use zkp::{
+        serialization::serialized_proof::{CircuitKind, SerializedProof},
+        verifier::verifier_data::export_verifier_data,
+    };
+
+    use crate::config::Config;
+
+    use super::{load_circuit_spec, registered_circuit_ids, OuterC, D, F};
+
+    #[test]
+    fn test_load_circuit_spec() {
+        let mut builder = CircuitBuilder::<F, D>::new(standard_stark_verifier_config());
+        let a = builder.add_virtual_target();
+        let b = builder.add_virtual_target();
+        let c = builder.mul(a, b);
+        builder.register_public_input(c);
+        let data = builder.build::<OuterC>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(a, F::from_canonical_u64(2));
+        pw.set_target(b, F::from_canonical_u64(3));
+        let proof = data.prove(pw).unwrap();
+
+        let config = Config {
+            circuits_dir: std::env::temp_dir().join(format!("circuits_{}", rand::random::<u64>())),
+            ..Default::default()
+        };
+        let dir = config.circuits_dir.join("spent_v2");
+        export_verifier_data(&dir, CircuitKind::Spent, &data).unwrap();
+        // the proving key cannot be generated without a sample proof
+        assert!(load_circuit_spec(&config, "spent_v2").is_err());
+        let serialized_proof = SerializedProof::from_proof(CircuitKind::Spent, &data, &proof);
+        std::fs::write(
+            dir.join("sample_proof.hex"),
+            hex::encode(&serialized_proof.0),
+        )
+        .unwrap();
+        std::fs::write(dir.join("vk_address"), format!("0x{}", "11".repeat(20))).unwrap();
+
+        assert_eq!(
+            registered_circuit_ids(&config.circuits_dir).unwrap(),
+            vec!["spent_v2".to_string()]
+        );
+        let mut spec = load_circuit_spec(&config, "spent_v2").unwrap();
+        assert_eq!(spec.kind, CircuitKind::Spent);
+        assert_eq!(spec.vk_address, Some([0x11; 20]));
+        assert_eq!(spec.key_dir(&config), config.key_dir.join("spent_v2"));
+        let (sample_proof, _, _) = spec.sample_proof_tuple().unwrap();
+        assert_eq!(sample_proof.public_inputs, vec![F::from_canonical_u64(6)]);
+
+        let mut invalid_proof = proof.clone();
+        invalid_proof.public_inputs[0] = F::from_canonical_u64(7);
+        let serialized_proof =
+            SerializedProof::from_proof(CircuitKind::Spent, &data, &invalid_proof);
+        assert!(spec.decode(&serialized_proof).is_err());
+        assert!(load_circuit_spec(&config, "unknown").is_err());
+
+        // the id of the built-in circuit cannot be registered
+        std::fs::create_dir_all(config.circuits_dir.join("wrap2")).unwrap();
+        assert!(registered_circuit_ids(&config.circuits_dir).is_err());
+        std::fs::remove_dir_all(config.circuits_dir).unwrap();
+    }
+
+    // A cyclic circuit registers its own verifier data as public inputs, which the
+    // proofs of the circuit must carry.
+    #[test]
+    fn test_load_cyclic_circuit_spec() {
+        let mut builder = CircuitBuilder::<F, D>::new(standard_stark_verifier_config());
+        let a = builder.add_virtual_target();
+        let b = builder.add_virtual_target();
+        let c = builder.mul(a, b);
+        builder.register_public_input(c);
+        let verifier_data_target = builder.add_verifier_data_public_inputs();
+        let data = builder.build::<OuterC>();
+        let prove = |verifier_only: &VerifierOnlyCircuitData<OuterC, D>| {
+            let mut pw = PartialWitness::new();
+            pw.set_target(a, F::from_canonical_u64(2));
+            pw.set_target(b, F::from_canonical_u64(3));
+            pw.set_hash_target(
+                verifier_data_target.circuit_digest,
+                HashOut::from_vec(verifier_only.circuit_digest.to_vec()),
+            );
+            for (target, hash) in verifier_data_target
+                .constants_sigmas_cap
+                .0
+                .iter()
+                .zip(&verifier_only.constants_sigmas_cap.0)
+            {
+                pw.set_hash_target(*target, HashOut::from_vec(hash.to_vec()));
+            }
+            data.prove(pw).unwrap()
+        };
+        let proof = prove(&data.verifier_only);
+
+        let config = Config {
+            circuits_dir: std::env::temp_dir().join(format!("circuits_{}", rand::random::<u64>())),
+            ..Default::default()
+        };
+        let dir = config.circuits_dir.join("withdraw_v2");
+        export_verifier_data(&dir, CircuitKind::Withdraw, &data).unwrap();
+        let serialized_proof = SerializedProof::from_proof(CircuitKind::Withdraw, &data, &proof);
+        std::fs::write(
+            dir.join("sample_proof.hex"),
+            hex::encode(&serialized_proof.0),
+        )
+        .unwrap();
+
+        let mut spec = load_circuit_spec(&config, "withdraw_v2").unwrap();
+        assert_eq!(spec.kind, CircuitKind::Withdraw);
+        let (sample_proof, _, _) = spec.sample_proof_tuple().unwrap();
+        assert_eq!(sample_proof.public_inputs, proof.public_inputs);
+        assert!(spec.decode(&serialized_proof).is_ok());
+
+        // a valid proof of the circuit carrying the verifier data of another circuit
+        let mut foreign_verifier_only = data.verifier_only.clone();
+        foreign_verifier_only.circuit_digest = HashOut::from_vec(vec![F::from_canonical_u64(1); 4]);
+        let foreign_proof = prove(&foreign_verifier_only);
+        data.verify(foreign_proof.clone()).unwrap();
+        let serialized_proof =
+            SerializedProof::from_proof(CircuitKind::Withdraw, &data, &foreign_proof);
+        assert!(spec.decode(&serialized_proof).is_err());
+        std::fs::remove_dir_all(config.circuits_dir).unwrap();
+    }
+}
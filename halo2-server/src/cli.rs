@@ -0,0 +1,362 @@
+use anyhow::{bail, Context};
+use log::{info, warn};
+use std::{collections::HashMap, path::PathBuf, str::FromStr, time::Duration};
+
+use crate::{
+    api::VerifyInput,
+    circuits::{load_circuit_spec, read_serialized_proof, DEFAULT_CIRCUIT_ID},
+    config::Config,
+    evm_verifier,
+    snark_processor::{parse_address, parse_instances, SnarkProcessor},
+};
+
+pub const USAGE: &str = "usage: halo2-server [<flags>] [<command>]
+
+commands:
+  serve                                     start the server (default)
+  srs import <file>                         import the SRS from a .ptau file or halo2 params
+  srs setup-dev                             generate an SRS from a random tau, for development only
+  keygen                                    generate the proving key and save it to the key directory
+  gen-solidity [--out-dir <dir>]            write Halo2Verifier.sol and Halo2VerifyingKey.sol
+  prove --input <file> [--output <file>]    prove a serialized plonky2 proof and write the result as JSON
+  verify --input <file>                     verify a halo2 proof in the JSON format written by prove
+  evm-verify [--dir <dir>]                  check the Solidity verifier in an in-process EVM
+
+keygen, gen-solidity, prove, verify and evm-verify take --circuit <id> to select the circuit
+[default: wrap2]. By default, the Solidity verifier of a registered circuit is written to and
+read from its directory in the circuits directory, and that of wrap2 from the current directory.
+
+flags:
+  --srs <file>            the SRS [default: srs.dat]
+  --key-dir <dir>         the directory of the proving keys [default: keys]
+  --circuits-dir <dir>    the directory of the registered circuits [default: circuits]
+  --vk-address <address>  the address of the Halo2VerifyingKey contract of wrap2
+  --num-workers <n>       the number of proofs generated at the same time [default: 1]
+  --max-attempts <n>      the number of runs of a proof job before it fails [default: 3]
+  --max-queue-len <n>     the number of queued jobs above which jobs are rejected [default: 64]
+  --proof-dir <dir>       the directory of the finished proofs [default: proofs]
+  --proof-ttl <seconds>   how long a finished proof is kept [default: 3600]";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Serve,
+    ImportSrs {
+        path: PathBuf,
+    },
+    SetupDevSrs,
+    Keygen {
+        circuit_id: String,
+    },
+    GenSolidity {
+        circuit_id: String,
+        out_dir: PathBuf,
+    },
+    Prove {
+        circuit_id: String,
+        input: PathBuf,
+        output: Option<PathBuf>,
+    },
+    Verify {
+        circuit_id: String,
+        input: PathBuf,
+    },
+    EvmVerify {
+        circuit_id: String,
+        dir: PathBuf,
+    },
+}
+
+/// Parses the arguments without the program name into the config and the command. The flags
+/// override the default config.
+pub fn parse_args(args: &[String]) -> anyhow::Result<(Config, Command)> {
+    let mut flags = HashMap::new();
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--") {
+            let value = args
+                .next()
+                .with_context(|| format!("--{} needs a value\n\n{}", name, USAGE))?;
+            flags.insert(name, value.clone());
+        } else {
+            positional.push(arg.as_str());
+        }
+    }
+
+    let mut config = Config::default();
+    if let Some(srs_path) = flags.remove("srs") {
+        config.srs_path = srs_path.into();
+    }
+    if let Some(key_dir) = flags.remove("key-dir") {
+        config.key_dir = key_dir.into();
+    }
+    if let Some(circuits_dir) = flags.remove("circuits-dir") {
+        config.circuits_dir = circuits_dir.into();
+    }
+    if let Some(vk_address) = flags.remove("vk-address") {
+        config.vk_address =
+            Some(parse_address(&vk_address).context("--vk-address must be a 20-byte hex address")?);
+    }
+    let job_queue = &mut config.job_queue;
+    if let Some(num_workers) = parsed(&mut flags, "num-workers")? {
+        job_queue.num_workers = num_workers;
+    }
+    if let Some(max_attempts) = parsed(&mut flags, "max-attempts")? {
+        job_queue.max_attempts = max_attempts;
+    }
+    if let Some(max_queue_len) = parsed(&mut flags, "max-queue-len")? {
+        job_queue.max_queue_len = max_queue_len;
+    }
+    if let Some(proof_dir) = flags.remove("proof-dir") {
+        job_queue.proof_dir = proof_dir.into();
+    }
+    if let Some(ttl) = parsed(&mut flags, "proof-ttl")? {
+        job_queue.ttl = Duration::from_secs(ttl);
+    }
+    config.check()?;
+    let command = match positional.as_slice() {
+        [] | ["serve"] => Command::Serve,
+        ["srs", "import", path] => Command::ImportSrs { path: path.into() },
+        ["srs", "setup-dev"] => Command::SetupDevSrs,
+        ["keygen"] => Command::Keygen {
+            circuit_id: circuit_id(&mut flags),
+        },
+        ["gen-solidity"] => {
+            let circuit_id = circuit_id(&mut flags);
+            Command::GenSolidity {
+                out_dir: solidity_dir(&mut flags, "out-dir", &config, &circuit_id),
+                circuit_id,
+            }
+        }
+        ["prove"] => Command::Prove {
+            circuit_id: circuit_id(&mut flags),
+            input: required(&mut flags, "input")?.into(),
+            output: flags.remove("output").map(PathBuf::from),
+        },
+        ["verify"] => Command::Verify {
+            circuit_id: circuit_id(&mut flags),
+            input: required(&mut flags, "input")?.into(),
+        },
+        ["evm-verify"] => {
+            let circuit_id = circuit_id(&mut flags);
+            Command::EvmVerify {
+                dir: solidity_dir(&mut flags, "dir", &config, &circuit_id),
+                circuit_id,
+            }
+        }
+        _ => bail!("{}", USAGE),
+    };
+    if let Some(name) = flags.keys().next() {
+        bail!("unknown flag --{} for this command\n\n{}", name, USAGE);
+    }
+    Ok((config, command))
+}
+
+fn required(flags: &mut HashMap<&str, String>, name: &str) -> anyhow::Result<String> {
+    flags
+        .remove(name)
+        .with_context(|| format!("--{} is required\n\n{}", name, USAGE))
+}
+
+fn parsed<T: FromStr>(flags: &mut HashMap<&str, String>, name: &str) -> anyhow::Result<Option<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    flags
+        .remove(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|e| anyhow::anyhow!("--{} is invalid: {}\n\n{}", name, e, USAGE))
+        })
+        .transpose()
+}
+
+fn circuit_id(flags: &mut HashMap<&str, String>) -> String {
+    flags
+        .remove("circuit")
+        .unwrap_or_else(|| DEFAULT_CIRCUIT_ID.to_string())
+}
+
+// The directory of the Solidity verifier, so that each circuit has its own contracts.
+fn solidity_dir(
+    flags: &mut HashMap<&str, String>,
+    name: &str,
+    config: &Config,
+    circuit_id: &str,
+) -> PathBuf {
+    match flags.remove(name) {
+        Some(dir) => dir.into(),
+        None if circuit_id == DEFAULT_CIRCUIT_ID => PathBuf::from("."),
+        None => config.circuits_dir.join(circuit_id),
+    }
+}
+
+/// Runs a command other than `serve`.
+pub fn run(config: &Config, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Serve => bail!("serve is run by main"),
+        Command::ImportSrs { path } => {
+            SnarkProcessor::import_srs(&path, &config.srs_path)?;
+            info!(
+                "Imported the SRS from {} to {}",
+                path.display(),
+                config.srs_path.display()
+            );
+        }
+        Command::SetupDevSrs => {
+            warn!("The SRS is generated from a random tau, do not use it in production");
+            SnarkProcessor::setup_dev_srs(&config.srs_path)?;
+        }
+        Command::Keygen { circuit_id } => {
+            let mut spec = load_circuit_spec(config, &circuit_id)?;
+            let snark_processor =
+                SnarkProcessor::load(spec.sample_proof_tuple()?, &config.srs_path)?;
+            let key_dir = spec.key_dir(config);
+            snark_processor.save_keys(&key_dir, spec.kind, &spec.data)?;
+            info!(
+                "Saved the proving key of the {} circuit to {}",
+                circuit_id,
+                key_dir.display()
+            );
+        }
+        Command::GenSolidity {
+            circuit_id,
+            out_dir,
+        } => {
+            let mut spec = load_circuit_spec(config, &circuit_id)?;
+            SnarkProcessor::generate_solidity(
+                spec.sample_proof_tuple()?,
+                &config.srs_path,
+                &out_dir,
+            )?;
+            info!(
+                "Wrote the Solidity verifier of the {} circuit to {}",
+                circuit_id,
+                out_dir.display()
+            );
+        }
+        Command::Prove {
+            circuit_id,
+            input,
+            output,
+        } => {
+            let proof = read_serialized_proof(&input)?;
+            let mut spec = load_circuit_spec(config, &circuit_id)?;
+            spec.release_circuits();
+            let snark_processor = spec.load_keys(config)?;
+            let result = serde_json::to_string_pretty(&spec.prove(&snark_processor, &proof)?)?;
+            match output {
+                Some(output) => std::fs::write(&output, result)
+                    .with_context(|| format!("failed to write {}", output.display()))?,
+                None => println!("{}", result),
+            }
+        }
+        Command::Verify { circuit_id, input } => {
+            let content = std::fs::read_to_string(&input)
+                .with_context(|| format!("failed to read {}", input.display()))?;
+            let input: VerifyInput = serde_json::from_str(&content)?;
+            let proof = hex::decode(input.proof.trim_start_matches("0x"))?;
+            let instances = parse_instances(&input.instance)?;
+            let mut spec = load_circuit_spec(config, &circuit_id)?;
+            spec.release_circuits();
+            spec.load_keys(config)?.verify(&proof, &instances)?;
+            info!("The proof is valid");
+        }
+        Command::EvmVerify { circuit_id, dir } => {
+            let gas_used = evm_verifier::check_solidity_verifier(&dir, config, &circuit_id)?;
+            info!(
+                "The Solidity verifier accepts the proof with {} gas",
+                gas_used
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::{parse_args, Command};
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let (config, command) = parse_args(&args("")).unwrap();
+        assert_eq!(command, Command::Serve);
+        assert_eq!(config.srs_path, PathBuf::from("srs.dat"));
+        assert_eq!(config.job_queue.num_workers, 1);
+        assert_eq!(config.vk_address, None);
+
+        let (config, command) = parse_args(&args(
+            "--srs params.dat prove --input proof.txt --key-dir k",
+        ))
+        .unwrap();
+        assert_eq!(config.srs_path, PathBuf::from("params.dat"));
+        assert_eq!(config.key_dir, PathBuf::from("k"));
+        assert_eq!(
+            command,
+            Command::Prove {
+                circuit_id: "wrap2".to_string(),
+                input: PathBuf::from("proof.txt"),
+                output: None
+            }
+        );
+
+        let (config, _) = parse_args(&args(&format!(
+            "--num-workers 2 --proof-ttl 60 --proof-dir p --vk-address 0x{} serve",
+            "11".repeat(20)
+        )))
+        .unwrap();
+        assert_eq!(config.job_queue.num_workers, 2);
+        assert_eq!(config.job_queue.ttl, Duration::from_secs(60));
+        assert_eq!(config.job_queue.proof_dir, PathBuf::from("p"));
+        assert_eq!(config.vk_address, Some([0x11; 20]));
+        assert!(parse_args(&args("--num-workers 0")).is_err());
+        assert!(parse_args(&args("--max-attempts many")).is_err());
+        assert!(parse_args(&args("--vk-address 0x11")).is_err());
+
+        let (_, command) = parse_args(&args("srs import ceremony.ptau")).unwrap();
+        assert_eq!(
+            command,
+            Command::ImportSrs {
+                path: PathBuf::from("ceremony.ptau")
+            }
+        );
+        let (_, command) = parse_args(&args("gen-solidity --out-dir contracts")).unwrap();
+        assert_eq!(
+            command,
+            Command::GenSolidity {
+                circuit_id: "wrap2".to_string(),
+                out_dir: PathBuf::from("contracts")
+            }
+        );
+        // each registered circuit has its own Solidity verifier
+        let (config, command) =
+            parse_args(&args("--circuits-dir c gen-solidity --circuit withdraw_v2")).unwrap();
+        assert_eq!(config.circuits_dir, PathBuf::from("c"));
+        assert_eq!(
+            command,
+            Command::GenSolidity {
+                circuit_id: "withdraw_v2".to_string(),
+                out_dir: PathBuf::from("c/withdraw_v2")
+            }
+        );
+        let (_, command) = parse_args(&args("keygen --circuit withdraw_v2")).unwrap();
+        assert_eq!(
+            command,
+            Command::Keygen {
+                circuit_id: "withdraw_v2".to_string()
+            }
+        );
+
+        assert!(parse_args(&args("prove")).is_err());
+        assert!(parse_args(&args("keygen --out-dir contracts")).is_err());
+        assert!(parse_args(&args("verify --input")).is_err());
+        assert!(parse_args(&args("unknown")).is_err());
+    }
+}
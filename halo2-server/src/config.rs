@@ -0,0 +1,47 @@
+use anyhow::ensure;
+use std::path::PathBuf;
+
+use crate::job_queue::JobQueueConfig;
+
+pub const DEFAULT_SRS_PATH: &str = "srs.dat";
+pub const DEFAULT_KEY_DIR: &str = "keys";
+pub const DEFAULT_CIRCUITS_DIR: &str = "circuits";
+
+/// The locations of the SRS, the proving keys and the registered circuits, the address of
+/// the verifying key contract of wrap2 and the settings of the job queue. They are set by the
+/// flags of `cli::parse_args`, and the defaults are used for omitted flags.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub srs_path: PathBuf,
+    pub key_dir: PathBuf,
+    pub circuits_dir: PathBuf,
+    /// The address of the deployed `Halo2VerifyingKey` contract of the wrap2 circuit
+    pub vk_address: Option<[u8; 20]>,
+    pub job_queue: JobQueueConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            srs_path: PathBuf::from(DEFAULT_SRS_PATH),
+            key_dir: PathBuf::from(DEFAULT_KEY_DIR),
+            circuits_dir: PathBuf::from(DEFAULT_CIRCUITS_DIR),
+            vk_address: None,
+            job_queue: JobQueueConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.job_queue.num_workers > 0,
+            "the number of workers must be positive"
+        );
+        ensure!(
+            self.job_queue.max_attempts > 0,
+            "the number of attempts must be positive"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{ensure, Context};
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_solidity_verifier::{compile_solidity, encode_calldata, Evm};
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+};
+
+use crate::{circuits::load_circuit_spec, config::Config, snark_processor::parse_instances};
+
+/// The `verifyProof` return value of an accepted proof, an ABI-encoded `true`.
+const ACCEPTED: [u8; 32] = {
+    let mut output = [0u8; 32];
+    output[31] = 1;
+    output
+};
+
+/// `Halo2Verifier.sol` and `Halo2VerifyingKey.sol` compiled with `solc`, which are deployed
+/// to a fresh in-process EVM for every call.
+pub struct EvmVerifier {
+    verifier_code: Vec<u8>,
+    vk_code: Vec<u8>,
+}
+
+impl EvmVerifier {
+    /// Compiles the contracts. `solc` must be in `PATH`.
+    pub fn compile(verifier_solidity: &str, vk_solidity: &str) -> Self {
+        Self {
+            verifier_code: compile_solidity(verifier_solidity),
+            vk_code: compile_solidity(vk_solidity),
+        }
+    }
+
+    /// Deploys the contracts and calls `verifyProof` with the proof and instances.
+    /// Returns the gas used if the proof is accepted.
+    pub fn verify(&self, proof: &[u8], instances: &[Fr]) -> anyhow::Result<u64> {
+        self.call(|vk_address| encode_calldata(Some(vk_address), proof, instances))
+    }
+
+    /// Deploys the contracts and calls the verifier with the calldata as it is sent on chain,
+    /// e.g. `ProofResult::calldata` of a proof generated with `vk_address`.
+    pub fn verify_calldata(&self, calldata: &[u8]) -> anyhow::Result<u64> {
+        self.call(|_| calldata.to_vec())
+    }
+
+    /// The address of `Halo2VerifyingKey` in the EVM of every call, which is deployed after
+    /// the verifier to a fresh EVM.
+    pub fn vk_address(&self) -> anyhow::Result<[u8; 20]> {
+        catch_unwind(AssertUnwindSafe(|| {
+            let mut evm = Evm::default();
+            evm.create(self.verifier_code.clone());
+            evm.create(self.vk_code.clone()).into()
+        }))
+        .map_err(|_| anyhow::anyhow!("the deployment reverted"))
+    }
+
+    // Deploys the contracts and calls the verifier with the calldata built from the address
+    // of the verifying key.
+    fn call(&self, calldata: impl FnOnce([u8; 20]) -> Vec<u8>) -> anyhow::Result<u64> {
+        // `Evm` panics if the deployment or the call reverts
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let mut evm = Evm::default();
+            let verifier_address = evm.create(self.verifier_code.clone());
+            let vk_address = evm.create(self.vk_code.clone());
+            evm.call(verifier_address, calldata(vk_address.into()))
+        }));
+        let (gas_used, output) = result.map_err(|_| anyhow::anyhow!("verifyProof reverted"))?;
+        ensure!(output == ACCEPTED, "verifyProof did not return true");
+        Ok(gas_used)
+    }
+}
+
+/// Checks `Halo2Verifier.sol` and `Halo2VerifyingKey.sol` of the circuit in `dir` end to end:
+/// proves the sample proof of the circuit with the saved proving key, checks that the
+/// contracts accept its calldata, and that they reject it with a tampered instance. Returns
+/// the gas used by `verifyProof`.
+pub fn check_solidity_verifier(
+    dir: &Path,
+    config: &Config,
+    circuit_id: &str,
+) -> anyhow::Result<u64> {
+    let verifier_path = dir.join("Halo2Verifier.sol");
+    let vk_path = dir.join("Halo2VerifyingKey.sol");
+    let verifier_solidity = std::fs::read_to_string(&verifier_path)
+        .with_context(|| format!("failed to read {}", verifier_path.display()))?;
+    let vk_solidity = std::fs::read_to_string(&vk_path)
+        .with_context(|| format!("failed to read {}", vk_path.display()))?;
+    let evm_verifier = EvmVerifier::compile(&verifier_solidity, &vk_solidity);
+
+    let mut spec = load_circuit_spec(config, circuit_id)?;
+    let snark_processor = spec.load_keys(config)?;
+    let result = snark_processor.prove(
+        spec.sample_proof_tuple()?.0,
+        Some(evm_verifier.vk_address()?),
+    )?;
+    let proof = hex::decode(result.proof.trim_start_matches("0x"))?;
+    let instances = parse_instances(&result.instance)?;
+    let calldata = result
+        .calldata
+        .context("the calldata is encoded with the address of the verifying key")?;
+
+    let gas_used = evm_verifier
+        .verify_calldata(&hex::decode(calldata.trim_start_matches("0x"))?)
+        .context("the Solidity verifier rejects a valid proof")?;
+    let mut tampered = instances;
+    tampered[0] += Fr::from(1);
+    ensure!(
+        evm_verifier.verify(&proof, &tampered).is_err(),
+        "the Solidity verifier accepts a tampered instance"
+    );
+    Ok(gas_used)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use std::path::Path;
+
+    use crate::{
+        config::DEFAULT_SRS_PATH,
+        snark_processor::{generate_proof_tuple_and_data, parse_instances, SnarkProcessor},
+    };
+
+    use super::EvmVerifier;
+
+    #[test]
+    fn test_evm_verifier() {
+        let (proof_tuple, _) = generate_proof_tuple_and_data();
+        let snark_processor =
+            SnarkProcessor::load(proof_tuple.clone(), Path::new(DEFAULT_SRS_PATH)).unwrap();
+        let (verifier_solidity, vk_solidity) = snark_processor
+            .render_solidity(proof_tuple.2.num_public_inputs)
+            .unwrap();
+        let evm_verifier = EvmVerifier::compile(&verifier_solidity, &vk_solidity);
+
+        // the calldata returned by the prover is accepted as it is
+        let vk_address = evm_verifier.vk_address().unwrap();
+        let result = snark_processor
+            .prove(proof_tuple.0, Some(vk_address))
+            .unwrap();
+        let calldata = hex::decode(&result.calldata.unwrap()[2..]).unwrap();
+        let gas_used = evm_verifier.verify_calldata(&calldata).unwrap();
+        println!("verifyProof gas: {}", gas_used);
+        let mut tampered_calldata = calldata.clone();
+        *tampered_calldata.last_mut().unwrap() ^= 1;
+        assert!(evm_verifier.verify_calldata(&tampered_calldata).is_err());
+
+        let proof = hex::decode(&result.proof[2..]).unwrap();
+        let instances = parse_instances(&result.instance).unwrap();
+        assert_eq!(evm_verifier.verify(&proof, &instances).unwrap(), gas_used);
+
+        let mut tampered = instances.clone();
+        tampered[1] += Fr::from(1);
+        assert!(evm_verifier.verify(&proof, &tampered).is_err());
+        let mut tampered_proof = proof.clone();
+        tampered_proof[0] ^= 1;
+        assert!(evm_verifier.verify(&tampered_proof, &instances).is_err());
+    }
+}
@@ -0,0 +1,595 @@
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::{
+    collections::{HashMap, VecDeque},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+use zkp::serialization::serialized_proof::SerializedProof;
+
+use crate::{
+    proof_store::{ProofStore, StoredProof},
+    snark_processor::ProofResult,
+};
+
+/// The default time-to-live for a finished job in seconds
+pub const PROOF_TTL: u64 = 60 * 60;
+
+/// The default number of queued jobs above which new jobs are rejected
+pub const MAX_QUEUE_LEN: usize = 64;
+
+/// Generates the halo2 proof of a proof of the circuit with the given id.
+pub type Prover = dyn Fn(&str, SerializedProof) -> anyhow::Result<ProofResult> + Send + Sync;
+
+#[derive(Clone, Debug)]
+pub struct JobQueueConfig {
+    /// The number of proofs generated at the same time
+    pub num_workers: usize,
+    /// The number of times a job is run before it fails
+    pub max_attempts: u32,
+    /// How long a finished job is kept
+    pub ttl: Duration,
+    /// The directory where finished proofs are saved
+    pub proof_dir: PathBuf,
+    /// The number of queued jobs above which new jobs are rejected
+    pub max_queue_len: usize,
+}
+
+impl Default for JobQueueConfig {
+    fn default() -> Self {
+        Self {
+            num_workers: 1,
+            max_attempts: 3,
+            ttl: Duration::from_secs(PROOF_TTL),
+            proof_dir: PathBuf::from("proofs"),
+            max_queue_len: MAX_QUEUE_LEN,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// The state of a job as reported by the API.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobInfo {
+    /// The sha256 of the proof bytes in hex
+    pub job_id: String,
+    pub status: JobStatus,
+    /// The number of jobs ahead of this one if it is queued
+    pub queue_position: Option<usize>,
+    pub attempts: u32,
+    /// The proof with its instances and calldata once the job is done
+    #[serde(flatten)]
+    pub result: Option<ProofResult>,
+    /// The error of the last attempt
+    pub error: Option<String>,
+    /// The seconds since the job was submitted, or until it finished
+    pub elapsed_time: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueueStatus {
+    pub num_workers: usize,
+    pub running: Vec<String>,
+    pub queued: Vec<String>,
+    pub num_done: usize,
+    pub num_failed: usize,
+    pub num_cancelled: usize,
+}
+
+struct Job {
+    // distinguishes the job from an earlier cancelled or failed job of the same proof,
+    // whose worker may still be running
+    generation: u64,
+    circuit_id: String,
+    proof: SerializedProof,
+    status: JobStatus,
+    attempts: u32,
+    result: Option<ProofResult>,
+    error: Option<String>,
+    submitted_at: Instant,
+    finished_at: Option<Instant>,
+}
+
+impl Job {
+    fn new(generation: u64, circuit_id: &str, proof: SerializedProof) -> Self {
+        Self {
+            generation,
+            circuit_id: circuit_id.to_string(),
+            proof,
+            status: JobStatus::Queued,
+            attempts: 0,
+            result: None,
+            error: None,
+            submitted_at: Instant::now(),
+            finished_at: None,
+        }
+    }
+
+    fn finish(&mut self, status: JobStatus) {
+        self.status = status;
+        self.finished_at = Some(Instant::now());
+    }
+}
+
+#[derive(Default)]
+struct Jobs {
+    jobs: HashMap<String, Job>,
+    queue: VecDeque<String>,
+    next_generation: u64,
+}
+
+impl Jobs {
+    fn info(&self, job_id: &str) -> Option<JobInfo> {
+        let job = self.jobs.get(job_id)?;
+        let end = job.finished_at.unwrap_or_else(Instant::now);
+        Some(JobInfo {
+            job_id: job_id.to_string(),
+            status: job.status,
+            queue_position: self.queue.iter().position(|id| id == job_id),
+            attempts: job.attempts,
+            result: job.result.clone(),
+            error: job.error.clone(),
+            elapsed_time: (end - job.submitted_at).as_secs().to_string(),
+        })
+    }
+
+    fn stored_info(job_id: &str, stored: StoredProof) -> JobInfo {
+        JobInfo {
+            job_id: job_id.to_string(),
+            status: JobStatus::Done,
+            queue_position: None,
+            attempts: stored.attempts,
+            result: Some(stored.result),
+            error: None,
+            elapsed_time: stored.elapsed_time,
+        }
+    }
+
+    // Removes the jobs which finished more than `ttl` ago.
+    fn prune(&mut self, ttl: Duration) {
+        let now = Instant::now();
+        self.jobs.retain(|_, job| match job.finished_at {
+            Some(finished_at) => finished_at + ttl > now,
+            None => true,
+        });
+    }
+}
+
+/// Runs proving jobs on a fixed number of worker threads in the order they are submitted.
+/// A job is identified by the sha256 of its proof, so submitting the same proof again
+/// returns the existing job. The proof header binds the proof to a single circuit, so the
+/// circuit id is not part of the job id. Finished proofs are saved in a `ProofStore` and served from it
+/// once the job is no longer in memory, e.g. after a restart.
+pub struct JobQueue {
+    config: JobQueueConfig,
+    // the condvar is waited on by both the workers and the callers of `wait`, so it is
+    // always notified with `notify_all`
+    shared: Arc<(Mutex<Jobs>, Condvar)>,
+    store: Arc<ProofStore>,
+}
+
+pub fn job_id(proof: &SerializedProof) -> String {
+    hex::encode(sha2::Sha256::digest(&proof.0))
+}
+
+impl JobQueue {
+    pub fn start(config: JobQueueConfig, prover: Arc<Prover>) -> anyhow::Result<Self> {
+        let store = Arc::new(ProofStore::open(&config.proof_dir, config.ttl)?);
+        let shared = Arc::new((Mutex::new(Jobs::default()), Condvar::new()));
+        for _ in 0..config.num_workers {
+            let shared = shared.clone();
+            let prover = prover.clone();
+            let store = store.clone();
+            let max_attempts = config.max_attempts;
+            std::thread::spawn(move || work(&shared, prover.as_ref(), &store, max_attempts));
+        }
+        let gc_store = store.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(gc_store.ttl().max(GC_MIN_INTERVAL));
+            if let Err(e) = gc_store.gc() {
+                log::error!("Failed to remove expired proofs: {}", e);
+            }
+        });
+        Ok(Self {
+            config,
+            shared,
+            store,
+        })
+    }
+
+    fn stored(&self, job_id: &str) -> Option<JobInfo> {
+        match self.store.get(job_id) {
+            Ok(stored) => stored.map(|stored| Jobs::stored_info(job_id, stored)),
+            Err(e) => {
+                log::error!("Failed to read the proof of job {}: {}", job_id, e);
+                None
+            }
+        }
+    }
+
+    /// Queues the proof of the circuit unless it is already queued, running or done. A failed
+    /// or cancelled job is queued again. Fails if `max_queue_len` jobs are already queued.
+    pub fn submit(&self, circuit_id: &str, proof: SerializedProof) -> anyhow::Result<JobInfo> {
+        let job_id = job_id(&proof);
+        let (lock, condvar) = &*self.shared;
+        let mut guard = lock.lock().unwrap();
+        let jobs = &mut *guard;
+        jobs.prune(self.config.ttl);
+        let is_new = match jobs.jobs.get(&job_id) {
+            None => {
+                if let Some(info) = self.stored(&job_id) {
+                    return Ok(info);
+                }
+                true
+            }
+            Some(job) => matches!(job.status, JobStatus::Failed | JobStatus::Cancelled),
+        };
+        if is_new {
+            anyhow::ensure!(
+                jobs.queue.len() < self.config.max_queue_len,
+                "the queue is full with {} jobs",
+                jobs.queue.len()
+            );
+            let generation = jobs.next_generation;
+            jobs.next_generation += 1;
+            jobs.jobs
+                .insert(job_id.clone(), Job::new(generation, circuit_id, proof));
+            jobs.queue.push_back(job_id.clone());
+            condvar.notify_all();
+        }
+        Ok(jobs.info(&job_id).unwrap())
+    }
+
+    pub fn get(&self, job_id: &str) -> Option<JobInfo> {
+        let mut jobs = self.shared.0.lock().unwrap();
+        jobs.prune(self.config.ttl);
+        jobs.info(job_id).or_else(|| self.stored(job_id))
+    }
+
+    /// Blocks until the job is done, failed or cancelled, and returns its final state. Returns
+    /// `None` if the job is unknown.
+    pub fn wait(&self, job_id: &str) -> Option<JobInfo> {
+        let (lock, condvar) = &*self.shared;
+        let mut jobs = lock.lock().unwrap();
+        loop {
+            let is_finished = jobs.jobs.get(job_id).map(|job| job.finished_at.is_some());
+            match is_finished {
+                None => return self.stored(job_id),
+                Some(true) => return jobs.info(job_id),
+                Some(false) => jobs = condvar.wait(jobs).unwrap(),
+            }
+        }
+    }
+
+    /// Cancels a queued or running job. A running proof is not interrupted, but its result
+    /// is discarded.
+    pub fn cancel(&self, job_id: &str) -> Option<JobInfo> {
+        let (lock, condvar) = &*self.shared;
+        let mut guard = lock.lock().unwrap();
+        let jobs = &mut *guard;
+        let Some(job) = jobs.jobs.get_mut(job_id) else {
+            // a stored proof is done and cannot be cancelled
+            return self.stored(job_id);
+        };
+        if matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+            job.finish(JobStatus::Cancelled);
+            jobs.queue.retain(|id| id != job_id);
+            condvar.notify_all();
+        }
+        jobs.info(job_id)
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let mut jobs = self.shared.0.lock().unwrap();
+        jobs.prune(self.config.ttl);
+        let ids_with_status = |status: JobStatus| {
+            jobs.jobs
+                .iter()
+                .filter(|(_, job)| job.status == status)
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>()
+        };
+        QueueStatus {
+            num_workers: self.config.num_workers,
+            running: ids_with_status(JobStatus::Running),
+            queued: jobs.queue.iter().cloned().collect(),
+            num_done: ids_with_status(JobStatus::Done).len(),
+            num_failed: ids_with_status(JobStatus::Failed).len(),
+            num_cancelled: ids_with_status(JobStatus::Cancelled).len(),
+        }
+    }
+}
+
+// The interval between garbage collections of the proof store if the TTL is shorter
+const GC_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+fn work(shared: &(Mutex<Jobs>, Condvar), prover: &Prover, store: &ProofStore, max_attempts: u32) {
+    let (lock, condvar) = shared;
+    loop {
+        let (job_id, generation, circuit_id, proof) = {
+            let mut guard = lock.lock().unwrap();
+            while guard.queue.is_empty() {
+                guard = condvar.wait(guard).unwrap();
+            }
+            let jobs = &mut *guard;
+            let job_id = jobs.queue.pop_front().unwrap();
+            let job = jobs.jobs.get_mut(&job_id).unwrap();
+            job.status = JobStatus::Running;
+            job.attempts += 1;
+            (
+                job_id,
+                job.generation,
+                job.circuit_id.clone(),
+                job.proof.clone(),
+            )
+        };
+
+        log::info!("Job {} started", job_id);
+        let result = catch_unwind(AssertUnwindSafe(|| prover(&circuit_id, proof))).unwrap_or_else(
+            |payload| {
+                Err(anyhow::anyhow!(
+                    "the prover panicked: {}",
+                    panic_message(&payload)
+                ))
+            },
+        );
+
+        let mut guard = lock.lock().unwrap();
+        let jobs = &mut *guard;
+        let Some(job) = jobs.jobs.get_mut(&job_id) else {
+            continue;
+        };
+        // the job has been cancelled, and possibly submitted again, while it was running
+        if job.generation != generation || job.status != JobStatus::Running {
+            log::info!("Job {} was cancelled, discarding its result", job_id);
+            continue;
+        }
+        match result {
+            Ok(proof_result) => {
+                log::info!("Job {} is done", job_id);
+                job.error = None;
+                job.finish(JobStatus::Done);
+                let elapsed_time = (Instant::now() - job.submitted_at).as_secs().to_string();
+                if let Err(e) = store.put(&job_id, &proof_result, job.attempts, elapsed_time) {
+                    log::error!("Failed to save the proof of job {}: {}", job_id, e);
+                }
+                job.result = Some(proof_result);
+                condvar.notify_all();
+            }
+            Err(e) if job.attempts < max_attempts => {
+                log::warn!("Job {} failed, retrying: {}", job_id, e);
+                job.error = Some(e.to_string());
+                job.status = JobStatus::Queued;
+                jobs.queue.push_back(job_id);
+                condvar.notify_all();
+            }
+            Err(e) => {
+                log::error!("Job {} failed: {}", job_id, e);
+                job.error = Some(e.to_string());
+                job.finish(JobStatus::Failed);
+                condvar.notify_all();
+            }
+        }
+    }
+}
+
+fn panic_message(payload: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
+
+    use zkp::serialization::serialized_proof::SerializedProof;
+
+    use crate::snark_processor::ProofResult;
+
+    use super::{job_id, JobInfo, JobQueue, JobQueueConfig, JobStatus};
+
+    fn proof_result(proof: &str) -> ProofResult {
+        ProofResult {
+            proof: proof.to_string(),
+            instance: vec![],
+            public_inputs_hash: Default::default(),
+            calldata: None,
+        }
+    }
+
+    fn test_config(num_workers: usize, max_attempts: u32) -> JobQueueConfig {
+        JobQueueConfig {
+            num_workers,
+            max_attempts,
+            proof_dir: std::env::temp_dir().join(format!("proofs_{}", rand::random::<u64>())),
+            ..Default::default()
+        }
+    }
+
+    fn wait_for(queue: &JobQueue, job_id: &str, status: JobStatus) -> JobInfo {
+        let start = Instant::now();
+        loop {
+            let info = queue.get(job_id).unwrap();
+            if info.status == status {
+                return info;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "job is {:?}",
+                info
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_job_queue() {
+        // the prover blocks until it receives a result for each job
+        let (sender, receiver) = mpsc::channel::<anyhow::Result<ProofResult>>();
+        let receiver = Mutex::new(receiver);
+        let config = test_config(1, 2);
+        let queue = JobQueue::start(
+            config.clone(),
+            Arc::new(move |circuit_id, _| {
+                assert_eq!(circuit_id, "wrap2");
+                receiver.lock().unwrap().recv().unwrap()
+            }),
+        )
+        .unwrap();
+
+        let first = SerializedProof(vec![1]);
+        let second = SerializedProof(vec![2]);
+        let info = queue.submit("wrap2", first.clone()).unwrap();
+        assert_eq!(info.job_id, job_id(&first));
+        wait_for(&queue, &info.job_id, JobStatus::Running);
+        let info = queue.submit("wrap2", second.clone()).unwrap();
+        assert_eq!(info.status, JobStatus::Queued);
+        assert_eq!(info.queue_position, Some(0));
+        // the same proof is the same job
+        assert_eq!(
+            queue
+                .submit("wrap2", second.clone())
+                .unwrap()
+                .queue_position,
+            Some(0)
+        );
+        let status = queue.status();
+        assert_eq!(status.running, vec![job_id(&first)]);
+        assert_eq!(status.queued, vec![job_id(&second)]);
+
+        // the first job fails once and is retried after the second one
+        sender.send(Err(anyhow::anyhow!("out of memory"))).unwrap();
+        wait_for(&queue, &job_id(&second), JobStatus::Running);
+        let info = queue.get(&job_id(&first)).unwrap();
+        assert_eq!(info.status, JobStatus::Queued);
+        assert_eq!(info.error.as_deref(), Some("out of memory"));
+        sender.send(Ok(proof_result("0x02"))).unwrap();
+        let info = queue.wait(&job_id(&second)).unwrap();
+        assert_eq!(info.status, JobStatus::Done);
+        assert_eq!(info.result, Some(proof_result("0x02")));
+        // the proof is served from the store after a restart
+        let restarted = JobQueue::start(
+            JobQueueConfig {
+                num_workers: 0,
+                ..config.clone()
+            },
+            Arc::new(|_, _| unreachable!()),
+        )
+        .unwrap();
+        let info = restarted.submit("wrap2", second.clone()).unwrap();
+        assert_eq!(info.status, JobStatus::Done);
+        assert_eq!(info.result, Some(proof_result("0x02")));
+
+        sender.send(Err(anyhow::anyhow!("out of memory"))).unwrap();
+        let info = queue.wait(&job_id(&first)).unwrap();
+        assert_eq!(info.status, JobStatus::Failed);
+        assert_eq!(info.attempts, 2);
+
+        // a failed job can be submitted again, and cancelled
+        let info = queue.submit("wrap2", first.clone()).unwrap();
+        assert_eq!(info.attempts, 0);
+        wait_for(&queue, &info.job_id, JobStatus::Running);
+        let info = queue.cancel(&info.job_id).unwrap();
+        assert_eq!(info.status, JobStatus::Cancelled);
+        sender.send(Ok(proof_result("0x01"))).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let info = queue.get(&info.job_id).unwrap();
+        assert_eq!(info.status, JobStatus::Cancelled);
+        assert_eq!(info.result, None);
+        assert!(queue.get("unknown").is_none());
+        assert!(queue.wait("unknown").is_none());
+        std::fs::remove_dir_all(config.proof_dir).unwrap();
+    }
+
+    #[test]
+    fn test_job_queue_panic() {
+        let config = test_config(2, 1);
+        let queue = JobQueue::start(
+            config.clone(),
+            Arc::new(|_, proof| {
+                assert!(!proof.0.is_empty(), "empty proof");
+                Ok(proof_result("0x"))
+            }),
+        )
+        .unwrap();
+        let info = queue.submit("wrap2", SerializedProof(vec![])).unwrap();
+        let info = wait_for(&queue, &info.job_id, JobStatus::Failed);
+        assert!(info.error.unwrap().contains("empty proof"));
+        let info = queue.submit("wrap2", SerializedProof(vec![0])).unwrap();
+        wait_for(&queue, &info.job_id, JobStatus::Done);
+        std::fs::remove_dir_all(config.proof_dir).unwrap();
+    }
+
+    #[test]
+    fn test_job_queue_resubmit() {
+        // the i-th proving run blocks until the i-th sender sends, and returns "0x0<i>"
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..3).map(|_| mpsc::channel::<()>()).unzip();
+        let receivers = receivers.into_iter().map(Mutex::new).collect::<Vec<_>>();
+        let calls = AtomicUsize::new(0);
+        let config = JobQueueConfig {
+            max_queue_len: 1,
+            ..test_config(2, 1)
+        };
+        let queue = JobQueue::start(
+            config.clone(),
+            Arc::new(move |_, _| {
+                let i = calls.fetch_add(1, Ordering::SeqCst);
+                receivers[i].lock().unwrap().recv().unwrap();
+                Ok(proof_result(&format!("0x0{}", i)))
+            }),
+        )
+        .unwrap();
+
+        // the first run is still going on when the job is cancelled and submitted again
+        let first = SerializedProof(vec![1]);
+        let info = queue.submit("wrap2", first.clone()).unwrap();
+        wait_for(&queue, &info.job_id, JobStatus::Running);
+        queue.cancel(&info.job_id).unwrap();
+        queue.submit("wrap2", first.clone()).unwrap();
+        wait_for(&queue, &info.job_id, JobStatus::Running);
+
+        // both workers are busy, so one job can be queued
+        let second = SerializedProof(vec![2]);
+        let third = SerializedProof(vec![3]);
+        assert_eq!(
+            queue.submit("wrap2", second.clone()).unwrap().status,
+            JobStatus::Queued
+        );
+        assert!(queue.submit("wrap2", third.clone()).is_err());
+
+        // the result of the cancelled run is discarded
+        senders[0].send(()).unwrap();
+        wait_for(&queue, &job_id(&second), JobStatus::Running);
+        let info = queue.get(&info.job_id).unwrap();
+        assert_eq!(info.status, JobStatus::Running);
+        assert_eq!(info.result, None);
+        senders[1].send(()).unwrap();
+        let info = wait_for(&queue, &info.job_id, JobStatus::Done);
+        assert_eq!(info.result, Some(proof_result("0x01")));
+        senders[2].send(()).unwrap();
+        wait_for(&queue, &job_id(&second), JobStatus::Done);
+        std::fs::remove_dir_all(config.proof_dir).unwrap();
+    }
+}
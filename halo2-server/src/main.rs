@@ -1,12 +1,20 @@
-use actix::Actor;
 use actix_web::{web::Data, App, HttpServer};
 use api::api_config;
+use cli::Command;
 use dotenv::dotenv;
+use job_queue::JobQueue;
 use log::{error, info};
-use state::{SnarkState, StateActor};
+use state::SnarkState;
 
 pub mod api;
+pub mod circuits;
+pub mod cli;
+pub mod config;
+pub mod evm_verifier;
+pub mod job_queue;
+pub mod proof_store;
 pub mod snark_processor;
+pub mod srs;
 pub mod state;
 
 #[actix_web::main]
@@ -37,9 +45,25 @@ async fn main() -> std::io::Result<()> {
         }
     }));
 
-    let state = SnarkState::new();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (config, command) = cli::parse_args(&args)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    if command != Command::Serve {
+        return cli::run(&config, command)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:#}", e)));
+    }
+
+    let state = SnarkState::new(&config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:#}", e)))?;
+    info!("Loaded the circuits {:?}", state.circuit_ids());
     let app_data = Data::new(state);
-    let addr = StateActor::new().start();
+    let prover_state = app_data.clone().into_inner();
+    let job_queue = JobQueue::start(
+        config.job_queue.clone(),
+        std::sync::Arc::new(move |circuit_id, proof| prover_state.prove(circuit_id, proof)),
+    )
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let job_queue = Data::new(job_queue);
     info!("Starting server at {server_host}:{server_port}");
 
     #[cfg(feature = "debug")]
@@ -48,7 +72,7 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .app_data(app_data.clone())
-            .app_data(Data::new(addr.clone()))
+            .app_data(job_queue.clone())
             .configure(api_config)
             .wrap(actix_web::middleware::Logger::default())
     })
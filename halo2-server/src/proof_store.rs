@@ -0,0 +1,198 @@
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::snark_processor::ProofResult;
+
+// A temporary file older than this is left by an interrupted write, since a write takes
+// much less time.
+const TMP_FILE_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// A finished proof as saved in the store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredProof {
+    pub result: ProofResult,
+    pub attempts: u32,
+    /// The seconds between the submission of the job and its completion
+    pub elapsed_time: String,
+    /// The unix time in seconds when the proof was saved
+    pub created_at: u64,
+}
+
+/// Keeps finished halo2 proofs on disk, one JSON file per job id, so that they are still
+/// served after a restart. A proof expires `ttl` after it was saved.
+pub struct ProofStore {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the system time is before the unix epoch")
+        .as_secs()
+}
+
+impl ProofStore {
+    /// Opens the store in `dir`, creating the directory if needed, and removes the
+    /// expired proofs.
+    pub fn open(dir: &Path, ttl: Duration) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+        let store = Self {
+            dir: dir.to_path_buf(),
+            ttl,
+        };
+        let removed = store.gc()?;
+        if removed > 0 {
+            log::info!("Removed {} expired proofs from {}", removed, dir.display());
+        }
+        Ok(store)
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    // The job id is the sha256 of the proof in hex, which also keeps the path in `dir`.
+    fn path(&self, job_id: &str) -> anyhow::Result<PathBuf> {
+        ensure!(
+            job_id.len() == 64 && job_id.bytes().all(|b| b.is_ascii_hexdigit()),
+            "invalid job id {}",
+            job_id
+        );
+        Ok(self.dir.join(format!("{}.json", job_id)))
+    }
+
+    fn is_expired(&self, proof: &StoredProof) -> bool {
+        proof.created_at + self.ttl.as_secs() <= now()
+    }
+
+    /// Returns the proof of the job unless it is missing or expired.
+    pub fn get(&self, job_id: &str) -> anyhow::Result<Option<StoredProof>> {
+        let Ok(path) = self.path(job_id) else {
+            return Ok(None);
+        };
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        let proof: StoredProof = serde_json::from_slice(&bytes)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        if self.is_expired(&proof) {
+            return Ok(None);
+        }
+        Ok(Some(proof))
+    }
+
+    pub fn put(
+        &self,
+        job_id: &str,
+        result: &ProofResult,
+        attempts: u32,
+        elapsed_time: String,
+    ) -> anyhow::Result<()> {
+        let path = self.path(job_id)?;
+        let stored = StoredProof {
+            result: result.clone(),
+            attempts,
+            elapsed_time,
+            created_at: now(),
+        };
+        // write to a temporary file first so that a crash never leaves a partial proof
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(&stored)?)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Removes the expired and unreadable proofs, and the temporary files of interrupted
+    /// writes, and returns how many were removed. Files whose name is not a job id are kept.
+    pub fn gc(&self) -> anyhow::Result<usize> {
+        let mut removed = 0;
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if name.ends_with(".json.tmp") {
+                let age = entry.metadata()?.modified()?.elapsed().unwrap_or_default();
+                if age > TMP_FILE_MAX_AGE {
+                    std::fs::remove_file(&path)?;
+                    removed += 1;
+                }
+                continue;
+            }
+            let Some(job_id) = name.strip_suffix(".json") else {
+                continue;
+            };
+            if self.path(job_id).is_err() {
+                continue;
+            }
+            if !matches!(self.get(job_id), Ok(Some(_))) {
+                std::fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::snark_processor::ProofResult;
+
+    use super::ProofStore;
+
+    #[test]
+    fn test_proof_store() {
+        let dir = std::env::temp_dir().join(format!("proofs_{}", rand::random::<u64>()));
+        let job_id = "ab".repeat(32);
+        let result = ProofResult {
+            proof: "0x01".to_string(),
+            instance: vec!["1".to_string()],
+            public_inputs_hash: Default::default(),
+            calldata: None,
+        };
+        let store = ProofStore::open(&dir, Duration::from_secs(60)).unwrap();
+        assert_eq!(store.get(&job_id).unwrap(), None);
+        store.put(&job_id, &result, 1, "5".to_string()).unwrap();
+        assert!(store.put("../proof", &result, 1, "5".to_string()).is_err());
+
+        // the proof is kept across reopening the store
+        let store = ProofStore::open(&dir, Duration::from_secs(60)).unwrap();
+        let proof = store.get(&job_id).unwrap().unwrap();
+        assert_eq!(proof.result, result);
+        assert_eq!(proof.elapsed_time, "5");
+
+        // a file whose name is not a job id is kept, and so is a temporary file being written
+        std::fs::write(dir.join("notes.json"), "{}").unwrap();
+        let tmp_path = dir.join(format!("{}.json.tmp", "cd".repeat(32)));
+        std::fs::write(&tmp_path, "{").unwrap();
+        assert_eq!(store.gc().unwrap(), 0);
+        // while a temporary file left by an interrupted write is removed
+        std::fs::File::options()
+            .write(true)
+            .open(&tmp_path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(120))
+            .unwrap();
+        assert_eq!(store.gc().unwrap(), 1);
+        assert!(!tmp_path.exists());
+
+        // and removed once it expired
+        let store = ProofStore::open(&dir, Duration::ZERO).unwrap();
+        assert_eq!(store.get(&job_id).unwrap(), None);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
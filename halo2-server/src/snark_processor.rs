@@ -1,17 +1,35 @@
+use crate::srs::{import_params, setup_dev_params};
+use anyhow::{ensure, Context};
 use halo2_proofs::{
     dev::MockProver,
     halo2curves::bn256::{Bn256, Fr, G1Affine},
-    plonk::{keygen_pk, keygen_vk, ProvingKey},
-    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    plonk::{keygen_pk, keygen_vk, verify_proof, ProvingKey},
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::VerifierSHPLONK,
+            strategy::SingleStrategy,
+        },
+        VerificationStrategy,
+    },
+    SerdeFormat,
 };
 use halo2_solidity_verifier::BatchOpenScheme::Bdfg21;
-use halo2_solidity_verifier::SolidityGenerator;
+use halo2_solidity_verifier::{encode_calldata, Keccak256Transcript, SolidityGenerator};
 use plonky2::{
-    field::{goldilocks_field::GoldilocksField, types::PrimeField64},
+    field::{
+        goldilocks_field::GoldilocksField,
+        types::{Field64, PrimeField64},
+    },
+    hash::hash_types::HashOut,
     plonk::{
-        circuit_data::CircuitData, config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs,
+        circuit_data::{CircuitData, VerifierCircuitData},
+        config::{GenericHashOut, PoseidonGoldilocksConfig},
+        proof::ProofWithPublicInputs,
     },
 };
+use serde::{Deserialize, Serialize};
 use stark_verifier::{
     bn254_poseidon::plonky2_config::{
         standard_inner_stark_verifier_config, standard_stark_verifier_config,
@@ -21,7 +39,11 @@ use stark_verifier::{
     types::{common_data::CommonData, proof::ProofValues, verification_key::VerificationKeyValues},
     verifier_circuit::{ProofTuple, Verifier},
 };
-use std::{fs::File, io::Write};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
 use zkp::{
     base_circuits::{
         block_tree_circuit::BlockTreeCircuit, spent_circuit::SpentCircuit,
@@ -32,14 +54,30 @@ use zkp::{
         block_processor::BlockProcessor, settlement_processor::SettlementProcessor,
         wrap_processor::WrapProcessor,
     },
+    serialization::serialized_proof::CircuitKind,
+    utils::h256::H256,
+    verifier::verifier_data::{export_verifier_circuit_data, load_verifier_data},
 };
 
 const D: usize = 2;
 type F = GoldilocksField;
 type OuterC = Bn254PoseidonGoldilocksConfig;
 
-lazy_static::lazy_static! {
-    static ref SRS_PATH: String = std::env::var("SRS_PATH").unwrap_or_else(|_| "srs.dat".to_string());
+pub fn parse_address(address: &str) -> anyhow::Result<[u8; 20]> {
+    let bytes = hex::decode(address.trim_start_matches("0x"))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow::anyhow!("the address has {} bytes", bytes.len()))
+}
+
+/// The magic bytes at the beginning of a proving key file.
+const PK_MAGIC: [u8; 4] = *b"PNPK";
+
+/// The version of the proving key file. Bump it when the layout changes.
+const PK_FORMAT_VERSION: u8 = 1;
+
+fn pk_path(dir: &Path, kind: CircuitKind) -> PathBuf {
+    dir.join(format!("{}.halo2_pk.bin", kind))
 }
 
 pub struct SnarkProcessor {
@@ -49,26 +87,93 @@ pub struct SnarkProcessor {
     pub common_data: CommonData<Fr>,
 }
 
+/// The halo2 proof of a plonky2 proof, with what is needed to submit it on chain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ProofResult {
     pub proof: String,
+    /// The public inputs of the plonky2 proof in decimal
     pub instance: Vec<String>,
+    /// The `WrapPublicInputs` hash decoded from the instances of a wrap2 proof
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_inputs_hash: Option<H256>,
+    /// The ABI-encoded call of `Halo2Verifier.verifyProof`, if the address of the
+    /// verifying key contract is known
+    pub calldata: Option<String>,
+}
+
+/// Parses instances in the decimal form of `ProofResult::instance`.
+pub fn parse_instances(instances: &[String]) -> anyhow::Result<Vec<Fr>> {
+    instances
+        .iter()
+        .map(|instance| {
+            let value: u64 = instance
+                .parse()
+                .with_context(|| format!("invalid instance {}", instance))?;
+            ensure!(
+                value < F::ORDER,
+                "the instance {} is not a Goldilocks field element",
+                instance
+            );
+            Ok(Fr::from(value))
+        })
+        .collect()
+}
+
+/// Decodes the four public inputs of a wrap2 proof into the keccak hash of the
+/// `WrapPublicInputs` they are reduced from. The hash is recovered exactly unless one of its
+/// 64-bit limbs is not below the Goldilocks order, which is negligible for a keccak output.
+pub fn decode_wrap_public_inputs(public_inputs: &[F]) -> anyhow::Result<H256> {
+    let elements: [F; 4] = public_inputs.try_into().map_err(|_| {
+        anyhow::anyhow!(
+            "a wrap2 proof has 4 public inputs, got {}",
+            public_inputs.len()
+        )
+    })?;
+    Ok(H256::from(HashOut { elements }))
 }
 
 const DEGREE: u32 = 20;
 
+fn read_srs(srs_path: &Path) -> anyhow::Result<ParamsKZG<Bn256>> {
+    let mut reader = BufReader::new(
+        File::open(srs_path)
+            .with_context(|| format!("failed to open the SRS {}", srs_path.display()))?,
+    );
+    Ok(ParamsKZG::<Bn256>::read(&mut reader)?)
+}
+
+fn write_srs(srs: &ParamsKZG<Bn256>, srs_path: &Path) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(
+        File::create(srs_path)
+            .with_context(|| format!("failed to create {}", srs_path.display()))?,
+    );
+    srs.write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
 impl SnarkProcessor {
-    pub fn setup_srs() {
-        let mut rng = rand::rngs::OsRng;
-        let srs = ParamsKZG::<Bn256>::setup(DEGREE, &mut rng);
-        let mut file = File::create(SRS_PATH.as_str()).unwrap();
-        srs.write(&mut file).unwrap();
+    /// Imports the SRS from a powers-of-tau ceremony transcript, and writes it to `srs_path`.
+    pub fn import_srs(path: &Path, srs_path: &Path) -> anyhow::Result<()> {
+        let srs = import_params(path, DEGREE)?;
+        write_srs(&srs, srs_path)
     }
 
-    pub fn setup(dummy_proof_tuple: ProofTuple<GoldilocksField, OuterC, 2>) {
-        let srs = {
-            let mut file = File::open(SRS_PATH.as_str()).expect("failed to open srs.dat");
-            ParamsKZG::<Bn256>::read(&mut file).unwrap()
-        };
+    /// Writes an SRS from a random tau to `srs_path`. Whoever runs it can learn tau,
+    /// so it must only be used for development.
+    pub fn setup_dev_srs(srs_path: &Path) -> anyhow::Result<()> {
+        let srs = setup_dev_params(DEGREE);
+        write_srs(&srs, srs_path)
+    }
+
+    /// Checks the halo2 circuit against the dummy proof with the mock prover, and writes
+    /// `Halo2Verifier.sol` and `Halo2VerifyingKey.sol` for it to `out_dir`.
+    pub fn generate_solidity(
+        dummy_proof_tuple: ProofTuple<GoldilocksField, OuterC, 2>,
+        srs_path: &Path,
+        out_dir: &Path,
+    ) -> anyhow::Result<()> {
+        let srs = read_srs(srs_path)?;
 
         let (proof_with_public_inputs, vd, cd) = dummy_proof_tuple;
         let proof = ProofValues::<Fr, 2>::from(proof_with_public_inputs.proof);
@@ -80,25 +185,25 @@ impl SnarkProcessor {
         let vk = VerificationKeyValues::from(vd.clone());
         let common_data = CommonData::from(cd);
         let circuit = Verifier::new(proof, instances.clone(), vk, common_data);
-        let mock_prover = MockProver::run(DEGREE, &circuit, vec![instances.clone()]).unwrap();
+        let mock_prover = MockProver::run(DEGREE, &circuit, vec![instances.clone()])
+            .map_err(|e| anyhow::anyhow!("failed to run the mock prover: {:?}", e))?;
         mock_prover.assert_satisfied();
 
         // generates EVM verifier
-        let vk = keygen_vk(&srs, &circuit).unwrap();
+        let vk = keygen_vk(&srs, &circuit)?;
         let generator = SolidityGenerator::new(&srs, &vk, Bdfg21, instances.len());
-        let (verifier_solidity, vk_solidity) = generator.render_separately().unwrap();
-        {
-            // save verifier solidity and vk_solidity
-            let mut file = File::create("Halo2Verifier.sol").unwrap();
-            file.write_all(verifier_solidity.as_bytes()).unwrap();
-            let mut file = File::create("Halo2VerifyingKey.sol").unwrap();
-            file.write_all(vk_solidity.as_bytes()).unwrap();
-        }
+        let (verifier_solidity, vk_solidity) = generator.render_separately()?;
+        std::fs::create_dir_all(out_dir)?;
+        std::fs::write(out_dir.join("Halo2Verifier.sol"), verifier_solidity)?;
+        std::fs::write(out_dir.join("Halo2VerifyingKey.sol"), vk_solidity)?;
+        Ok(())
     }
 
+    /// Generates the proving key for the circuit of the dummy proof.
     pub fn load(
         dummy_proof_tuple: ProofTuple<GoldilocksField, Bn254PoseidonGoldilocksConfig, 2>,
-    ) -> Self {
+        srs_path: &Path,
+    ) -> anyhow::Result<Self> {
         let (proof_with_public_inputs, vd, cd) = dummy_proof_tuple;
         let proof = ProofValues::<Fr, 2>::from(proof_with_public_inputs.proof);
         let instances = proof_with_public_inputs
@@ -109,24 +214,116 @@ impl SnarkProcessor {
         let vk = VerificationKeyValues::from(vd.clone());
         let common_data = CommonData::from(cd);
         let circuit = Verifier::new(proof, instances.clone(), vk.clone(), common_data.clone());
-        let srs = {
-            let mut file = File::open(SRS_PATH.as_str()).expect("failed to open srs.dat");
-            ParamsKZG::<Bn256>::read(&mut file).unwrap()
-        };
-        let _vk = keygen_vk(&srs, &circuit).unwrap();
-        let pk = keygen_pk(&srs, _vk, &circuit).unwrap();
-        Self {
+        let srs = read_srs(srs_path)?;
+        let _vk = keygen_vk(&srs, &circuit)?;
+        let pk = keygen_pk(&srs, _vk, &circuit)?;
+        Ok(Self {
             srs,
             pk,
             vk,
             common_data,
-        }
+        })
+    }
+
+    /// Writes the proving key to `dir` with the plonky2 circuit digest and the degree it was
+    /// generated for, and the verifier data of the plonky2 circuit, from which the
+    /// verification key values and the common data are restored.
+    pub fn save_keys(
+        &self,
+        dir: &Path,
+        kind: CircuitKind,
+        data: &VerifierCircuitData<F, OuterC, D>,
+    ) -> anyhow::Result<()> {
+        export_verifier_circuit_data(dir, kind, data)?;
+        let path = pk_path(dir, kind);
+        let mut writer = BufWriter::new(
+            File::create(&path).with_context(|| format!("failed to create {}", path.display()))?,
+        );
+        let circuit_digest = data.verifier_only.circuit_digest.to_bytes();
+        writer.write_all(&PK_MAGIC)?;
+        writer.write_all(&[PK_FORMAT_VERSION])?;
+        writer.write_all(&DEGREE.to_le_bytes())?;
+        writer.write_all(&(circuit_digest.len() as u32).to_le_bytes())?;
+        writer.write_all(&circuit_digest)?;
+        self.pk.write(&mut writer, SerdeFormat::RawBytes)?;
+        writer.flush()?;
+        Ok(())
     }
 
+    /// Reads the keys written by `save_keys`. Fails if they were generated for another
+    /// circuit than `data`, e.g. after the circuits have changed.
+    pub fn load_keys(
+        dir: &Path,
+        kind: CircuitKind,
+        data: &VerifierCircuitData<F, OuterC, D>,
+        srs_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let circuit_digest = data.verifier_only.circuit_digest.to_bytes();
+        let verifier_data: VerifierCircuitData<F, OuterC, D> = load_verifier_data(dir, kind)?;
+        ensure!(
+            verifier_data.verifier_only.circuit_digest.to_bytes() == circuit_digest,
+            "the saved verifier data is not of the current {} circuit",
+            kind
+        );
+
+        let path = pk_path(dir, kind);
+        let mut reader = BufReader::new(
+            File::open(&path).with_context(|| format!("failed to open {}", path.display()))?,
+        );
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        ensure!(
+            magic == PK_MAGIC,
+            "{} is not a proving key file",
+            path.display()
+        );
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        ensure!(
+            version[0] == PK_FORMAT_VERSION,
+            "unsupported proving key format version {}, expected {}",
+            version[0],
+            PK_FORMAT_VERSION
+        );
+        let mut degree = [0u8; 4];
+        reader.read_exact(&mut degree)?;
+        let degree = u32::from_le_bytes(degree);
+        ensure!(
+            degree == DEGREE,
+            "the proving key is of degree {}, expected {}",
+            degree,
+            DEGREE
+        );
+        let mut digest_len = [0u8; 4];
+        reader.read_exact(&mut digest_len)?;
+        let mut saved_digest = vec![0u8; u32::from_le_bytes(digest_len) as usize];
+        reader.read_exact(&mut saved_digest)?;
+        ensure!(
+            saved_digest == circuit_digest,
+            "the proving key was generated for the {} circuit digest {}, but the current digest is {}",
+            kind,
+            hex::encode(&saved_digest),
+            hex::encode(&circuit_digest)
+        );
+        let pk = ProvingKey::<G1Affine>::read::<_, Verifier>(&mut reader, SerdeFormat::RawBytes)?;
+
+        let srs = read_srs(srs_path)?;
+        Ok(Self {
+            srs,
+            pk,
+            vk: VerificationKeyValues::from(verifier_data.verifier_only),
+            common_data: CommonData::from(verifier_data.common),
+        })
+    }
+
+    /// Generates the halo2 proof and verifies it before returning it, so that a proof which
+    /// would be rejected on chain is never handed out. The calldata is encoded if the address
+    /// of the deployed `Halo2VerifyingKey` contract is given.
     pub fn prove(
         &self,
         proof_with_public_inputs: ProofWithPublicInputs<F, Bn254PoseidonGoldilocksConfig, D>,
-    ) -> ProofResult {
+        vk_address: Option<[u8; 20]>,
+    ) -> anyhow::Result<ProofResult> {
         let proof = ProofValues::<Fr, 2>::from(proof_with_public_inputs.proof);
         let instances = proof_with_public_inputs
             .public_inputs
@@ -142,6 +339,11 @@ impl SnarkProcessor {
         let mut rng = rand::thread_rng();
         let proof =
             create_proof_checked(&self.srs, &self.pk, circuit.clone(), &instances, &mut rng);
+        self.verify(&proof, &instances)
+            .context("the generated proof does not verify")?;
+        let calldata = vk_address.map(|vk_address| {
+            "0x".to_string() + &hex::encode(encode_calldata(Some(vk_address), &proof, &instances))
+        });
         let proof_hex = "0x".to_string() + &hex::encode(proof);
         let instance_str = proof_with_public_inputs
             .public_inputs
@@ -149,87 +351,162 @@ impl SnarkProcessor {
             .map(|e| format!("{}", e.to_canonical_u64()))
             .collect::<Vec<String>>();
 
-        ProofResult {
+        Ok(ProofResult {
             proof: proof_hex,
             instance: instance_str,
-        }
+            public_inputs_hash: None,
+            calldata,
+        })
+    }
+
+    /// Verifies a halo2 proof against the verifying key with the KZG verifier and the
+    /// keccak transcript of `Halo2Verifier.sol`.
+    pub fn verify(&self, proof: &[u8], instances: &[Fr]) -> anyhow::Result<()> {
+        let mut transcript = Keccak256Transcript::new(proof);
+        let strategy = verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<Bn256>, _, _, _>(
+            self.srs.verifier_params(),
+            self.pk.get_vk(),
+            SingleStrategy::new(&self.srs),
+            &[&[instances]],
+            &mut transcript,
+        )
+        .map_err(|e| anyhow::anyhow!("invalid proof: {:?}", e))?;
+        ensure!(
+            strategy.finalize(),
+            "invalid proof: the pairing check failed"
+        );
+        Ok(())
+    }
+
+    /// Renders `Halo2Verifier.sol` and `Halo2VerifyingKey.sol` for the loaded verifying key.
+    pub fn render_solidity(&self, num_instances: usize) -> anyhow::Result<(String, String)> {
+        let generator = SolidityGenerator::new(&self.srs, self.pk.get_vk(), Bdfg21, num_instances);
+        Ok(generator.render_separately()?)
     }
 }
 
-pub fn generate_proof_tuple_and_data() -> (
-    ProofTuple<F, Bn254PoseidonGoldilocksConfig, D>,
-    CircuitData<F, Bn254PoseidonGoldilocksConfig, D>,
-) {
-    type C = PoseidonGoldilocksConfig;
-    let spent_circuit = SpentCircuit::<F, C, D>::new();
-    let validity_circuit = ValidityCircuit::new(&spent_circuit);
-    let block_tree_circuit = BlockTreeCircuit::new();
-    let mut block_processor = BlockProcessor::<F, C, D>::new();
-
-    let block_info = block_processor
-        .generate_block(&spent_circuit, &[Transfer::default()], &Assets::default())
-        .unwrap();
-    block_processor
-        .tick(
+/// The circuits needed to build the wrap2 circuit.
+pub struct WrapCircuits {
+    pub spent_circuit: SpentCircuit<F, PoseidonGoldilocksConfig, D>,
+    pub validity_circuit: ValidityCircuit<F, PoseidonGoldilocksConfig, D>,
+    pub block_tree_circuit: BlockTreeCircuit<F, PoseidonGoldilocksConfig, D>,
+    pub settlement_processor: SettlementProcessor<F, PoseidonGoldilocksConfig, D>,
+    pub wrap_processor: WrapProcessor<F, PoseidonGoldilocksConfig, OuterC, D>,
+}
+
+impl WrapCircuits {
+    /// Builds the circuits without generating any proof.
+    pub fn new() -> anyhow::Result<Self> {
+        type C = PoseidonGoldilocksConfig;
+        let spent_circuit = SpentCircuit::<F, C, D>::new();
+        let validity_circuit = ValidityCircuit::new(&spent_circuit)?;
+        let block_tree_circuit = BlockTreeCircuit::new()?;
+        let settlement_processor = SettlementProcessor::new(&block_tree_circuit)?;
+        let inner_config = standard_inner_stark_verifier_config();
+        let outer_config = standard_stark_verifier_config();
+        let wrap_processor = WrapProcessor::<F, C, OuterC, D>::new(
+            inner_config,
+            outer_config,
+            &validity_circuit,
+            &block_tree_circuit,
+            &settlement_processor,
+        );
+        Ok(Self {
+            spent_circuit,
+            validity_circuit,
+            block_tree_circuit,
+            settlement_processor,
+            wrap_processor,
+        })
+    }
+
+    /// Proves a block and a settlement through the circuits and wraps them into a proof
+    /// of the wrap2 circuit. Returns the proof with the circuit data of wrap2.
+    pub fn prove_sample(
+        self,
+    ) -> anyhow::Result<(
+        ProofTuple<F, Bn254PoseidonGoldilocksConfig, D>,
+        CircuitData<F, Bn254PoseidonGoldilocksConfig, D>,
+    )> {
+        type C = PoseidonGoldilocksConfig;
+        let WrapCircuits {
+            spent_circuit,
+            validity_circuit,
+            block_tree_circuit,
+            mut settlement_processor,
+            wrap_processor,
+        } = self;
+        let mut block_processor = BlockProcessor::<F, C, D>::new();
+
+        let block_info = block_processor.generate_block(
+            &spent_circuit,
+            &[Transfer::default()],
+            &Assets::default(),
+        )?;
+        block_processor.tick(
             &validity_circuit,
             &block_tree_circuit,
             &block_info.spent_proof,
-        )
-        .unwrap();
-    let mut settlement_processor = SettlementProcessor::new(&block_tree_circuit);
-    let withdraw_proof = settlement_processor
-        .append_withdraw_proof(
+        )?;
+        let withdraw_proof = settlement_processor.append_withdraw_proof(
             &block_tree_circuit,
             &block_processor.block_tree,
             &block_processor.get_block_tree_proof().unwrap(),
             &block_info.transfer_info,
             &None,
-        )
-        .unwrap();
-    let evidence_transfer_info = block_info.transfer_info[0].clone();
+        )?;
+        let evidence_transfer_info = block_info.transfer_info[0].clone();
 
-    settlement_processor.initialize(&block_processor.get_block_tree_snapshot());
-    settlement_processor
-        .add(
+        settlement_processor.initialize(&block_processor.get_block_tree_snapshot());
+        settlement_processor.add(
             &block_processor.block_tree,
             &withdraw_proof,
             &evidence_transfer_info,
-        )
-        .unwrap();
-    let (settlement_proof, _) = settlement_processor.finalize().unwrap();
-    let inner_config = standard_inner_stark_verifier_config();
-    let outer_config = standard_stark_verifier_config();
-
-    let wrap_processor = WrapProcessor::<F, C, OuterC, D>::new(
-        inner_config,
-        outer_config,
-        &validity_circuit,
-        &block_tree_circuit,
-        &settlement_processor,
-    );
-    let (_pis, proof) = wrap_processor
-        .wrap(
+        )?;
+        let (settlement_proof, _) = settlement_processor
+            .finalize()
+            .context("the settlement has no withdraw proof")?;
+        let (_pis, proof) = wrap_processor.wrap(
             &validity_circuit,
             &block_tree_circuit,
             &settlement_processor,
             block_processor.get_validity_proof().unwrap(),
             block_processor.get_block_tree_proof().unwrap(),
             settlement_proof.proof,
-        )
-        .unwrap();
-    (
-        (
-            proof,
-            wrap_processor.wrap2_circuit.data.verifier_only.clone(),
-            wrap_processor.wrap2_circuit.data.common.clone(),
-        ),
-        wrap_processor.wrap2_circuit.data,
-    )
+        )?;
+        Ok((
+            (
+                proof,
+                wrap_processor.wrap2_circuit.data.verifier_only.clone(),
+                wrap_processor.wrap2_circuit.data.common.clone(),
+            ),
+            wrap_processor.wrap2_circuit.data,
+        ))
+    }
+}
+
+pub fn generate_proof_tuple_and_data() -> (
+    ProofTuple<F, Bn254PoseidonGoldilocksConfig, D>,
+    CircuitData<F, Bn254PoseidonGoldilocksConfig, D>,
+) {
+    WrapCircuits::new().unwrap().prove_sample().unwrap()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{generate_proof_tuple_and_data, SnarkProcessor};
+    use halo2_proofs::{arithmetic::Field, halo2curves::bn256::Fr};
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use std::path::Path;
+    use zkp::{
+        serialization::serialized_proof::CircuitKind, utils::h256::H256,
+        wrap_circuits::wrap::WrapPublicInputs,
+    };
+
+    use crate::config::DEFAULT_SRS_PATH;
+
+    use super::{
+        decode_wrap_public_inputs, generate_proof_tuple_and_data, parse_instances, SnarkProcessor,
+    };
 
     #[test]
     fn test_generate_proof_tuple_and_data() {
@@ -237,25 +514,119 @@ mod tests {
     }
 
     #[test]
-    fn test_snark_processor_setup_srs() {
-        SnarkProcessor::setup_srs();
+    fn test_snark_processor_setup_dev_srs() {
+        let path = std::env::temp_dir().join(format!("srs_{}.dat", rand::random::<u64>()));
+        SnarkProcessor::setup_dev_srs(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
     }
 
     #[test]
-    fn test_snark_processor_setup() {
-        SnarkProcessor::setup(generate_proof_tuple_and_data().0);
+    fn test_snark_processor_generate_solidity() {
+        let out_dir = std::env::temp_dir().join(format!("solidity_{}", rand::random::<u64>()));
+        SnarkProcessor::generate_solidity(
+            generate_proof_tuple_and_data().0,
+            Path::new(DEFAULT_SRS_PATH),
+            &out_dir,
+        )
+        .unwrap();
+        assert!(out_dir.join("Halo2Verifier.sol").exists());
+        assert!(out_dir.join("Halo2VerifyingKey.sol").exists());
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    // The committed contracts are generated from the wrap2 circuit with `srs.dat`, so they go
+    // stale whenever the circuit digest changes. After an intended change, regenerate them with
+    // `UPDATE_SOLIDITY=1 cargo test -r test_committed_solidity_verifier`.
+    #[test]
+    fn test_committed_solidity_verifier() {
+        let out_dir = std::env::temp_dir().join(format!("solidity_{}", rand::random::<u64>()));
+        SnarkProcessor::generate_solidity(
+            generate_proof_tuple_and_data().0,
+            Path::new(DEFAULT_SRS_PATH),
+            &out_dir,
+        )
+        .unwrap();
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        for file in ["Halo2Verifier.sol", "Halo2VerifyingKey.sol"] {
+            let generated = std::fs::read_to_string(out_dir.join(file)).unwrap();
+            if std::env::var("UPDATE_SOLIDITY").is_ok() {
+                std::fs::write(manifest_dir.join(file), &generated).unwrap();
+            }
+            let committed = std::fs::read_to_string(manifest_dir.join(file)).unwrap();
+            assert!(
+                generated == committed,
+                "{} does not match the wrap2 circuit, regenerate it with UPDATE_SOLIDITY=1",
+                file
+            );
+        }
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn test_snark_processor_save_and_load_keys() {
+        let (proof_tuple, data) = generate_proof_tuple_and_data();
+        let data = data.verifier_data();
+        let snark_processor =
+            SnarkProcessor::load(proof_tuple.clone(), Path::new(DEFAULT_SRS_PATH)).unwrap();
+        let dir = std::env::temp_dir().join(format!("halo2_keys_{}", rand::random::<u64>()));
+        snark_processor
+            .save_keys(&dir, CircuitKind::Wrap2, &data)
+            .unwrap();
+        let loaded =
+            SnarkProcessor::load_keys(&dir, CircuitKind::Wrap2, &data, Path::new(DEFAULT_SRS_PATH))
+                .unwrap();
+        assert_eq!(
+            loaded.pk.get_vk().transcript_repr(),
+            snark_processor.pk.get_vk().transcript_repr()
+        );
+        loaded.prove(proof_tuple.0, None).unwrap();
+        // the keys of a circuit are not loaded for another kind
+        assert!(SnarkProcessor::load_keys(
+            &dir,
+            CircuitKind::Withdraw,
+            &data,
+            Path::new(DEFAULT_SRS_PATH)
+        )
+        .is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_decode_wrap_public_inputs() {
+        let mut rng = rand::thread_rng();
+        let pis = WrapPublicInputs {
+            block_hash: H256::rand(&mut rng),
+            settlement_root: H256::rand(&mut rng),
+        };
+        let public_inputs = pis.to_solidity_pis::<GoldilocksField>().elements;
+        assert_eq!(
+            decode_wrap_public_inputs(&public_inputs).unwrap(),
+            pis.keccak_hash()
+        );
+        assert!(decode_wrap_public_inputs(&public_inputs[..3]).is_err());
     }
 
     #[test]
     fn test_snark_processor_prove() {
         let (proof_tuple, _) = generate_proof_tuple_and_data();
-        let snark_processor = SnarkProcessor::load(proof_tuple.clone());
+        let snark_processor =
+            SnarkProcessor::load(proof_tuple.clone(), Path::new(DEFAULT_SRS_PATH)).unwrap();
         println!("start proving");
         let now = std::time::Instant::now();
-        let res = snark_processor.prove(proof_tuple.0);
+        let res = snark_processor
+            .prove(proof_tuple.0, Some([0x11; 20]))
+            .unwrap();
         let elapsed_time = now.elapsed();
         println!("proof time {:?}", elapsed_time);
         println!("{}", res.proof);
         println!("{:?}", res.instance);
+        assert!(res.calldata.is_some());
+
+        let proof = hex::decode(&res.proof[2..]).unwrap();
+        let instances = parse_instances(&res.instance).unwrap();
+        snark_processor.verify(&proof, &instances).unwrap();
+        let mut wrong_instances = instances.clone();
+        wrong_instances[0] += Fr::ONE;
+        assert!(snark_processor.verify(&proof, &wrong_instances).is_err());
     }
 }
@@ -0,0 +1,360 @@
+use anyhow::{bail, ensure, Context};
+use halo2_proofs::{
+    arithmetic::{g_to_lagrange, CurveAffine, Field},
+    halo2curves::{
+        bn256::{Bn256, Fq, Fq2, Fr, G1Affine, G2Affine, G1},
+        group::{prime::PrimeCurveAffine, Curve, GroupEncoding},
+        pairing::Engine,
+    },
+    poly::{
+        commitment::{Blind, Params, ParamsProver},
+        kzg::commitment::ParamsKZG,
+        EvaluationDomain,
+    },
+    SerdeFormat,
+};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+/// The magic bytes of a snarkjs powers-of-tau file.
+const PTAU_MAGIC: [u8; 4] = *b"ptau";
+
+const PTAU_HEADER_SECTION: u32 = 1;
+const PTAU_TAU_G1_SECTION: u32 = 2;
+const PTAU_TAU_G2_SECTION: u32 = 3;
+
+/// The byte length of a base field element in a ptau file.
+const PTAU_N8: usize = 32;
+
+/// The modulus of the base field of bn254 in big-endian hex.
+const BN254_FQ_MODULUS: &str = "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47";
+
+lazy_static::lazy_static! {
+    // the inverse of the Montgomery factor 2^256
+    static ref MONTGOMERY_R_INV: Fq = Fq::from(2).pow_vartime([256]).invert().unwrap();
+}
+
+/// Reads KZG parameters of degree `k` from a ceremony transcript. The file is either a
+/// snarkjs `.ptau` file, or halo2 `ParamsKZG` of a larger degree such as the params derived
+/// from the perpetual powers of tau, which are downsized to `k`. The result is checked
+/// with `check_params`.
+pub fn import_params(path: &Path, k: u32) -> anyhow::Result<ParamsKZG<Bn256>> {
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?,
+    );
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    let params = if magic == PTAU_MAGIC {
+        read_ptau(&mut reader, k)?
+    } else {
+        // the magic bytes are the degree of halo2 params
+        let mut reader = (&magic[..]).chain(reader);
+        let mut params = ParamsKZG::<Bn256>::read_custom(&mut reader, SerdeFormat::RawBytes)
+            .context("the file is neither a ptau file nor halo2 params")?;
+        ensure!(
+            params.k() >= k,
+            "the params are of degree {}, but degree {} is needed",
+            params.k(),
+            k
+        );
+        if params.k() > k {
+            params.downsize(k);
+        }
+        params
+    };
+    check_params(&params, k)?;
+    Ok(params)
+}
+
+// Reads the powers of tau from a snarkjs ptau file whose magic bytes have been read.
+// The points are stored as affine coordinates in little-endian Montgomery form.
+fn read_ptau<R: Read>(reader: &mut R, k: u32) -> anyhow::Result<ParamsKZG<Bn256>> {
+    let _version = read_u32(reader)?;
+    let num_sections = read_u32(reader)?;
+    let mut power = None;
+    let mut g = None;
+    let mut g2_points = None;
+    for _ in 0..num_sections {
+        let section_type = read_u32(reader)?;
+        let section_size = read_u64(reader)?;
+        let mut section = reader.by_ref().take(section_size);
+        match section_type {
+            PTAU_HEADER_SECTION => {
+                let n8 = read_u32(&mut section)? as usize;
+                ensure!(
+                    n8 == PTAU_N8,
+                    "unsupported field size {} in the ptau file",
+                    n8
+                );
+                let mut modulus = vec![0u8; n8];
+                section.read_exact(&mut modulus)?;
+                modulus.reverse();
+                ensure!(
+                    hex::encode(&modulus) == BN254_FQ_MODULUS,
+                    "the ptau file is not over the bn254 curve"
+                );
+                let ptau_power = read_u32(&mut section)?;
+                ensure!(
+                    ptau_power >= k,
+                    "the ptau file has 2^{} powers, but 2^{} are needed",
+                    ptau_power,
+                    k
+                );
+                power = Some(ptau_power);
+            }
+            PTAU_TAU_G1_SECTION => {
+                ensure!(power.is_some(), "the ptau header must precede the points");
+                let mut points = Vec::with_capacity(1 << k);
+                let mut buf = [0u8; 2 * PTAU_N8];
+                for _ in 0..1 << k {
+                    section.read_exact(&mut buf)?;
+                    points.push(read_g1(&buf)?);
+                }
+                g = Some(points);
+            }
+            PTAU_TAU_G2_SECTION => {
+                ensure!(power.is_some(), "the ptau header must precede the points");
+                let mut buf = [0u8; 4 * PTAU_N8];
+                section.read_exact(&mut buf)?;
+                let g2 = read_g2(&buf)?;
+                section.read_exact(&mut buf)?;
+                let s_g2 = read_g2(&buf)?;
+                g2_points = Some((g2, s_g2));
+            }
+            _ => {}
+        }
+        // skip the rest of the section
+        std::io::copy(&mut section, &mut std::io::sink())?;
+    }
+    let (Some(g), Some((g2, s_g2))) = (g, g2_points) else {
+        bail!("the ptau file has no tau points");
+    };
+
+    // write the points in the halo2 format with the lagrange basis computed from them
+    let g_lagrange: Vec<G1Affine> =
+        g_to_lagrange(g.iter().map(|p| p.to_curve()).collect::<Vec<G1>>(), k);
+    let mut bytes = k.to_le_bytes().to_vec();
+    for point in g.iter().chain(g_lagrange.iter()) {
+        bytes.extend_from_slice(point.to_bytes().as_ref());
+    }
+    bytes.extend_from_slice(g2.to_bytes().as_ref());
+    bytes.extend_from_slice(s_g2.to_bytes().as_ref());
+    Ok(ParamsKZG::<Bn256>::read_custom(
+        &mut bytes.as_slice(),
+        SerdeFormat::Processed,
+    )?)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> anyhow::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> anyhow::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+// Reads a little-endian field element in Montgomery form.
+fn read_fq(bytes: &[u8]) -> Fq {
+    let limbs: [u64; 4] =
+        std::array::from_fn(|i| u64::from_le_bytes(bytes[8 * i..8 * (i + 1)].try_into().unwrap()));
+    Fq::from_raw(limbs) * *MONTGOMERY_R_INV
+}
+
+fn read_g1(bytes: &[u8]) -> anyhow::Result<G1Affine> {
+    let x = read_fq(&bytes[..PTAU_N8]);
+    let y = read_fq(&bytes[PTAU_N8..]);
+    Option::from(G1Affine::from_xy(x, y)).context("a G1 point of the ptau file is not on the curve")
+}
+
+fn read_g2(bytes: &[u8]) -> anyhow::Result<G2Affine> {
+    let x = Fq2 {
+        c0: read_fq(&bytes[..PTAU_N8]),
+        c1: read_fq(&bytes[PTAU_N8..2 * PTAU_N8]),
+    };
+    let y = Fq2 {
+        c0: read_fq(&bytes[2 * PTAU_N8..3 * PTAU_N8]),
+        c1: read_fq(&bytes[3 * PTAU_N8..]),
+    };
+    Option::from(G2Affine::from_xy(x, y)).context("a G2 point of the ptau file is not on the curve")
+}
+
+/// Checks that the params are powers of a single tau of degree `k`: the points start at the
+/// generators, a random combination of `g[i + 1]` and `g[i]` satisfies
+/// `e(sum r_i g[i + 1], g2) = e(sum r_i g[i], s_g2)`, and the lagrange basis commits
+/// a random polynomial to the same point as the monomial basis.
+pub fn check_params(params: &ParamsKZG<Bn256>, k: u32) -> anyhow::Result<()> {
+    ensure!(
+        params.k() == k,
+        "the params are of degree {}, expected {}",
+        params.k(),
+        k
+    );
+    ensure!(
+        params.get_g()[0] == G1Affine::generator(),
+        "the first G1 point is not the generator"
+    );
+    ensure!(
+        params.g2() == G2Affine::generator(),
+        "the G2 point is not the generator"
+    );
+
+    let mut rng = rand::rngs::OsRng;
+    let domain = EvaluationDomain::<Fr>::new(1, k);
+    let n = params.n() as usize;
+    let mut poly = domain.empty_coeff();
+    let mut shifted_poly = domain.empty_coeff();
+    for i in 0..n - 1 {
+        let r = Fr::random(&mut rng);
+        poly[i] = r;
+        shifted_poly[i + 1] = r;
+    }
+    let commitment = params.commit(&poly, Blind::default()).to_affine();
+    let shifted_commitment = params.commit(&shifted_poly, Blind::default()).to_affine();
+    ensure!(
+        Bn256::pairing(&shifted_commitment, &params.g2())
+            == Bn256::pairing(&commitment, &params.s_g2()),
+        "the G1 points are not consecutive powers of tau"
+    );
+
+    let mut evals = domain.empty_lagrange();
+    for eval in evals.iter_mut() {
+        *eval = Fr::random(&mut rng);
+    }
+    let coeffs = domain.lagrange_to_coeff(evals.clone());
+    ensure!(
+        params.commit_lagrange(&evals, Blind::default())
+            == params.commit(&coeffs, Blind::default()),
+        "the lagrange basis does not match the powers of tau"
+    );
+    Ok(())
+}
+
+/// Generates params from a random tau. Whoever runs it can learn tau, so the params must
+/// only be used for development.
+pub fn setup_dev_params(k: u32) -> ParamsKZG<Bn256> {
+    let mut rng = rand::rngs::OsRng;
+    ParamsKZG::<Bn256>::setup(k, &mut rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        arithmetic::{CurveAffine, Field},
+        halo2curves::{
+            bn256::{Bn256, Fq, Fr, G1Affine, G2Affine},
+            group::{ff::PrimeField, prime::PrimeCurveAffine, Curve},
+        },
+        poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{
+        check_params, import_params, setup_dev_params, BN254_FQ_MODULUS, MONTGOMERY_R_INV,
+        PTAU_HEADER_SECTION, PTAU_MAGIC, PTAU_N8, PTAU_TAU_G1_SECTION, PTAU_TAU_G2_SECTION,
+    };
+
+    // little-endian Montgomery form, as in ptau files
+    fn write_fq(bytes: &mut Vec<u8>, x: Fq) {
+        let montgomery = x * MONTGOMERY_R_INV.invert().unwrap();
+        bytes.extend_from_slice(montgomery.to_repr().as_ref());
+    }
+
+    fn write_section(bytes: &mut Vec<u8>, section_type: u32, section: &[u8]) {
+        bytes.extend_from_slice(&section_type.to_le_bytes());
+        bytes.extend_from_slice(&(section.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(section);
+    }
+
+    // A ptau file of 2^power powers of tau with the layout written by snarkjs.
+    fn synthetic_ptau(tau: Fr, power: u32) -> Vec<u8> {
+        let mut header = (PTAU_N8 as u32).to_le_bytes().to_vec();
+        let mut modulus = hex::decode(BN254_FQ_MODULUS).unwrap();
+        modulus.reverse();
+        header.extend_from_slice(&modulus);
+        header.extend_from_slice(&power.to_le_bytes());
+        // the power of the ceremony
+        header.extend_from_slice(&power.to_le_bytes());
+
+        let mut tau_g1 = vec![];
+        let mut tau_i = Fr::ONE;
+        for _ in 0..(2 << power) - 1 {
+            let point = (G1Affine::generator() * tau_i).to_affine();
+            let coordinates = point.coordinates().unwrap();
+            write_fq(&mut tau_g1, *coordinates.x());
+            write_fq(&mut tau_g1, *coordinates.y());
+            tau_i *= tau;
+        }
+        let mut tau_g2 = vec![];
+        for tau_i in [Fr::ONE, tau] {
+            let point = (G2Affine::generator() * tau_i).to_affine();
+            let coordinates = point.coordinates().unwrap();
+            for c in [coordinates.x(), coordinates.y()] {
+                write_fq(&mut tau_g2, c.c0);
+                write_fq(&mut tau_g2, c.c1);
+            }
+        }
+
+        let mut bytes = PTAU_MAGIC.to_vec();
+        // version and number of sections
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        write_section(&mut bytes, PTAU_HEADER_SECTION, &header);
+        write_section(&mut bytes, PTAU_TAU_G1_SECTION, &tau_g1);
+        write_section(&mut bytes, PTAU_TAU_G2_SECTION, &tau_g2);
+        // an unused section, e.g. alpha tau G1
+        write_section(&mut bytes, 4, &[0u8; 64]);
+        bytes
+    }
+
+    fn params_bytes(params: &ParamsKZG<Bn256>) -> Vec<u8> {
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_import_params() {
+        let params = setup_dev_params(6);
+        check_params(&params, 6).unwrap();
+        assert!(check_params(&params, 5).is_err());
+
+        let path = std::env::temp_dir().join(format!("params_{}.dat", rand::random::<u64>()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        params.write(&mut file).unwrap();
+        let imported = import_params(&path, 4).unwrap();
+        assert_eq!(imported.k(), 4);
+        assert!(import_params(&path, 7).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_import_ptau() {
+        let k = 4;
+        let rng = StdRng::seed_from_u64(42);
+        // `setup` samples tau as the first random value
+        let tau = Fr::random(rng.clone());
+        let expected = ParamsKZG::<Bn256>::setup(k, rng);
+
+        let path = std::env::temp_dir().join(format!("params_{}.ptau", rand::random::<u64>()));
+        std::fs::write(&path, synthetic_ptau(tau, k + 1)).unwrap();
+        let imported = import_params(&path, k).unwrap();
+        assert_eq!(params_bytes(&imported), params_bytes(&expected));
+        assert!(import_params(&path, k + 2).is_err());
+
+        // a point off the curve
+        let mut ptau = synthetic_ptau(tau, k);
+        // after the file header, the header section and the header of the G1 section
+        let first_point = 12 + (12 + 4 + PTAU_N8 + 8) + 12;
+        ptau[first_point] ^= 1;
+        std::fs::write(&path, ptau).unwrap();
+        assert!(import_params(&path, k).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
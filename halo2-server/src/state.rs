@@ -1,174 +1,189 @@
-use actix::prelude::*;
-use stark_verifier::bn254_poseidon::plonky2_config::Bn254PoseidonGoldilocksConfig;
-use std::{
-    collections::HashMap,
-    sync::RwLock,
-    time::{Duration, Instant},
-};
+use halo2_proofs::halo2curves::bn256::Fr;
 use zkp::serialization::serialized_proof::SerializedProof;
 
-#[cfg(not(feature = "debug"))]
-use plonky2::plonk::{circuit_data::CircuitData, config::GenericConfig};
-
-#[cfg(not(feature = "debug"))]
-use crate::snark_processor::{generate_proof_tuple_and_data, SnarkProcessor};
+use crate::{config::Config, snark_processor::ProofResult};
 
 #[cfg(not(feature = "debug"))]
-const D: usize = 2;
+use anyhow::Context;
 #[cfg(not(feature = "debug"))]
-type C = Bn254PoseidonGoldilocksConfig;
+use std::collections::HashMap;
+
 #[cfg(not(feature = "debug"))]
-type F = <C as GenericConfig<D>>::F;
+use crate::{
+    circuits::{load_circuit_specs, CircuitSpec},
+    snark_processor::SnarkProcessor,
+};
 
-/// The time-to-live for a proof in seconds
-pub const PROOF_TTL: u64 = 60 * 60;
+#[cfg(feature = "debug")]
+use crate::circuits::{registered_circuit_ids, DEFAULT_CIRCUIT_ID};
+#[cfg(feature = "debug")]
+use zkp::serialization::serialized_proof::CircuitKind;
+
+#[cfg(not(feature = "debug"))]
+struct LoadedCircuit {
+    spec: CircuitSpec,
+    snark_processor: SnarkProcessor,
+}
 
 pub struct SnarkState {
     #[cfg(not(feature = "debug"))]
-    data: CircuitData<F, C, D>,
-    #[cfg(not(feature = "debug"))]
-    snark_processor: SnarkProcessor,
+    circuits: HashMap<String, LoadedCircuit>,
+    #[cfg(feature = "debug")]
+    circuit_ids: Vec<String>,
+}
+
+/// Loads the proving key of the circuit saved in its key directory. If it is missing or was
+/// generated for another version of the circuit, generates it from the sample proof and
+/// saves it.
+#[cfg(not(feature = "debug"))]
+fn load_or_generate_keys(
+    spec: &mut CircuitSpec,
+    config: &Config,
+) -> anyhow::Result<SnarkProcessor> {
+    let key_dir = spec.key_dir(config);
+    match SnarkProcessor::load_keys(&key_dir, spec.kind, &spec.data, &config.srs_path) {
+        Ok(snark_processor) => {
+            log::info!(
+                "Loaded the proving key of the {} circuit from {}",
+                spec.id,
+                key_dir.display()
+            );
+            Ok(snark_processor)
+        }
+        Err(e) => {
+            log::warn!(
+                "Failed to load the proving key of the {} circuit: {}. Generating it...",
+                spec.id,
+                e
+            );
+            let snark_processor =
+                SnarkProcessor::load(spec.sample_proof_tuple()?, &config.srs_path)
+                    .context("failed to generate the proving key")?;
+            snark_processor
+                .save_keys(&key_dir, spec.kind, &spec.data)
+                .context("failed to save the proving key")?;
+            log::info!(
+                "Saved the proving key of the {} circuit to {}",
+                spec.id,
+                key_dir.display()
+            );
+            Ok(snark_processor)
+        }
+    }
 }
 
 impl SnarkState {
+    /// Loads the wrap2 circuit and the registered circuits with their proving keys.
     #[cfg(not(feature = "debug"))]
-    pub fn new() -> Self {
-        let (dummy_proof_tuple, data) = generate_proof_tuple_and_data();
-        let snark_processor = SnarkProcessor::load(dummy_proof_tuple.clone());
-        Self {
-            data,
-            snark_processor,
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut circuits = HashMap::new();
+        for mut spec in load_circuit_specs(config)? {
+            let snark_processor = load_or_generate_keys(&mut spec, config)?;
+            spec.release_circuits();
+            circuits.insert(
+                spec.id.clone(),
+                LoadedCircuit {
+                    spec,
+                    snark_processor,
+                },
+            );
         }
+        Ok(Self { circuits })
     }
 
     #[cfg(feature = "debug")]
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut circuit_ids = vec![DEFAULT_CIRCUIT_ID.to_string()];
+        circuit_ids.extend(registered_circuit_ids(&config.circuits_dir)?);
+        Ok(Self { circuit_ids })
     }
 
     #[cfg(not(feature = "debug"))]
-    pub fn prove(&self, proof: SerializedProof) -> String {
-        let proof = proof.to_proof(&self.data).unwrap();
-        self.snark_processor.prove(proof).proof
+    pub fn circuit_ids(&self) -> Vec<String> {
+        let mut ids = self.circuits.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids
     }
 
     #[cfg(feature = "debug")]
-    pub fn prove(&self, _proof: SerializedProof) -> String {
-        log::debug!("Waiting for 1 minutes...");
-
-        let two_minutes = Duration::from_secs(60);
-        std::thread::sleep(two_minutes);
-
-        "0xaaaa".to_string()
+    pub fn circuit_ids(&self) -> Vec<String> {
+        self.circuit_ids.clone()
     }
-}
 
-/// An actor that holds state
-pub struct StateActor {
-    states: HashMap<Vec<u8>, RwLock<(Option<String>, Instant)>>,
-}
-
-impl StateActor {
-    pub fn new() -> Self {
-        Self {
-            states: HashMap::new(),
-        }
+    pub fn has_circuit(&self, circuit_id: &str) -> bool {
+        self.circuit_ids().iter().any(|id| id == circuit_id)
     }
-}
-
-/// Messages sent to the actor
-pub struct SetProof {
-    pub key: Vec<u8>,
-    pub value: String,
-}
-
-impl Message for SetProof {
-    type Result = ();
-}
 
-impl Handler<SetProof> for StateActor {
-    type Result = ();
-
-    fn handle(&mut self, msg: SetProof, _: &mut Context<Self>) {
-        let expire_in = PROOF_TTL;
-        self.states.insert(
-            msg.key,
-            RwLock::new((
-                Some(msg.value),
-                Instant::now() + Duration::from_secs(expire_in),
-            )),
-        );
+    #[cfg(not(feature = "debug"))]
+    fn circuit(&self, circuit_id: &str) -> anyhow::Result<&LoadedCircuit> {
+        self.circuits
+            .get(circuit_id)
+            .with_context(|| format!("unknown circuit {}", circuit_id))
     }
-}
-
-pub struct SetPendingJob {
-    pub key: Vec<u8>,
-}
-
-impl Message for SetPendingJob {
-    type Result = ();
-}
 
-impl Handler<SetPendingJob> for StateActor {
-    type Result = ();
+    /// Checks that the proof is a valid proof of the circuit, so that no proving run is
+    /// started for a proof which cannot be proven.
+    #[cfg(not(feature = "debug"))]
+    pub fn validate(&self, circuit_id: &str, proof: &SerializedProof) -> anyhow::Result<()> {
+        self.circuit(circuit_id)?.spec.decode(proof)?;
+        Ok(())
+    }
 
-    fn handle(&mut self, msg: SetPendingJob, _: &mut Context<Self>) {
-        let expire_in = PROOF_TTL;
-        self.states.insert(
-            msg.key,
-            RwLock::new((None, Instant::now() + Duration::from_secs(expire_in))),
+    #[cfg(feature = "debug")]
+    pub fn validate(&self, circuit_id: &str, proof: &SerializedProof) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.has_circuit(circuit_id),
+            "unknown circuit {}",
+            circuit_id
+        );
+        let kind = proof.header()?.kind;
+        anyhow::ensure!(
+            circuit_id != DEFAULT_CIRCUIT_ID || kind == CircuitKind::Wrap2,
+            "circuit kind mismatch: expected a {} proof, got a {} proof",
+            CircuitKind::Wrap2,
+            kind
         );
+        Ok(())
     }
-}
-
-pub struct GetProof {
-    pub key: Vec<u8>,
-}
 
-impl Message for GetProof {
-    type Result = Option<(Option<String>, Instant)>;
-}
-
-impl Handler<GetProof> for StateActor {
-    type Result = Option<(Option<String>, Instant)>;
-
-    fn handle(&mut self, msg: GetProof, _: &mut Context<Self>) -> Self::Result {
-        self.states
-            .get(&msg.key)
-            .map(|inner| inner.read().unwrap().clone())
+    #[cfg(not(feature = "debug"))]
+    pub fn prove(&self, circuit_id: &str, proof: SerializedProof) -> anyhow::Result<ProofResult> {
+        let circuit = self.circuit(circuit_id)?;
+        circuit.spec.prove(&circuit.snark_processor, &proof)
     }
-}
 
-struct CheckExpire;
-
-impl Message for CheckExpire {
-    type Result = ();
-}
-
-impl Handler<CheckExpire> for StateActor {
-    type Result = ();
+    #[cfg(not(feature = "debug"))]
+    pub fn verify(&self, circuit_id: &str, proof: &[u8], instances: &[Fr]) -> anyhow::Result<()> {
+        self.circuit(circuit_id)?
+            .snark_processor
+            .verify(proof, instances)
+    }
 
-    fn handle(&mut self, _msg: CheckExpire, _ctx: &mut Context<Self>) {
-        let now = Instant::now();
-        // Remove expired states
-        self.states.retain(|_, inner| {
-            let expiry = inner.read().unwrap().1;
-            expiry > now
-        });
+    // only the dummy proof is valid in debug mode
+    #[cfg(feature = "debug")]
+    pub fn verify(&self, circuit_id: &str, proof: &[u8], _instances: &[Fr]) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.has_circuit(circuit_id),
+            "unknown circuit {}",
+            circuit_id
+        );
+        anyhow::ensure!(proof == [0xaa, 0xaa], "invalid proof");
+        Ok(())
     }
-}
 
-impl Actor for StateActor {
-    type Context = Context<Self>;
+    #[cfg(feature = "debug")]
+    pub fn prove(&self, circuit_id: &str, proof: SerializedProof) -> anyhow::Result<ProofResult> {
+        self.validate(circuit_id, &proof)?;
+        log::debug!("Waiting for 1 minutes...");
 
-    // Schedule regular expiration checks when the actor starts
-    fn started(&mut self, ctx: &mut Self::Context) {
-        self.states.clear();
+        let two_minutes = std::time::Duration::from_secs(60);
+        std::thread::sleep(two_minutes);
 
-        // Check every PROOF_TTL seconds
-        let interval = PROOF_TTL;
-        ctx.run_interval(Duration::from_secs(interval), |_actor, ctx| {
-            ctx.address().do_send(CheckExpire);
-        });
+        Ok(ProofResult {
+            proof: "0xaaaa".to_string(),
+            instance: vec![],
+            public_inputs_hash: Default::default(),
+            calldata: None,
+        })
     }
 }
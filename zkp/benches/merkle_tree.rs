@@ -0,0 +1,82 @@
+#![feature(test)]
+
+extern crate test;
+
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Sample},
+    hash::hash_types::HashOut,
+};
+use test::Bencher;
+use zkp::utils::trees::{
+    frontier_merkle_tree::FrontierMerkleTree, merkle_tree_with_leaves::MerkleTreeWithLeaves,
+    sparse_merkle_tree_with_leaves::SparseMerkleTreeWithLeaves,
+};
+
+type F = GoldilocksField;
+
+// The same height as the block tree.
+const HEIGHT: usize = 32;
+const NUM_LEAVES: usize = 1 << 10;
+
+fn random_leaves() -> Vec<HashOut<F>> {
+    (0..NUM_LEAVES).map(|_| HashOut::rand()).collect()
+}
+
+#[bench]
+fn bench_merkle_tree_with_leaves_push(b: &mut Bencher) {
+    let leaves = random_leaves();
+    b.iter(|| {
+        let mut tree = MerkleTreeWithLeaves::<F, HashOut<F>>::new(HEIGHT);
+        for leaf in leaves.iter() {
+            tree.push(*leaf);
+        }
+        tree.get_root()
+    });
+}
+
+#[bench]
+fn bench_merkle_tree_with_leaves_prove(b: &mut Bencher) {
+    let mut tree = MerkleTreeWithLeaves::<F, HashOut<F>>::new(HEIGHT);
+    for leaf in random_leaves() {
+        tree.push(leaf);
+    }
+    b.iter(|| {
+        for index in 0..NUM_LEAVES {
+            test::black_box(tree.prove(index));
+        }
+    });
+}
+
+#[bench]
+fn bench_merkle_tree_with_leaves_clone(b: &mut Bencher) {
+    let mut tree = MerkleTreeWithLeaves::<F, HashOut<F>>::new(HEIGHT);
+    for leaf in random_leaves() {
+        tree.push(leaf);
+    }
+    b.iter(|| tree.clone());
+}
+
+#[bench]
+fn bench_frontier_merkle_tree_push(b: &mut Bencher) {
+    let leaves = random_leaves();
+    b.iter(|| {
+        let mut tree = FrontierMerkleTree::<F, HashOut<F>>::new(HEIGHT);
+        for leaf in leaves.iter() {
+            tree.push(*leaf);
+        }
+        tree.prove_latest()
+    });
+}
+
+// The hash map backed tree which `MerkleTreeWithLeaves` used to be built on.
+#[bench]
+fn bench_sparse_merkle_tree_push(b: &mut Bencher) {
+    let leaves = random_leaves();
+    b.iter(|| {
+        let mut tree = SparseMerkleTreeWithLeaves::<F, u32, HashOut<F>>::new(HEIGHT);
+        for (index, leaf) in leaves.iter().enumerate() {
+            tree.update(index as u32, *leaf);
+        }
+        tree.get_root()
+    });
+}
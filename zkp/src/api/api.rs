@@ -1,30 +1,54 @@
 use actix_web::{
-    get, post,
-    web::{self, Data, Json},
-    HttpResponse, Responder,
+    web::{self, Bytes, Data, PayloadConfig},
+    HttpRequest, HttpResponse, Responder,
 };
 use log::error;
 
-use crate::api::io::{AddInput, AppendToProofInput};
+use crate::api::io::{AddInput, AppendToProofInput, VerifyProofInput, VerifyWrapInput};
 
 use crate::api::io::{SerializedBlockStatus, SyncBlockTreeInput, TickInput};
 
-use super::{io::GenerateBlockInput, state::ServerState};
+use super::{
+    encoding::{decode_body, respond},
+    io::GenerateBlockInput,
+    state::ServerState,
+};
+
+const MB: usize = 1 << 20;
 
-#[get("/get-status")]
-pub async fn get_status(data: Data<ServerState>) -> impl Responder {
+// Request body limits. The JSON encoding doubles the size of proofs, so the limits are set
+// for JSON bodies. Endpoints without an input do not read the body.
+const GENERATE_BLOCK_BODY_LIMIT: usize = 4 * MB;
+const TICK_BODY_LIMIT: usize = 4 * MB;
+const SYNC_BLOCK_TREE_BODY_LIMIT: usize = 16 * MB;
+const RESTORE_BODY_LIMIT: usize = 8 * MB;
+const APPEND_TO_WITHDRAW_PROOF_BODY_LIMIT: usize = 32 * MB;
+const ADD_BODY_LIMIT: usize = 8 * MB;
+const VERIFY_BODY_LIMIT: usize = 8 * MB;
+const NO_BODY_LIMIT: usize = 0;
+
+fn bad_request(endpoint: &str, e: anyhow::Error) -> HttpResponse {
+    error!("{} error: {}", endpoint, e.to_string());
+    HttpResponse::BadRequest().body(e.to_string())
+}
+
+pub async fn get_status(data: Data<ServerState>, req: HttpRequest) -> impl Responder {
     let status = data.get_status();
-    HttpResponse::Ok().json(status)
+    respond(&req, &status)
 }
 
-#[post("/generate-block")]
 pub async fn generate_block(
     data: Data<ServerState>,
-    req: Json<GenerateBlockInput>,
+    req: HttpRequest,
+    body: Bytes,
 ) -> impl Responder {
-    let res = data.generate_block(req.into_inner());
+    let input: GenerateBlockInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("generate-block", e),
+    };
+    let res = data.generate_block(input);
     match res {
-        Ok(block_info) => HttpResponse::Ok().json(block_info),
+        Ok(block_info) => respond(&req, &block_info),
         Err(e) => {
             error!("generate-block error: {}", e.to_string());
             HttpResponse::InternalServerError().body(e.to_string())
@@ -32,11 +56,14 @@ pub async fn generate_block(
     }
 }
 
-#[post("/tick")]
-pub async fn tick(data: Data<ServerState>, req: Json<TickInput>) -> impl Responder {
-    let res = data.tick(req.into_inner());
+pub async fn tick(data: Data<ServerState>, req: HttpRequest, body: Bytes) -> impl Responder {
+    let input: TickInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("tick", e),
+    };
+    let res = data.tick(input);
     match res {
-        Ok(block_status) => HttpResponse::Ok().json(block_status),
+        Ok(block_status) => respond(&req, &block_status),
         Err(e) => {
             error!("tick error: {}", e.to_string());
             HttpResponse::InternalServerError().body(e.to_string())
@@ -44,26 +71,28 @@ pub async fn tick(data: Data<ServerState>, req: Json<TickInput>) -> impl Respond
     }
 }
 
-#[post("/reset-block-tree")]
-pub async fn reset_block_tree(data: Data<ServerState>) -> impl Responder {
+pub async fn reset_block_tree(data: Data<ServerState>, req: HttpRequest) -> impl Responder {
     data.reset_block_tree();
-    HttpResponse::Ok().json("reseted block tree")
+    respond(&req, &"reseted block tree")
 }
 
-#[post("/reset")]
-pub async fn reset(data: Data<ServerState>) -> impl Responder {
+pub async fn reset(data: Data<ServerState>, req: HttpRequest) -> impl Responder {
     data.reset();
-    HttpResponse::Ok().json("reseted")
+    respond(&req, &"reseted")
 }
 
-#[post("/sync-block-tree")]
 pub async fn sync_block_tree(
     data: Data<ServerState>,
-    req: Json<SyncBlockTreeInput>,
+    req: HttpRequest,
+    body: Bytes,
 ) -> impl Responder {
-    let res = data.sync_block_tree(req.into_inner());
+    let input: SyncBlockTreeInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("sync-block-tree", e),
+    };
+    let res = data.sync_block_tree(input);
     match res {
-        Ok(()) => HttpResponse::Ok().json("synced block tree"),
+        Ok(()) => respond(&req, &"synced block tree"),
         Err(e) => {
             error!("sync-block-tree error: {}", e.to_string());
             HttpResponse::InternalServerError().body(e.to_string())
@@ -71,23 +100,27 @@ pub async fn sync_block_tree(
     }
 }
 
-#[get("/get-block-tree-status")]
-pub async fn get_block_tree_status(data: Data<ServerState>) -> impl Responder {
+pub async fn get_block_tree_status(data: Data<ServerState>, req: HttpRequest) -> impl Responder {
     let block_tree_status = data.get_block_tree_status();
-    HttpResponse::Ok().json(block_tree_status)
+    respond(&req, &block_tree_status)
 }
 
-#[get("/get-snapshot-block-number")]
-pub async fn get_snapshot_block_number(data: Data<ServerState>) -> impl Responder {
+pub async fn get_snapshot_block_number(
+    data: Data<ServerState>,
+    req: HttpRequest,
+) -> impl Responder {
     let snapshot_block_number = data.get_snapshot_block_number();
-    HttpResponse::Ok().json(snapshot_block_number)
+    respond(&req, &snapshot_block_number)
 }
 
-#[post("/restore")]
-pub async fn restore(data: Data<ServerState>, req: Json<SerializedBlockStatus>) -> impl Responder {
-    let res = data.restore(req.into_inner());
+pub async fn restore(data: Data<ServerState>, req: HttpRequest, body: Bytes) -> impl Responder {
+    let input: SerializedBlockStatus = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("restore", e),
+    };
+    let res = data.restore(input);
     match res {
-        Ok(()) => HttpResponse::Ok().json("restored"),
+        Ok(()) => respond(&req, &"restored"),
         Err(e) => {
             error!("restore error: {}", e.to_string());
             HttpResponse::InternalServerError().body(e.to_string())
@@ -95,14 +128,18 @@ pub async fn restore(data: Data<ServerState>, req: Json<SerializedBlockStatus>)
     }
 }
 
-#[post("/append-to-withdraw-proof")]
 pub async fn append_to_withdraw_proof(
     data: Data<ServerState>,
-    req: Json<AppendToProofInput>,
+    req: HttpRequest,
+    body: Bytes,
 ) -> impl Responder {
-    let res = data.append_to_withdraw_proof(req.into_inner().into());
+    let input: AppendToProofInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("append-to-withdraw-proof", e),
+    };
+    let res = data.append_to_withdraw_proof(input);
     match res {
-        Ok(output) => HttpResponse::Ok().json(output),
+        Ok(output) => respond(&req, &output),
         Err(e) => {
             error!("append-to-withdraw-proof error: {}", e.to_string());
             HttpResponse::InternalServerError().body(e.to_string())
@@ -110,17 +147,19 @@ pub async fn append_to_withdraw_proof(
     }
 }
 
-#[post("/initialize")]
-pub async fn initialize(data: Data<ServerState>) -> impl Responder {
+pub async fn initialize(data: Data<ServerState>, req: HttpRequest) -> impl Responder {
     let snapshot_block_number = data.initialize();
-    HttpResponse::Ok().json(snapshot_block_number)
+    respond(&req, &snapshot_block_number)
 }
 
-#[post("/add")]
-pub async fn add(data: Data<ServerState>, req: Json<AddInput>) -> impl Responder {
-    let res = data.add(req.into_inner());
+pub async fn add(data: Data<ServerState>, req: HttpRequest, body: Bytes) -> impl Responder {
+    let input: AddInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("add", e),
+    };
+    let res = data.add(input);
     match res {
-        Ok(_) => HttpResponse::Ok().json("added"),
+        Ok(_) => respond(&req, &"added"),
         Err(e) => {
             error!("add error: {}", e.to_string());
             HttpResponse::InternalServerError().body(e.to_string())
@@ -128,11 +167,10 @@ pub async fn add(data: Data<ServerState>, req: Json<AddInput>) -> impl Responder
     }
 }
 
-#[post("/finalize-and-wrap")]
-pub async fn finalize_and_wrap(data: Data<ServerState>) -> impl Responder {
+pub async fn finalize_and_wrap(data: Data<ServerState>, req: HttpRequest) -> impl Responder {
     let res = data.finalize_and_wrap();
     match res {
-        Ok(finalize_output) => HttpResponse::Ok().json(finalize_output),
+        Ok(finalize_output) => respond(&req, &finalize_output),
         Err(e) => {
             error!("finalize-and-wrap error: {}", e.to_string());
             HttpResponse::InternalServerError().body(e.to_string())
@@ -140,28 +178,172 @@ pub async fn finalize_and_wrap(data: Data<ServerState>) -> impl Responder {
     }
 }
 
-#[get("/health")]
+// Invalid proofs are rejected as bad requests, because verification does not change the state.
+pub async fn verify_spent(
+    data: Data<ServerState>,
+    req: HttpRequest,
+    body: Bytes,
+) -> impl Responder {
+    let input: VerifyProofInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("verify-spent", e),
+    };
+    match data.verify_spent(input) {
+        Ok(output) => respond(&req, &output),
+        Err(e) => bad_request("verify-spent", e),
+    }
+}
+
+pub async fn verify_validity(
+    data: Data<ServerState>,
+    req: HttpRequest,
+    body: Bytes,
+) -> impl Responder {
+    let input: VerifyProofInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("verify-validity", e),
+    };
+    match data.verify_validity(input) {
+        Ok(output) => respond(&req, &output),
+        Err(e) => bad_request("verify-validity", e),
+    }
+}
+
+pub async fn verify_block_tree(
+    data: Data<ServerState>,
+    req: HttpRequest,
+    body: Bytes,
+) -> impl Responder {
+    let input: VerifyProofInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("verify-block-tree", e),
+    };
+    match data.verify_block_tree(input) {
+        Ok(output) => respond(&req, &output),
+        Err(e) => bad_request("verify-block-tree", e),
+    }
+}
+
+pub async fn verify_withdraw(
+    data: Data<ServerState>,
+    req: HttpRequest,
+    body: Bytes,
+) -> impl Responder {
+    let input: VerifyProofInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("verify-withdraw", e),
+    };
+    match data.verify_withdraw(input) {
+        Ok(output) => respond(&req, &output),
+        Err(e) => bad_request("verify-withdraw", e),
+    }
+}
+
+pub async fn verify_wrap(data: Data<ServerState>, req: HttpRequest, body: Bytes) -> impl Responder {
+    let input: VerifyWrapInput = match decode_body(&req, &body) {
+        Ok(input) => input,
+        Err(e) => return bad_request("verify-wrap", e),
+    };
+    match data.verify_wrap(input) {
+        Ok(output) => respond(&req, &output),
+        Err(e) => bad_request("verify-wrap", e),
+    }
+}
+
 pub async fn health() -> impl Responder {
     HttpResponse::Ok().body("OK!")
 }
 
+fn get_resource<F, Args>(path: &str, handler: F) -> actix_web::Resource
+where
+    F: actix_web::Handler<Args>,
+    Args: actix_web::FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    web::resource(path)
+        .app_data(PayloadConfig::new(NO_BODY_LIMIT))
+        .route(web::get().to(handler))
+}
+
+fn post_resource<F, Args>(path: &str, body_limit: usize, handler: F) -> actix_web::Resource
+where
+    F: actix_web::Handler<Args>,
+    Args: actix_web::FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    web::resource(path)
+        .app_data(PayloadConfig::new(body_limit))
+        .route(web::post().to(handler))
+}
+
 pub fn api_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
-            .service(get_status)
-            .service(generate_block)
-            .service(tick)
-            .service(reset_block_tree)
-            .service(reset)
-            .service(get_block_tree_status)
-            .service(get_snapshot_block_number)
-            .service(sync_block_tree)
-            .service(restore)
-            .service(append_to_withdraw_proof)
-            .service(initialize)
-            .service(add)
-            .service(finalize_and_wrap)
-            .service(health),
+            .service(get_resource("/get-status", get_status))
+            .service(post_resource(
+                "/generate-block",
+                GENERATE_BLOCK_BODY_LIMIT,
+                generate_block,
+            ))
+            .service(post_resource("/tick", TICK_BODY_LIMIT, tick))
+            .service(post_resource(
+                "/reset-block-tree",
+                NO_BODY_LIMIT,
+                reset_block_tree,
+            ))
+            .service(post_resource("/reset", NO_BODY_LIMIT, reset))
+            .service(get_resource(
+                "/get-block-tree-status",
+                get_block_tree_status,
+            ))
+            .service(get_resource(
+                "/get-snapshot-block-number",
+                get_snapshot_block_number,
+            ))
+            .service(post_resource(
+                "/sync-block-tree",
+                SYNC_BLOCK_TREE_BODY_LIMIT,
+                sync_block_tree,
+            ))
+            .service(post_resource("/restore", RESTORE_BODY_LIMIT, restore))
+            .service(post_resource(
+                "/append-to-withdraw-proof",
+                APPEND_TO_WITHDRAW_PROOF_BODY_LIMIT,
+                append_to_withdraw_proof,
+            ))
+            .service(post_resource("/initialize", NO_BODY_LIMIT, initialize))
+            .service(post_resource("/add", ADD_BODY_LIMIT, add))
+            .service(post_resource(
+                "/finalize-and-wrap",
+                NO_BODY_LIMIT,
+                finalize_and_wrap,
+            ))
+            .service(post_resource(
+                "/verify-spent",
+                VERIFY_BODY_LIMIT,
+                verify_spent,
+            ))
+            .service(post_resource(
+                "/verify-validity",
+                VERIFY_BODY_LIMIT,
+                verify_validity,
+            ))
+            .service(post_resource(
+                "/verify-block-tree",
+                VERIFY_BODY_LIMIT,
+                verify_block_tree,
+            ))
+            .service(post_resource(
+                "/verify-withdraw",
+                VERIFY_BODY_LIMIT,
+                verify_withdraw,
+            ))
+            .service(post_resource(
+                "/verify-wrap",
+                VERIFY_BODY_LIMIT,
+                verify_wrap,
+            ))
+            .service(get_resource("/health", health)),
     );
 }
 
@@ -170,20 +352,32 @@ mod tests {
     use actix_http::Request;
     use actix_web::{
         dev::{Service, ServiceResponse},
+        http::{
+            header::{ACCEPT, CONTENT_TYPE},
+            StatusCode,
+        },
         test, web, App, Error,
     };
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
     use crate::{
         api::{
+            encoding::BINCODE_CONTENT_TYPE,
             io::{
                 AddInput, AppendToProofInput, AppendToProofOutput, FinalizeOutput,
-                GenerateBlockInput, SerializedBlockInfo, SerializedBlockStatus, TickInput,
+                GenerateBlockInput, SerializedBlockInfo, SerializedBlockStatus,
+                SerializedBlockTreePublicInputs, TickInput, VerifyProofInput, VerifyProofOutput,
+                VerifyWrapInput,
             },
             state::ServerState,
         },
-        common::{address::Address, asset::Assets},
+        base_circuits::{
+            spent_circuit::SpentPublicInputs, validity_circuit::ValidityPublicInputs,
+            withdraw_circuit::WithdrawPublicInputs,
+        },
+        common::{address::Address, asset::Assets, asset_registry::AssetRegistry},
         random::transfers::generate_random_transfers,
+        wrap_circuits::wrap::WrapPublicInputs,
     };
 
     use super::api_config;
@@ -211,9 +405,48 @@ mod tests {
         serde_json::from_slice(&body).unwrap()
     }
 
+    async fn post_bincode_helper<I, O>(
+        app: &mut impl Service<Request, Response = ServiceResponse, Error = Error>,
+        path: &str,
+        input: I,
+    ) -> O
+    where
+        I: serde::Serialize,
+        O: serde::de::DeserializeOwned,
+    {
+        let req = test::TestRequest::post()
+            .uri(path)
+            .insert_header((CONTENT_TYPE, BINCODE_CONTENT_TYPE))
+            .insert_header((ACCEPT, BINCODE_CONTENT_TYPE))
+            .set_payload(bincode::serialize(&input).unwrap())
+            .to_request();
+        let resp = test::call_service(app, req).await;
+        assert!(resp.status().is_success(), "response: {:?}", resp);
+        let body = test::read_body(resp).await;
+        bincode::deserialize(&body).unwrap()
+    }
+
+    async fn post_bad_request_helper<I: serde::Serialize>(
+        app: &mut impl Service<Request, Response = ServiceResponse, Error = Error>,
+        path: &str,
+        input: I,
+    ) {
+        let req = test::TestRequest::post()
+            .uri(path)
+            .set_json(&input)
+            .to_request();
+        let resp = test::call_service(app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
+            "response: {:?}",
+            resp
+        );
+    }
+
     #[actix_web::test]
     async fn test_server_to_finalize() {
-        let status = ServerState::new();
+        let status = ServerState::new(AssetRegistry::default()).unwrap();
         let app_data = web::Data::new(status);
         let mut app =
             test::init_service(App::new().app_data(app_data.clone()).configure(api_config)).await;
@@ -227,8 +460,28 @@ mod tests {
             },
         )
         .await;
-        // tick
-        let _block_status: SerializedBlockStatus = post_helper(
+        // the new block is consistent before it is ticked
+        let verified: VerifyProofOutput<SpentPublicInputs> = post_helper(
+            &mut app,
+            "/api/verify-spent",
+            VerifyProofInput {
+                proof: block_info.spent_proof.clone(),
+            },
+        )
+        .await;
+        assert_eq!(verified.public_inputs.block, block_info.block);
+        assert!(verified.consistent_with_block_root);
+        // a spent proof is not a validity proof
+        post_bad_request_helper(
+            &mut app,
+            "/api/verify-validity",
+            VerifyProofInput {
+                proof: block_info.spent_proof.clone(),
+            },
+        )
+        .await;
+        // tick with the binary encoding
+        let block_status: SerializedBlockStatus = post_bincode_helper(
             &mut app,
             "/api/tick",
             TickInput {
@@ -236,6 +489,30 @@ mod tests {
             },
         )
         .await;
+        let verified: VerifyProofOutput<ValidityPublicInputs> = post_helper(
+            &mut app,
+            "/api/verify-validity",
+            VerifyProofInput {
+                proof: block_status.validity_proof.unwrap(),
+            },
+        )
+        .await;
+        assert_eq!(
+            verified.public_inputs.block_hash,
+            block_info.block.block_hash()
+        );
+        assert!(verified.consistent_with_block_root);
+        let verified: VerifyProofOutput<SerializedBlockTreePublicInputs> = post_helper(
+            &mut app,
+            "/api/verify-block-tree",
+            VerifyProofInput {
+                proof: block_status.block_tree_proof.unwrap(),
+            },
+        )
+        .await;
+        assert_eq!(verified.public_inputs.block_root, block_status.block_root);
+        assert_eq!(verified.block_root, block_status.block_root);
+        assert!(verified.consistent_with_block_root);
         // initialize
         let _snapshot_block_number: String = post_helper(&mut app, "/api/initialize", ()).await;
         // finalize
@@ -247,7 +524,7 @@ mod tests {
     async fn test_server_settlement_wrap() {
         let does_print = true;
 
-        let status = ServerState::new();
+        let status = ServerState::new(AssetRegistry::default()).unwrap();
         let app_data = web::Data::new(status);
         let mut app =
             test::init_service(App::new().app_data(app_data.clone()).configure(api_config)).await;
@@ -267,6 +544,13 @@ mod tests {
                 },
             )
             .await;
+            assert_eq!(
+                block_info
+                    .transfer_info_bundle
+                    .as_ref()
+                    .map_or(0, |bundle| bundle.transfers.len()),
+                block_info.transfer_info.len()
+            );
             let _block_status: SerializedBlockStatus = post_helper(
                 &mut app,
                 "/api/tick",
@@ -284,6 +568,16 @@ mod tests {
         };
         let output: AppendToProofOutput =
             post_helper(&mut app, "/api/append-to-withdraw-proof", input).await;
+        let verified: VerifyProofOutput<WithdrawPublicInputs> = post_helper(
+            &mut app,
+            "/api/verify-withdraw",
+            VerifyProofInput {
+                proof: output.withdraw_proof.clone(),
+            },
+        )
+        .await;
+        assert_eq!(verified.public_inputs, output.withdraw_pis);
+        assert!(verified.consistent_with_block_root);
         let add_input = AddInput {
             withdraw_proof: output.withdraw_proof,
             evidence_transfer_info: withdraws[0].clone(),
@@ -291,8 +585,31 @@ mod tests {
         let _: String = post_helper(&mut app, "/api/add", add_input).await;
         let finalize_res: FinalizeOutput =
             post_helper(&mut app, "/api/finalize-and-wrap", ()).await;
+        let wrap_public_inputs = finalize_res.wrap_public_inputs.clone().unwrap();
+        let verified: VerifyProofOutput<WrapPublicInputs> = post_helper(
+            &mut app,
+            "/api/verify-wrap",
+            VerifyWrapInput {
+                wrap_proof: finalize_res.wrap_proof.clone().unwrap(),
+                wrap_public_inputs: wrap_public_inputs.clone(),
+            },
+        )
+        .await;
+        assert!(verified.consistent_with_block_root);
+        // the public inputs must match the hash in the proof
+        post_bad_request_helper(
+            &mut app,
+            "/api/verify-wrap",
+            VerifyWrapInput {
+                wrap_proof: finalize_res.wrap_proof.clone().unwrap(),
+                wrap_public_inputs: WrapPublicInputs {
+                    block_hash: wrap_public_inputs.settlement_root,
+                    settlement_root: wrap_public_inputs.block_hash,
+                },
+            },
+        )
+        .await;
         if does_print {
-            let wrap_public_inputs = finalize_res.wrap_public_inputs.unwrap();
             let serialized_proof =
                 serde_json::to_string(&finalize_res.wrap_proof.unwrap()).unwrap();
             println!("wrap_public_inputs: {}", wrap_public_inputs);
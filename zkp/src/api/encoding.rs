@@ -0,0 +1,154 @@
+use actix_web::{
+    http::header::{ACCEPT, CONTENT_TYPE},
+    HttpRequest, HttpResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const BINCODE_CONTENT_TYPE: &str = "application/x-bincode";
+
+/// The encoding of request and response bodies. Requests choose it with `Content-Type`
+/// and responses with `Accept`. JSON is used if the header is missing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    Bincode,
+}
+
+impl Encoding {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Json => JSON_CONTENT_TYPE,
+            Encoding::Bincode => BINCODE_CONTENT_TYPE,
+        }
+    }
+
+    /// Returns the encoding of the request body.
+    pub fn of_request(req: &HttpRequest) -> anyhow::Result<Self> {
+        let content_type = match req.headers().get(CONTENT_TYPE) {
+            Some(value) => value.to_str()?,
+            None => return Ok(Encoding::Json),
+        };
+        match essence(content_type) {
+            JSON_CONTENT_TYPE => Ok(Encoding::Json),
+            BINCODE_CONTENT_TYPE => Ok(Encoding::Bincode),
+            other => anyhow::bail!(
+                "unsupported content type {}, expected {} or {}",
+                other,
+                JSON_CONTENT_TYPE,
+                BINCODE_CONTENT_TYPE
+            ),
+        }
+    }
+
+    /// Returns the encoding of the response body. Bincode is used only if it is accepted
+    /// explicitly, so `*/*` gets JSON.
+    pub fn accepted(req: &HttpRequest) -> Self {
+        let accept = req
+            .headers()
+            .get(ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if accept
+            .split(',')
+            .any(|v| essence(v) == BINCODE_CONTENT_TYPE)
+        {
+            Encoding::Bincode
+        } else {
+            Encoding::Json
+        }
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Encoding::Json => serde_json::to_vec(value)?,
+            Encoding::Bincode => bincode::serialize(value)?,
+        })
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> anyhow::Result<T> {
+        Ok(match self {
+            Encoding::Json => serde_json::from_slice(bytes)?,
+            Encoding::Bincode => bincode::deserialize(bytes)?,
+        })
+    }
+}
+
+// The media type without parameters such as `charset` or `q`.
+fn essence(media_type: &str) -> &str {
+    media_type.split(';').next().unwrap_or_default().trim()
+}
+
+/// Decodes the request body in the encoding of its `Content-Type`.
+pub fn decode_body<T: DeserializeOwned>(req: &HttpRequest, body: &[u8]) -> anyhow::Result<T> {
+    Encoding::of_request(req)?.decode(body)
+}
+
+/// Responds with `value` in the encoding accepted by the request.
+pub fn respond<T: Serialize>(req: &HttpRequest, value: &T) -> HttpResponse {
+    let encoding = Encoding::accepted(req);
+    match encoding.encode(value) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(encoding.content_type())
+            .body(body),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::{ACCEPT, CONTENT_TYPE},
+        test::TestRequest,
+    };
+
+    use super::{Encoding, BINCODE_CONTENT_TYPE};
+    use crate::{
+        api::io::TickInput, common::block::Block, serialization::serialized_proof::SerializedProof,
+    };
+
+    #[test]
+    fn test_encoding_negotiation() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(Encoding::of_request(&req).unwrap(), Encoding::Json);
+        assert_eq!(Encoding::accepted(&req), Encoding::Json);
+
+        let req = TestRequest::default()
+            .insert_header((CONTENT_TYPE, "application/json; charset=utf-8"))
+            .insert_header((ACCEPT, "application/json, application/x-bincode;q=0.9"))
+            .to_http_request();
+        assert_eq!(Encoding::of_request(&req).unwrap(), Encoding::Json);
+        assert_eq!(Encoding::accepted(&req), Encoding::Bincode);
+
+        let req = TestRequest::default()
+            .insert_header((CONTENT_TYPE, BINCODE_CONTENT_TYPE))
+            .insert_header((ACCEPT, "*/*"))
+            .to_http_request();
+        assert_eq!(Encoding::of_request(&req).unwrap(), Encoding::Bincode);
+        assert_eq!(Encoding::accepted(&req), Encoding::Json);
+
+        let req = TestRequest::default()
+            .insert_header((CONTENT_TYPE, "text/plain"))
+            .to_http_request();
+        assert!(Encoding::of_request(&req).is_err());
+    }
+
+    #[test]
+    fn test_encoding_round_trip() {
+        let input = TickInput {
+            spent_proof: SerializedProof(vec![1u8; 1000]),
+        };
+        let json = Encoding::Json.encode(&input).unwrap();
+        let binary = Encoding::Bincode.encode(&input).unwrap();
+        // the proof is not hex encoded
+        assert!(binary.len() < json.len() / 2);
+        let recovered: TickInput = Encoding::Bincode.decode(&binary).unwrap();
+        assert_eq!(recovered.spent_proof, input.spent_proof);
+
+        let block = Block::default();
+        let recovered: Block = Encoding::Bincode
+            .decode(&Encoding::Bincode.encode(&block).unwrap())
+            .unwrap();
+        assert_eq!(recovered, block);
+    }
+}
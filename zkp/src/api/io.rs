@@ -1,15 +1,20 @@
+use plonky2::field::goldilocks_field::GoldilocksField;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    base_circuits::withdraw_circuit::WithdrawPublicInputs,
-    common::{asset::Assets, block::Block, transfer::Transfer},
+    base_circuits::{
+        block_tree_circuit::BlockTreePublicInputs, withdraw_circuit::WithdrawPublicInputs,
+    },
+    common::{asset::Assets, asset_registry::FormattedAsset, block::Block, transfer::Transfer},
     processors::settlement_processor::SettlementMerkleProof,
+    utils::h256::H256,
     wrap_circuits::wrap::WrapPublicInputs,
 };
 
 use crate::serialization::{
-    serialized_hashout::SerializedHashOut, serialized_proof::SerializedProof,
-    serialized_transfer_info::SerializedTransferInfo,
+    serialized_hashout::SerializedHashOut,
+    serialized_proof::SerializedProof,
+    serialized_transfer_info::{SerializedTransferInfo, SerializedTransferInfoBundle},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,4 +80,55 @@ pub struct SerializedBlockInfo {
     pub block: Block,
     pub transfer_info: Vec<SerializedTransferInfo>,
     pub spent_proof: SerializedProof,
+    /// `block.total_deposit` and the transfer amounts formatted with the asset registry
+    /// of the server. They are informational and not read back.
+    #[serde(default)]
+    pub formatted_total_deposit: Vec<FormattedAsset>,
+    #[serde(default)]
+    pub formatted_transfers: Vec<FormattedAsset>,
+    /// `transfer_info` with one multiproof of the transfer tree instead of a merkle proof
+    /// per transfer, for wallets. `None` for a block without transfers.
+    #[serde(default)]
+    pub transfer_info_bundle: Option<SerializedTransferInfoBundle>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyProofInput {
+    pub proof: SerializedProof,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyWrapInput {
+    pub wrap_proof: SerializedProof,
+    pub wrap_public_inputs: WrapPublicInputs,
+}
+
+/// The decoded public inputs of a verified proof, and whether they are consistent with
+/// the current block tree whose root is `block_root`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyProofOutput<P> {
+    pub public_inputs: P,
+    pub block_root: SerializedHashOut,
+    pub consistent_with_block_root: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedBlockTreePublicInputs {
+    pub block: Block,
+    pub block_hash: H256,
+    pub block_root: SerializedHashOut,
+}
+
+impl From<BlockTreePublicInputs<GoldilocksField>> for SerializedBlockTreePublicInputs {
+    fn from(value: BlockTreePublicInputs<GoldilocksField>) -> Self {
+        Self {
+            block: value.block,
+            block_hash: value.block_hash,
+            block_root: SerializedHashOut(value.block_root),
+        }
+    }
 }
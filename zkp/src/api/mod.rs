@@ -1,3 +1,4 @@
 pub mod api;
+pub mod encoding;
 pub mod io;
 pub mod state;
@@ -1,13 +1,16 @@
 use super::io::{
     AddInput, AppendToProofInput, AppendToProofOutput, FinalizeOutput, GenerateBlockInput,
-    SerializedBlockInfo, SerializedBlockStatus, SyncBlockTreeInput, TickInput,
+    SerializedBlockInfo, SerializedBlockStatus, SerializedBlockTreePublicInputs,
+    SyncBlockTreeInput, TickInput, VerifyProofInput, VerifyProofOutput, VerifyWrapInput,
 };
 use crate::{
     base_circuits::{
-        block_tree_circuit::BlockTreeCircuit, spent_circuit::SpentCircuit,
-        validity_circuit::ValidityCircuit, withdraw_circuit::WithdrawPublicInputs,
+        block_tree_circuit::{BlockTreeCircuit, BlockTreePublicInputs},
+        spent_circuit::{SpentCircuit, SpentPublicInputs},
+        validity_circuit::{ValidityCircuit, ValidityPublicInputs},
+        withdraw_circuit::WithdrawPublicInputs,
     },
-    common::{block::Block, transfer_info::TransferInfo},
+    common::{asset_registry::AssetRegistry, block::Block, transfer_info::TransferInfo},
     processors::{
         block_io::{BlockStatus, BlockTreeStatus},
         block_processor::BlockProcessor,
@@ -15,10 +18,12 @@ use crate::{
         wrap_processor::{validate_balance_block_proof, WrapProcessor},
     },
     serialization::{
-        serialized_hashout::SerializedHashOut, serialized_proof::SerializedProof,
-        serialized_transfer_info::SerializedTransferInfo,
+        serialized_hashout::SerializedHashOut,
+        serialized_proof::{CircuitKind, SerializedProof},
+        serialized_transfer_info::{SerializedTransferInfo, SerializedTransferInfoBundle},
     },
     utils::trees::merkle_tree_with_leaves::MerkleTreeWithLeaves,
+    wrap_circuits::wrap::WrapPublicInputs,
 };
 use anyhow::{anyhow, ensure};
 use parking_lot::RwLock;
@@ -46,15 +51,16 @@ pub struct ServerState {
     pub block_tree_proof_snapshot: RwLock<Option<ProofWithPublicInputs<F, C, D>>>,
     pub settlement_processor: RwLock<SettlementProcessor<F, C, D>>,
     pub wrap_processor: WrapProcessor<F, C, OuterC, D>,
+    pub asset_registry: AssetRegistry,
 }
 
 impl ServerState {
-    pub fn new() -> Self {
+    pub fn new(asset_registry: AssetRegistry) -> anyhow::Result<Self> {
         let spent_circuit = SpentCircuit::<F, C, D>::new();
-        let validity_circuit = ValidityCircuit::<F, C, D>::new(&spent_circuit);
-        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new();
+        let validity_circuit = ValidityCircuit::<F, C, D>::new(&spent_circuit)?;
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new()?;
         let block_processor = BlockProcessor::<F, C, D>::new();
-        let settlement_processor = SettlementProcessor::<F, C, D>::new(&block_tree_circuit);
+        let settlement_processor = SettlementProcessor::<F, C, D>::new(&block_tree_circuit)?;
         let inner_config = standard_inner_stark_verifier_config();
         let outer_config = standard_stark_verifier_config();
         let wrap_processor = WrapProcessor::<F, C, OuterC, D>::new(
@@ -64,7 +70,7 @@ impl ServerState {
             &block_tree_circuit,
             &settlement_processor,
         );
-        Self {
+        Ok(Self {
             spent_circuit,
             validity_circuit,
             block_tree_circuit,
@@ -74,17 +80,22 @@ impl ServerState {
             block_tree_proof_snapshot: RwLock::new(None),
             settlement_processor: RwLock::new(settlement_processor),
             wrap_processor,
-        }
+            asset_registry,
+        })
     }
 
     pub fn get_status(&self) -> SerializedBlockStatus {
         let status = self.block_processor.read().get_status();
-        let validity_proof = status
-            .validity_proof
-            .map(|proof| SerializedProof::from_proof(&self.validity_circuit.data, &proof));
-        let block_tree_proof = status
-            .block_tree_proof
-            .map(|proof| SerializedProof::from_proof(&self.block_tree_circuit.data, &proof));
+        let validity_proof = status.validity_proof.map(|proof| {
+            SerializedProof::from_proof(CircuitKind::Validity, &self.validity_circuit.data, &proof)
+        });
+        let block_tree_proof = status.block_tree_proof.map(|proof| {
+            SerializedProof::from_proof(
+                CircuitKind::BlockTree,
+                &self.block_tree_circuit.data,
+                &proof,
+            )
+        });
         SerializedBlockStatus {
             latest_block: status.latest_block.clone(),
             block_root: SerializedHashOut(status.block_root),
@@ -94,27 +105,62 @@ impl ServerState {
     }
 
     pub fn generate_block(&self, input: GenerateBlockInput) -> anyhow::Result<SerializedBlockInfo> {
+        for transfer in input.transfers.iter() {
+            self.asset_registry.validate_asset(&transfer.asset)?;
+        }
+        self.asset_registry.validate_assets(&input.deposit)?;
         let block_info = self.block_processor.read().generate_block(
             &self.spent_circuit,
             &input.transfers,
             &input.deposit,
         )?;
-        let spent_proof =
-            SerializedProof::from_proof(&self.spent_circuit.data, &block_info.spent_proof);
+        log::info!(
+            "Generated block {} with total deposit {}",
+            block_info.block.block_number,
+            block_info
+                .block
+                .total_deposit
+                .display_with(&self.asset_registry)
+        );
+        let spent_proof = SerializedProof::from_proof(
+            CircuitKind::Spent,
+            &self.spent_circuit.data,
+            &block_info.spent_proof,
+        );
         let transfer_info = block_info
             .transfer_info
             .iter()
             .map(|t| t.clone().into())
             .collect::<Vec<SerializedTransferInfo>>();
+        let transfer_info_bundle = if block_info.transfer_info.is_empty() {
+            None
+        } else {
+            Some(SerializedTransferInfoBundle::try_from(
+                block_info.transfer_info.clone(),
+            )?)
+        };
+        let formatted_total_deposit = self
+            .asset_registry
+            .format_assets(&block_info.block.total_deposit);
+        let formatted_transfers = input
+            .transfers
+            .iter()
+            .map(|transfer| self.asset_registry.format_asset(&transfer.asset))
+            .collect();
         Ok(SerializedBlockInfo {
             block: block_info.block.clone(),
             transfer_info,
             spent_proof,
+            formatted_total_deposit,
+            formatted_transfers,
+            transfer_info_bundle,
         })
     }
 
     pub fn tick(&self, input: TickInput) -> anyhow::Result<SerializedBlockStatus> {
-        let spent_proof = input.spent_proof.to_proof(&self.spent_circuit.data)?;
+        let spent_proof = input
+            .spent_proof
+            .to_proof(CircuitKind::Spent, &self.spent_circuit.data)?;
         self.block_processor.write().tick(
             &self.validity_circuit,
             &self.block_tree_circuit,
@@ -153,11 +199,11 @@ impl ServerState {
     pub fn restore(&self, input: SerializedBlockStatus) -> anyhow::Result<()> {
         let validity_proof = input
             .validity_proof
-            .map(|proof| proof.to_proof(&self.validity_circuit.data))
+            .map(|proof| proof.to_proof(CircuitKind::Validity, &self.validity_circuit.data))
             .transpose()?;
         let block_tree_proof = input
             .block_tree_proof
-            .map(|proof| proof.to_proof(&self.block_tree_circuit.data))
+            .map(|proof| proof.to_proof(CircuitKind::BlockTree, &self.block_tree_circuit.data))
             .transpose()?;
         let status = BlockStatus {
             latest_block: input.latest_block.clone(),
@@ -183,13 +229,21 @@ impl ServerState {
         );
         let withdraw_proof = input
             .withdraw_proof
-            .map(|proof| proof.to_proof(&self.settlement_processor.read().withdraw_circuit.data))
+            .map(|proof| {
+                proof.to_proof(
+                    CircuitKind::Withdraw,
+                    &self.settlement_processor.read().withdraw_circuit.data,
+                )
+            })
             .transpose()?;
         let transfer_info = input
             .transfer_info
             .iter()
             .map(|t| t.clone().into())
             .collect::<Vec<TransferInfo<F>>>();
+        for t in transfer_info.iter() {
+            self.asset_registry.validate_asset(&t.transfer.asset)?;
+        }
         let new_withdraw_proof = self.settlement_processor.read().append_withdraw_proof(
             &self.block_tree_circuit,
             self.block_tree_snapshot.read().as_ref().unwrap(),
@@ -201,6 +255,7 @@ impl ServerState {
         Ok(AppendToProofOutput {
             withdraw_pis,
             withdraw_proof: SerializedProof::from_proof(
+                CircuitKind::Withdraw,
                 &self.settlement_processor.read().withdraw_circuit.data,
                 &new_withdraw_proof,
             ),
@@ -225,9 +280,10 @@ impl ServerState {
             self.block_tree_snapshot.read().is_some(),
             "block_tree_snapshot is None"
         );
-        let withdraw_proof = input
-            .withdraw_proof
-            .to_proof(&self.settlement_processor.read().withdraw_circuit.data)?;
+        let withdraw_proof = input.withdraw_proof.to_proof(
+            CircuitKind::Withdraw,
+            &self.settlement_processor.read().withdraw_circuit.data,
+        )?;
         self.settlement_processor.write().add(
             &self.block_tree_snapshot.read().as_ref().unwrap(),
             &withdraw_proof,
@@ -236,6 +292,111 @@ impl ServerState {
         Ok(())
     }
 
+    // The verify methods do not change the state. They return an error if the proof is invalid,
+    // and otherwise the public inputs and whether they agree with the current block tree.
+    fn verify_output<P>(&self, public_inputs: P, is_consistent: bool) -> VerifyProofOutput<P> {
+        VerifyProofOutput {
+            public_inputs,
+            block_root: SerializedHashOut(self.block_processor.read().block_tree.get_root()),
+            consistent_with_block_root: is_consistent,
+        }
+    }
+
+    pub fn verify_spent(
+        &self,
+        input: VerifyProofInput,
+    ) -> anyhow::Result<VerifyProofOutput<SpentPublicInputs>> {
+        let proof = input
+            .proof
+            .to_proof(CircuitKind::Spent, &self.spent_circuit.data)?;
+        self.spent_circuit
+            .verify(proof.clone())
+            .map_err(|e| anyhow!("spent proof verification failed: {}", e))?;
+        let pis = SpentPublicInputs::from_vec(&proof.public_inputs);
+        // the block is already in the block tree, or can be appended by `tick`
+        let is_consistent = {
+            let block_processor = self.block_processor.read();
+            block_processor.contains_block(&pis.block) || block_processor.is_next_block(&pis.block)
+        };
+        Ok(self.verify_output(pis, is_consistent))
+    }
+
+    pub fn verify_validity(
+        &self,
+        input: VerifyProofInput,
+    ) -> anyhow::Result<VerifyProofOutput<ValidityPublicInputs>> {
+        let proof = input
+            .proof
+            .to_proof(CircuitKind::Validity, &self.validity_circuit.data)?;
+        self.validity_circuit
+            .verify(proof.clone())
+            .map_err(|e| anyhow!("validity proof verification failed: {}", e))?;
+        let pis = ValidityPublicInputs::from_pis(&proof.public_inputs);
+        let is_consistent = self
+            .block_processor
+            .read()
+            .contains_block_hash(pis.block_hash);
+        Ok(self.verify_output(pis, is_consistent))
+    }
+
+    pub fn verify_block_tree(
+        &self,
+        input: VerifyProofInput,
+    ) -> anyhow::Result<VerifyProofOutput<SerializedBlockTreePublicInputs>> {
+        let proof = input
+            .proof
+            .to_proof(CircuitKind::BlockTree, &self.block_tree_circuit.data)?;
+        self.block_tree_circuit
+            .verify(proof.clone())
+            .map_err(|e| anyhow!("block tree proof verification failed: {}", e))?;
+        let pis = BlockTreePublicInputs::from_pis(&proof.public_inputs);
+        let is_consistent = pis.block_root == self.block_processor.read().block_tree.get_root();
+        Ok(self.verify_output(pis.into(), is_consistent))
+    }
+
+    pub fn verify_withdraw(
+        &self,
+        input: VerifyProofInput,
+    ) -> anyhow::Result<VerifyProofOutput<WithdrawPublicInputs>> {
+        let settlement_processor = self.settlement_processor.read();
+        let proof = input.proof.to_proof(
+            CircuitKind::Withdraw,
+            &settlement_processor.withdraw_circuit.data,
+        )?;
+        settlement_processor
+            .withdraw_circuit
+            .verify(&proof)
+            .map_err(|e| anyhow!("withdraw proof verification failed: {}", e))?;
+        let pis = WithdrawPublicInputs::from_pis(&proof.public_inputs);
+        let is_consistent = self.block_processor.read().contains_block(&pis.block);
+        Ok(self.verify_output(pis, is_consistent))
+    }
+
+    pub fn verify_wrap(
+        &self,
+        input: VerifyWrapInput,
+    ) -> anyhow::Result<VerifyProofOutput<WrapPublicInputs>> {
+        let wrap2_circuit = &self.wrap_processor.wrap2_circuit;
+        let proof = input
+            .wrap_proof
+            .to_proof(CircuitKind::Wrap2, &wrap2_circuit.data)?;
+        wrap2_circuit
+            .verify(proof.clone())
+            .map_err(|e| anyhow!("wrap proof verification failed: {}", e))?;
+        // the proof only carries the keccak hash of the public inputs
+        let pis = input.wrap_public_inputs;
+        ensure!(
+            proof.public_inputs == pis.to_solidity_pis::<F>().elements.to_vec(),
+            "wrap public inputs {} do not match the proof",
+            pis
+        );
+        let is_consistent = self
+            .block_processor
+            .read()
+            .contains_block_hash(pis.block_hash);
+        Ok(self.verify_output(pis, is_consistent))
+    }
+
     pub fn finalize_and_wrap(&self) -> anyhow::Result<FinalizeOutput> {
         let validity_proof = self
             .validity_proof_snapshot
@@ -283,8 +444,11 @@ impl ServerState {
             block_tree_proof,
             settlement_tree_proof,
         )?;
-        let wrap_proof =
-            SerializedProof::from_proof(&self.wrap_processor.wrap2_circuit.data, &wrap_proof);
+        let wrap_proof = SerializedProof::from_proof(
+            CircuitKind::Wrap2,
+            &self.wrap_processor.wrap2_circuit.data,
+            &wrap_proof,
+        );
         Ok(FinalizeOutput {
             settlement_merkle_proofs: Some(settlment_merkle_proofs),
             wrap_public_inputs: Some(wrap_public_inputs),
@@ -1,6 +1,6 @@
+use anyhow::ensure;
 use plonky2::{
     field::extension::Extendable,
-    gates::noop::NoopGate,
     hash::hash_types::{HashOut, HashOutTarget, RichField},
     iop::{
         target::{BoolTarget, Target},
@@ -21,11 +21,12 @@ use starky_keccak::builder::CircuitBuilderWithKeccak;
 
 use crate::{
     common::block::{Block, BlockTarget, BLOCK_VEC_LEN},
-    constants::BLOCK_TREE_PADDING_DEGREE,
     utils::{
+        cyclic::build_cyclic_circuit,
         h256::{H256Target, H256},
         leafable::{Leafable, LeafableTarget},
         logic::enforce_equal_targets_if_enabled,
+        mock::{mock_cyclic_proof, mock_verify_cyclic},
         trees::merkle_tree_with_leaves::{
             MerkleProofWithLeaves, MerkleProofWithLeavesTarget, MerkleTreeWithLeaves,
         },
@@ -234,13 +235,20 @@ where
     C: GenericConfig<D, F = F> + 'static,
     C::Hasher: AlgebraicHasher<F>,
 {
-    pub fn new() -> Self {
+    pub fn new() -> anyhow::Result<Self> {
+        build_cyclic_circuit(
+            &common_data_for_block_tree_circuit::<F, C, D>(),
+            Self::build,
+            |circuit| &circuit.data,
+        )
+    }
+
+    fn build(mut common_data: CommonCircuitData<F, D>) -> Self {
         let mut builder = CircuitBuilderWithKeccak::<F, D>::new(CircuitConfig::default());
         let target = BlockTreeTarget::new(&mut builder, 32);
         let current_pis = BlockTreePublicInputsTarget::from_block_tree_target(&target);
         builder.register_public_inputs(&current_pis.to_vec());
 
-        let mut common_data = common_data_for_block_tree_circuit::<F, C, D>();
         let verifier_data_target = builder.add_verifier_data_public_inputs();
         common_data.num_public_inputs = builder.num_public_inputs();
 
@@ -281,9 +289,6 @@ where
             )
             .unwrap();
         let circuit_data = builder.build::<C>();
-
-        debug_assert_eq!(circuit_data.common, common_data);
-
         Self {
             data: circuit_data,
             target,
@@ -298,9 +303,22 @@ where
         value: &BlockTreeValue<F>,
         previous_proof: &Option<ProofWithPublicInputs<F, C, D>>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        if cfg!(feature = "mock") {
+            return self.prove_mock(value, previous_proof);
+        }
         let mut pw = PartialWitness::<F>::new();
+        self.set_witness(&mut pw, value, previous_proof);
+        self.data.prove(pw)
+    }
+
+    pub fn set_witness(
+        &self,
+        pw: &mut impl Witness<F>,
+        value: &BlockTreeValue<F>,
+        previous_proof: &Option<ProofWithPublicInputs<F, C, D>>,
+    ) {
         pw.set_verifier_data_target(&self.verifier_data_target, &self.data.verifier_only);
-        self.target.set_witness(&mut pw, value);
+        self.target.set_witness(pw, value);
         if let Some(previous_proof) = previous_proof {
             pw.set_bool_target(self.is_not_first_step, true);
             pw.set_proof_with_pis_target::<C, D>(&self.previous_proof, &previous_proof);
@@ -314,10 +332,51 @@ where
             pw.set_bool_target(self.is_not_first_step, false);
             pw.set_proof_with_pis_target::<C, D>(&self.previous_proof, &dummy_proof);
         };
-        self.data.prove(pw)
+    }
+
+    // Checks the witness natively instead of proving it.
+    fn prove_mock(
+        &self,
+        value: &BlockTreeValue<F>,
+        previous_proof: &Option<ProofWithPublicInputs<F, C, D>>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let empty_leaf = <Block as Leafable<F>>::empty_leaf();
+        let block_number = value.block.block_number as usize;
+        ensure!(
+            value.block_hash == value.block.block_hash(),
+            "block hash mismatch"
+        );
+        value
+            .merkle_proof
+            .verify(&empty_leaf, block_number, value.prev_block_root)?;
+        value
+            .merkle_proof
+            .verify(&value.block, block_number, value.new_block_root)?;
+        let previous_pis = match previous_proof {
+            Some(previous_proof) => {
+                self.verify(previous_proof.clone())?;
+                BlockTreePublicInputs::from_pis(&previous_proof.public_inputs)
+            }
+            None => {
+                ensure!(
+                    value.block.block_number == 1,
+                    "the first block number must be 1"
+                );
+                BlockTreePublicInputs::default()
+            }
+        };
+        ensure!(
+            previous_pis.block_hash == value.block.prev_block_hash,
+            "prev block hash mismatch"
+        );
+        let pis = BlockTreePublicInputs::from_block_tree(value);
+        mock_cyclic_proof(&self.data, pis.to_vec())
     }
 
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
+        if cfg!(feature = "mock") {
+            return mock_verify_cyclic(&self.data, &proof_with_pis);
+        }
         check_cyclic_proof_verifier_data(
             &proof_with_pis,
             &self.data.verifier_only,
@@ -379,9 +438,6 @@ where
     let _ = builder.add_many_u32(&[zero, zero, zero]);
     let _ = builder.sub_u32(zero, zero, zero);
     let _zero_limbs = [(); 8].map(|_| builder.zero_u32());
-    while builder.num_gates() < 1 << BLOCK_TREE_PADDING_DEGREE {
-        builder.add_gate(NoopGate, vec![]);
-    }
     builder.build::<C>().common
 }
 
@@ -394,7 +450,11 @@ mod tests {
     use crate::{
         base_circuits::block_tree_circuit::BlockTreePublicInputs,
         common::{asset::Assets, block::Block},
-        utils::{h256::H256, trees::merkle_tree_with_leaves::MerkleTreeWithLeaves},
+        utils::{
+            h256::H256,
+            tampering::{assert_tampered_witnesses_fail, tamper_merkle_sibling, Tamper},
+            trees::merkle_tree_with_leaves::MerkleTreeWithLeaves,
+        },
     };
 
     use super::{BlockTreeCircuit, BlockTreeValue};
@@ -405,7 +465,7 @@ mod tests {
 
     #[test]
     fn test_block_tree_circuit() {
-        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new();
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new().unwrap();
 
         let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
         let block0 = Block::default();
@@ -452,4 +512,59 @@ mod tests {
         let pis2 = BlockTreePublicInputs::from_pis(&proof_with_pis2.public_inputs);
         assert_eq!(block_root2, pis2.block_root);
     }
+
+    #[test]
+    #[cfg_attr(feature = "mock", ignore)]
+    fn test_block_tree_circuit_tampered_witness() {
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new().unwrap();
+
+        let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
+        let block0 = Block::default();
+        block_tree.push(block0.clone());
+        let block_root0 = block_tree.get_root();
+        let block1 = Block {
+            prev_block_hash: block0.block_hash(),
+            transfer_tree_root: H256::default(),
+            total_deposit: Assets::default(),
+            block_number: 1,
+        };
+        block_tree.push(block1.clone());
+        let block_root1 = block_tree.get_root();
+        let block1_merkle_proof = block_tree.prove(block1.block_number as usize);
+        let value1 = BlockTreeValue::new(block1, block_root0, block_root1, block1_merkle_proof);
+
+        let wrong_block_hash = H256::rand(&mut rand::thread_rng());
+        // the block with a wrong prev_block_hash is committed in a consistent block tree, so
+        // that only the prev_block_hash constraint can fail
+        let tamper_prev_block_hash = move |value: &mut BlockTreeValue<F>| {
+            let mut block = value.block.clone();
+            block.prev_block_hash = wrong_block_hash;
+            let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
+            block_tree.push(block0.clone());
+            block_tree.push(block.clone());
+            let merkle_proof = block_tree.prove(block.block_number as usize);
+            *value = BlockTreeValue::new(
+                block,
+                value.prev_block_root,
+                block_tree.get_root(),
+                merkle_proof,
+            );
+        };
+        let tampers = vec![
+            Tamper::value("wrong merkle sibling", |value: &mut BlockTreeValue<F>| {
+                tamper_merkle_sibling(&mut value.merkle_proof)
+            }),
+            Tamper::value("wrong prev_block_hash", tamper_prev_block_hash),
+            Tamper::non_canonical_u32(
+                "non-canonical u32 limb",
+                block_tree_circuit.target.block.total_deposit.0[0].0[7],
+            ),
+        ];
+        assert_tampered_witnesses_fail(
+            &block_tree_circuit.data,
+            |pw, value| block_tree_circuit.set_witness(pw, value, &None),
+            &value1,
+            &tampers,
+        );
+    }
 }
@@ -5,8 +5,12 @@ use crate::{
         transfer::{calc_transfer_tree, calc_transfer_tree_circuit, Transfer, TransferTarget},
     },
     constants::{NUM_ASSETS, TRANSFER_TREE_HEIGHT},
-    utils::h256::{H256Target, H256},
+    utils::{
+        h256::{H256Target, H256},
+        mock::{mock_proof, mock_verify},
+    },
 };
+use anyhow::ensure;
 use plonky2::{
     field::{extension::Extendable, types::PrimeField64},
     hash::hash_types::RichField,
@@ -25,8 +29,10 @@ use plonky2_u32::{
     gadgets::arithmetic_u32::{CircuitBuilderU32, U32Target},
     witness::WitnessU32,
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SpentPublicInputs {
     pub block: Block,
     pub spent: Assets,
@@ -71,6 +77,7 @@ impl SpentPublicInputsTarget {
     }
 }
 
+#[derive(Clone)]
 pub struct SpentValue {
     pub transfers: Vec<Transfer>,  // padded transfers
     pub prev_block_hash: H256,     // previous block hash
@@ -185,11 +192,43 @@ where
     }
 
     pub fn prove(&self, value: &SpentValue) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        if cfg!(feature = "mock") {
+            return self.prove_mock(value);
+        }
         let mut pw = PartialWitness::<F>::new();
         self.target.set_witness(&mut pw, value);
         self.data.prove(pw)
     }
 
+    // Checks the witness natively instead of proving it.
+    fn prove_mock(&self, value: &SpentValue) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            value.transfers.len() == 1 << TRANSFER_TREE_HEIGHT,
+            "transfers are not padded"
+        );
+        let (transfer_tree_root, spent) = calc_transfer_tree::<F>(&value.transfers);
+        let new_block = Block {
+            prev_block_hash: value.prev_block_hash,
+            transfer_tree_root: transfer_tree_root.into(),
+            total_deposit: value.new_total_deposit.clone(),
+            block_number: value.new_block_number,
+        };
+        ensure!(value.new_block == new_block, "new block mismatch");
+        ensure!(value.spent == spent, "spent mismatch");
+        let pis = SpentPublicInputs {
+            block: new_block,
+            spent,
+        };
+        mock_proof(&self.data, pis.to_vec())
+    }
+
+    pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
+        if cfg!(feature = "mock") {
+            return mock_verify(&self.data, &proof_with_pis);
+        }
+        self.data.verify(proof_with_pis)
+    }
+
     pub fn add_proof_target_and_verify(
         &self,
         builder: &mut CircuitBuilder<F, D>,
@@ -211,7 +250,11 @@ mod tests {
     use crate::{
         common::{address::Address, asset::Assets},
         random::transfers::generate_random_transfers,
-        utils::h256::H256,
+        utils::{
+            h256::H256,
+            tampering::{assert_tampered_witnesses_fail, Tamper},
+            u256::U256,
+        },
     };
 
     use super::{SpentCircuit, SpentValue};
@@ -239,4 +282,39 @@ mod tests {
         let _proof = circuit.prove(&value).unwrap();
         println!("spent circuit: {:?}", now.elapsed());
     }
+
+    #[test]
+    #[cfg_attr(feature = "mock", ignore)]
+    fn test_spent_circuit_tampered_witness() {
+        let circuit = SpentCircuit::<F, C, D>::new();
+        let mut rng = rand::thread_rng();
+        let recipients = vec![Address::rand(&mut rng)];
+        let transfers = generate_random_transfers::<F, _>(&mut rng, 1, 4, &recipients)[0].clone();
+        let total_deposit = Assets::rand_full(&mut rng);
+        let prev_block_hash = H256::rand(&mut rng);
+        let value = SpentValue::new::<F>(&transfers, &total_deposit, &prev_block_hash, rng.gen());
+
+        let wrong_block_hash = H256::rand(&mut rng);
+        let tampers = vec![
+            Tamper::value("overspent amount", |value: &mut SpentValue| {
+                value.spent.0[0] += U256::from(1u64);
+            }),
+            Tamper::value("wrong prev_block_hash", move |value: &mut SpentValue| {
+                value.new_block.prev_block_hash = wrong_block_hash;
+            }),
+            Tamper::value("wrong transfer tree root", move |value: &mut SpentValue| {
+                value.new_block.transfer_tree_root = wrong_block_hash;
+            }),
+            Tamper::non_canonical_u32(
+                "non-canonical u32 limb",
+                circuit.target.transfers[0].amount.amount.0[7],
+            ),
+        ];
+        assert_tampered_witnesses_fail(
+            &circuit.data,
+            |pw, value| circuit.target.set_witness(pw, value),
+            &value,
+            &tampers,
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use crate::utils::logic::enforce_equal_targets_if_enabled;
+use anyhow::ensure;
 use plonky2::{
     field::extension::Extendable,
-    gates::noop::NoopGate,
     hash::hash_types::RichField,
     iop::{
         target::{BoolTarget, Target},
@@ -9,7 +9,10 @@ use plonky2::{
     },
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget},
+        circuit_data::{
+            CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitData,
+            VerifierCircuitTarget,
+        },
         config::{AlgebraicHasher, GenericConfig},
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
@@ -18,6 +21,7 @@ use plonky2::{
     },
 };
 use plonky2_u32::gadgets::arithmetic_u32::CircuitBuilderU32;
+use serde::{Deserialize, Serialize};
 use starky_keccak::builder::CircuitBuilderWithKeccak;
 
 use crate::{
@@ -26,12 +30,17 @@ use crate::{
         block::Block,
     },
     constants::NUM_ASSETS,
-    utils::h256::{H256Target, H256},
+    utils::{
+        cyclic::build_cyclic_circuit,
+        h256::{H256Target, H256},
+        mock::{mock_cyclic_proof, mock_verify_cyclic, mock_verify_inner},
+    },
 };
 
-use super::spent_circuit::{SpentCircuit, SpentPublicInputsTarget};
+use super::spent_circuit::{SpentCircuit, SpentPublicInputs, SpentPublicInputsTarget};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidityPublicInputs {
     pub block_hash: H256,      // the latest block hash
     pub total_spent: Assets,   // the total spent amount so far (including the latest block)
@@ -153,6 +162,7 @@ where
     pub is_not_first_step: BoolTarget,
     pub prev_proof: ProofWithPublicInputsTarget<D>,
     pub verifier_data_target: VerifierCircuitTarget,
+    spent_verifier_data: VerifierCircuitData<F, C, D>,
 }
 
 impl<F, C, const D: usize> ValidityCircuit<F, C, D>
@@ -161,7 +171,18 @@ where
     C: GenericConfig<D, F = F> + 'static,
     C::Hasher: AlgebraicHasher<F>,
 {
-    pub fn new(spent_circuit: &SpentCircuit<F, C, D>) -> Self {
+    pub fn new(spent_circuit: &SpentCircuit<F, C, D>) -> anyhow::Result<Self> {
+        build_cyclic_circuit(
+            &common_data_for_validity::<F, C, D>(),
+            |common_data| Self::build(spent_circuit, common_data),
+            |circuit| &circuit.data,
+        )
+    }
+
+    fn build(
+        spent_circuit: &SpentCircuit<F, C, D>,
+        mut common_data: CommonCircuitData<F, D>,
+    ) -> Self {
         let mut builder = CircuitBuilderWithKeccak::<F, D>::new(CircuitConfig::default());
         let spent_proof = spent_circuit.add_proof_target_and_verify(&mut builder);
         let spent_pis = SpentPublicInputsTarget::from_vec(&spent_proof.public_inputs);
@@ -181,7 +202,6 @@ where
         let is_not_first_step = builder.add_virtual_bool_target_safe();
         let is_first_step = builder.not(is_not_first_step);
 
-        let mut common_data = common_data_for_validity::<F, C, D>();
         let verifier_data_target = builder.add_verifier_data_public_inputs();
         common_data.num_public_inputs = builder.num_public_inputs();
         let prev_proof = builder.add_virtual_proof_with_pis(&common_data);
@@ -204,13 +224,13 @@ where
             is_first_step,
         );
         let data = builder.build();
-        assert_eq!(common_data, data.common);
         Self {
             data,
             spent_proof,
             is_not_first_step,
             prev_proof,
             verifier_data_target,
+            spent_verifier_data: spent_circuit.data.verifier_data(),
         }
     }
 
@@ -219,7 +239,20 @@ where
         spent_proof: &ProofWithPublicInputs<F, C, D>,
         prev_proof: &Option<ProofWithPublicInputs<F, C, D>>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        if cfg!(feature = "mock") {
+            return self.prove_mock(spent_proof, prev_proof);
+        }
         let mut pw = PartialWitness::<F>::new();
+        self.set_witness(&mut pw, spent_proof, prev_proof);
+        self.data.prove(pw)
+    }
+
+    pub fn set_witness(
+        &self,
+        pw: &mut impl Witness<F>,
+        spent_proof: &ProofWithPublicInputs<F, C, D>,
+        prev_proof: &Option<ProofWithPublicInputs<F, C, D>>,
+    ) {
         pw.set_verifier_data_target(&self.verifier_data_target, &self.data.verifier_only);
         pw.set_proof_with_pis_target(&self.spent_proof, spent_proof);
 
@@ -239,11 +272,44 @@ where
             pw.set_bool_target(self.is_not_first_step, true);
             pw.set_proof_with_pis_target(&self.prev_proof, prev_proof.as_ref().unwrap());
         }
+    }
 
-        self.data.prove(pw)
+    // Checks the witness natively instead of proving it.
+    fn prove_mock(
+        &self,
+        spent_proof: &ProofWithPublicInputs<F, C, D>,
+        prev_proof: &Option<ProofWithPublicInputs<F, C, D>>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        mock_verify_inner(&self.spent_verifier_data, spent_proof, false)?;
+        let spent_pis = SpentPublicInputs::from_vec(&spent_proof.public_inputs);
+        let prev_pis = match prev_proof {
+            Some(prev_proof) => {
+                self.verify(prev_proof.clone())?;
+                ValidityPublicInputs::from_pis(&prev_proof.public_inputs)
+            }
+            None => ValidityPublicInputs::default(),
+        };
+        let total_spent = prev_pis
+            .total_spent
+            .checked_add(&spent_pis.spent)
+            .ok_or_else(|| anyhow::anyhow!("total spent overflows"))?;
+        let total_deposit = spent_pis.block.total_deposit.clone();
+        ensure!(
+            total_spent <= total_deposit,
+            "total spent exceeds total deposit"
+        );
+        let pis = ValidityPublicInputs {
+            block_hash: spent_pis.block.block_hash(),
+            total_spent,
+            total_deposit,
+        };
+        mock_cyclic_proof(&self.data, pis.to_vec())
     }
 
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
+        if cfg!(feature = "mock") {
+            return mock_verify_cyclic(&self.data, &proof_with_pis);
+        }
         check_cyclic_proof_verifier_data(
             &proof_with_pis,
             &self.data.verifier_only,
@@ -305,25 +371,29 @@ where
     let _ = builder.add_many_u32(&[zero, zero, zero]);
     let _ = builder.sub_u32(zero, zero, zero);
     let _zero_limbs = [(); 8].map(|_| builder.zero_u32());
-    while builder.num_gates() < 1 << 15 {
-        builder.add_gate(NoopGate, vec![]);
-    }
     builder.build::<C>().common
 }
 
 #[cfg(test)]
 mod tests {
-    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::{
+        iop::witness::WitnessWrite,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+        recursion::dummy_circuit::cyclic_base_proof,
+    };
     use rand::Rng;
 
     use crate::{
         base_circuits::spent_circuit::{SpentCircuit, SpentValue},
         common::{address::Address, asset::Assets},
         random::transfers::generate_random_transfers,
-        utils::h256::H256,
+        utils::{
+            h256::H256,
+            tampering::{assert_tampered_witnesses_fail, Tamper},
+        },
     };
 
-    use super::ValidityCircuit;
+    use super::{ValidityCircuit, ValidityPublicInputs};
 
     const D: usize = 2;
     type C = PoseidonGoldilocksConfig;
@@ -346,7 +416,7 @@ mod tests {
         );
         let spent_proof = spent_circuit.prove(&value).unwrap();
 
-        let validity_circuit = ValidityCircuit::new(&spent_circuit);
+        let validity_circuit = ValidityCircuit::new(&spent_circuit).unwrap();
         let validity_proof = validity_circuit.prove(&spent_proof, &None).unwrap();
 
         let now = std::time::Instant::now();
@@ -356,4 +426,74 @@ mod tests {
         println!("validity circuit: {:?}", now.elapsed());
         validity_circuit.verify(validity_proof2).unwrap();
     }
+
+    #[test]
+    #[cfg_attr(feature = "mock", ignore)]
+    fn test_validity_circuit_tampered_witness() {
+        let spent_circuit = SpentCircuit::<F, C, D>::new();
+        let validity_circuit = ValidityCircuit::new(&spent_circuit).unwrap();
+        let mut rng = rand::thread_rng();
+        let recipients = vec![Address::rand(&mut rng)];
+        let transfers = generate_random_transfers::<F, _>(&mut rng, 1, 4, &recipients)[0].clone();
+        let total_deposit = Assets::rand_full(&mut rng);
+        let prev_block_hash = H256::rand(&mut rng);
+        let new_block_number: u32 = rng.gen();
+        let value = SpentValue::new::<F>(
+            &transfers,
+            &total_deposit,
+            &prev_block_hash,
+            new_block_number,
+        );
+
+        // the spent proof itself is valid, but the spent amount exceeds the deposit
+        let overspent_value = SpentValue::new::<F>(
+            &transfers,
+            &Assets::default(),
+            &prev_block_hash,
+            new_block_number,
+        );
+        // the public inputs of the base proof of the first step must be the initial ones
+        let wrong_block_hash = H256::rand(&mut rng);
+        let wrong_total_deposit = Assets::rand_full(&mut rng);
+        let tampers = vec![
+            Tamper::value(
+                "overspent amount",
+                move |value: &mut (SpentValue, ValidityPublicInputs)| {
+                    value.0 = overspent_value.clone()
+                },
+            ),
+            Tamper::value(
+                "block hash mismatch",
+                move |value: &mut (SpentValue, ValidityPublicInputs)| {
+                    value.1.block_hash = wrong_block_hash
+                },
+            ),
+            Tamper::value(
+                "total deposit mismatch",
+                move |value: &mut (SpentValue, ValidityPublicInputs)| {
+                    value.1.total_deposit = wrong_total_deposit.clone()
+                },
+            ),
+        ];
+        assert_tampered_witnesses_fail(
+            &validity_circuit.data,
+            |pw, (value, prev_pis)| {
+                let spent_proof = spent_circuit.prove(value).unwrap();
+                let base_proof = cyclic_base_proof(
+                    &validity_circuit.data.common,
+                    &validity_circuit.data.verifier_only,
+                    prev_pis.to_vec().into_iter().enumerate().collect(),
+                );
+                pw.set_verifier_data_target(
+                    &validity_circuit.verifier_data_target,
+                    &validity_circuit.data.verifier_only,
+                );
+                pw.set_proof_with_pis_target(&validity_circuit.spent_proof, &spent_proof);
+                pw.set_bool_target(validity_circuit.is_not_first_step, false);
+                pw.set_proof_with_pis_target(&validity_circuit.prev_proof, &base_proof);
+            },
+            &(value, ValidityPublicInputs::default()),
+            &tampers,
+        );
+    }
 }
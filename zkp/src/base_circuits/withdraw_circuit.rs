@@ -1,7 +1,6 @@
-use anyhow::Ok;
+use anyhow::{ensure, Ok};
 use plonky2::{
     field::{extension::Extendable, types::PrimeField64},
-    gates::noop::NoopGate,
     hash::hash_types::RichField,
     iop::{
         target::{BoolTarget, Target},
@@ -17,7 +16,6 @@ use plonky2::{
         cyclic_recursion::check_cyclic_proof_verifier_data, dummy_circuit::cyclic_base_proof,
     },
 };
-use plonky2_u32::gadgets::arithmetic_u32::CircuitBuilderU32;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -29,16 +27,20 @@ use crate::{
         extended_block_number::{ExtendedBlockNumber, ExtendedBlockNumberTarget},
         transfer_info::{TransferInfo, TransferInfoTarget},
     },
-    constants::WITHDRAW_PADDING_DEGREE,
+    constants::{MAX_WITHDRAW_BATCH_SIZE, WITHDRAW_BATCH_SIZES},
     utils::{
+        cyclic::build_cyclic_circuit,
+        dummy::DummyProof,
         logic::enforce_equal_targets_if_enabled,
+        mock::{mock_cyclic_proof, mock_proof, mock_verify_cyclic, mock_verify_inner},
         trees::merkle_tree_with_leaves::{MerkleProofWithLeaves, MerkleProofWithLeavesTarget},
+        u256::{U256Target, U256},
     },
 };
 
 use super::block_tree_circuit::{BlockTreeCircuit, BlockTreePublicInputsTarget};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WithdrawPublicInputs {
     pub recipient: Address,
@@ -191,10 +193,10 @@ pub struct WithdrawValue<F: RichField + Extendable<D>, C: GenericConfig<D, F = F
     pub is_first_step: bool,
     pub prev_pis: WithdrawPublicInputs,
     pub new_pis: WithdrawPublicInputs,
-    pub transfer_info: TransferInfo<F>,
+    pub transfer_info: Vec<TransferInfo<F>>, // sorted by ebn, at most `MAX_WITHDRAW_BATCH_SIZE`
     pub block_tree_proof: ProofWithPublicInputs<F, C, D>,
     pub block_merkle_proof_prev: MerkleProofWithLeaves<F, Block>, // merkle proof for the prev_block
-    pub block_merkle_proof_transfer: MerkleProofWithLeaves<F, Block>, // merkle proof for the transfer info
+    pub block_merkle_proof_transfer: Vec<MerkleProofWithLeaves<F, Block>>, // merkle proofs for the transfer info
 }
 
 impl<F: RichField + Extendable<D>, const D: usize, C: GenericConfig<D, F = F> + 'static>
@@ -206,39 +208,28 @@ where
         block_tree_circuit: &BlockTreeCircuit<F, C, D>,
         is_first_step: bool,
         prev_pis: WithdrawPublicInputs,
-        transfer_info: TransferInfo<F>,
+        transfer_info: Vec<TransferInfo<F>>,
         block_tree_proof: ProofWithPublicInputs<F, C, D>,
         block_merkle_proof_prev: MerkleProofWithLeaves<F, Block>,
-        block_merkle_proof_transfer: MerkleProofWithLeaves<F, Block>,
+        block_merkle_proof_transfer: Vec<MerkleProofWithLeaves<F, Block>>,
     ) -> anyhow::Result<Self> {
-        let new_recipient = prev_pis.recipient;
-        transfer_info
-            .verify()
-            .map_err(|_| anyhow::anyhow!("transfer_info is invalid"))?;
-        assert!(
-            transfer_info.transfer.recipient == new_recipient,
-            "recipient mismatch"
+        ensure!(!transfer_info.is_empty(), "transfer_info is empty");
+        ensure!(
+            transfer_info.len() <= MAX_WITHDRAW_BATCH_SIZE,
+            "too many transfers in one step: {} > {}",
+            transfer_info.len(),
+            MAX_WITHDRAW_BATCH_SIZE
+        );
+        ensure!(
+            transfer_info.len() == block_merkle_proof_transfer.len(),
+            "the number of block merkle proofs must be the same as the number of transfers"
         );
-        let (new_start_ebn, new_end_ebn, new_total_amount) = if is_first_step {
-            assert_eq!(
-                prev_pis.total_amount,
-                Assets::default(),
+        if is_first_step {
+            ensure!(
+                prev_pis.total_amount == Assets::default(),
                 "prev_total_amount must be zero"
             );
-            let new_total_amount = prev_pis.total_amount.clone() + transfer_info.transfer.asset;
-            let new_start_ebn = transfer_info.ebn();
-            let new_end_ebn = new_start_ebn;
-            (new_start_ebn, new_end_ebn, new_total_amount)
-        } else {
-            let new_total_amount = prev_pis.total_amount.clone() + transfer_info.transfer.asset;
-            let new_start_ebn = prev_pis.start_ebn;
-            let new_end_ebn = transfer_info.ebn();
-            assert!(
-                prev_pis.end_ebn < new_end_ebn,
-                "prev_end_ebn must be less than new_end_ebn"
-            );
-            (new_start_ebn, new_end_ebn, new_total_amount)
-        };
+        }
 
         block_tree_circuit.verify(block_tree_proof.clone())?;
         let block_tree_pis = BlockTreePublicInputs::from_pis(&block_tree_proof.public_inputs);
@@ -247,11 +238,44 @@ where
             prev_pis.block.block_number as usize,
             block_tree_pis.block_root,
         )?;
-        block_merkle_proof_transfer.verify(
-            &transfer_info.block,
-            transfer_info.block.block_number as usize,
-            block_tree_pis.block_root,
-        )?;
+
+        let new_recipient = prev_pis.recipient;
+        let mut new_total_amount = prev_pis.total_amount.clone();
+        let mut prev_end_ebn = if is_first_step {
+            None
+        } else {
+            Some(prev_pis.end_ebn)
+        };
+        for (transfer_info, block_merkle_proof) in
+            transfer_info.iter().zip(block_merkle_proof_transfer.iter())
+        {
+            transfer_info
+                .verify()
+                .map_err(|_| anyhow::anyhow!("transfer_info is invalid"))?;
+            ensure!(
+                transfer_info.transfer.recipient == new_recipient,
+                "recipient mismatch"
+            );
+            if let Some(prev_end_ebn) = prev_end_ebn {
+                ensure!(
+                    prev_end_ebn < transfer_info.ebn(),
+                    "ebn must be strictly increasing"
+                );
+            }
+            block_merkle_proof.verify(
+                &transfer_info.block,
+                transfer_info.block.block_number as usize,
+                block_tree_pis.block_root,
+            )?;
+            new_total_amount += transfer_info.transfer.asset;
+            prev_end_ebn = Some(transfer_info.ebn());
+        }
+        let new_start_ebn = if is_first_step {
+            transfer_info[0].ebn()
+        } else {
+            prev_pis.start_ebn
+        };
+        let new_end_ebn = transfer_info.last().unwrap().ebn();
         let new_block = block_tree_pis.block.clone();
         let new_pis = WithdrawPublicInputs {
             recipient: new_recipient,
@@ -277,32 +301,34 @@ pub struct WithdrawTarget<const D: usize> {
     is_first_step: BoolTarget,
     prev_pis: WithdrawPublicInputsTarget,
     new_pis: WithdrawPublicInputsTarget,
-    transfer_info: TransferInfoTarget,
+    transfer_info: Vec<TransferInfoTarget>,
+    is_enabled: Vec<BoolTarget>, // whether each slot of the batch holds a transfer
     block_tree_proof: ProofWithPublicInputsTarget<D>,
     block_merkle_proof_prev: MerkleProofWithLeavesTarget<BlockTarget>,
-    block_merkle_proof_transfer: MerkleProofWithLeavesTarget<BlockTarget>,
+    block_merkle_proof_transfer: Vec<MerkleProofWithLeavesTarget<BlockTarget>>,
 }
 
 impl<const D: usize> WithdrawTarget<D> {
     pub fn new<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static>(
         block_tree_circuit: &BlockTreeCircuit<F, C, D>,
         builder: &mut CircuitBuilder<F, D>,
+        batch_size: usize,
     ) -> Self
     where
         C::Hasher: AlgebraicHasher<F>,
     {
         let is_first_step = builder.add_virtual_bool_target_safe();
-        let is_not_first_step = builder.not(is_first_step);
 
-        let transfer_info = TransferInfoTarget::new(builder);
+        let transfer_info = (0..batch_size)
+            .map(|_| TransferInfoTarget::new(builder))
+            .collect::<Vec<_>>();
+        let is_enabled = (0..batch_size)
+            .map(|_| builder.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+        // the first slot always holds a transfer
+        builder.assert_one(is_enabled[0].target);
         let prev_pis = WithdrawPublicInputsTarget::new(builder);
 
-        // new_recipient = prev_recipient = transfer_info.transfer.recipient
-        prev_pis
-            .recipient
-            .connect(builder, &transfer_info.transfer.recipient);
-        let new_recipient = prev_pis.recipient.clone();
-
         // prev_total_amount == 0 if is_first_step == true
         let zero_assets = AssetsTarget::constant(builder, &Assets::default());
         enforce_equal_targets_if_enabled(
@@ -312,48 +338,80 @@ impl<const D: usize> WithdrawTarget<D> {
             is_first_step,
         );
 
-        // new_total_amount = prev_total_amount + transfer_info.transfer.amount
-        let transfer_amount = AssetsTarget::from_asset(builder, &transfer_info.transfer.amount);
-        let new_total_amount = AssetsTarget::add(builder, &prev_pis.total_amount, &transfer_amount);
-
-        // new_start_ebn == prev_start_ebn if is_not_first_step == true
-        let new_start_ebn = ExtendedBlockNumberTarget::new(builder);
-        enforce_equal_targets_if_enabled(
+        let block_tree_proof = block_tree_circuit.add_proof_target_and_verify(builder);
+        let block_tree_pis = BlockTreePublicInputsTarget::from_pis(&block_tree_proof.public_inputs);
+        let block_merkle_proof_prev = MerkleProofWithLeavesTarget::new(builder, 32);
+        block_merkle_proof_prev.verify(
             builder,
-            &prev_pis.start_ebn.to_vec(),
-            &new_start_ebn.to_vec(),
-            is_not_first_step,
+            &prev_pis.block,
+            prev_pis.block.block_number.0,
+            block_tree_pis.block_root,
         );
 
-        // constrain prev_end_ebn < new_end_ebn if is_first_step == false
-        let new_end_ebn = transfer_info.ebn(builder);
+        // The disabled slots are filled with a copy of the last transfer, so the merkle proofs
+        // are verified for every slot, and only the recipient, the amount and the ebn are masked.
+        let zero = U256Target::constant(builder, U256::default());
         let zero_ebn =
             ExtendedBlockNumberTarget::constant(builder, &ExtendedBlockNumber::default());
-        let prev_end_or_zero_ebn = ExtendedBlockNumberTarget::select(
+        let one_ebn = ExtendedBlockNumberTarget::constant(builder, &ExtendedBlockNumber::new(1));
+        let mut new_total_amount = prev_pis.total_amount.clone();
+        let mut new_end_ebn = ExtendedBlockNumberTarget::select(
             builder,
             is_first_step,
-            zero_ebn,
+            zero_ebn.clone(),
             prev_pis.end_ebn.clone(),
         );
-        prev_end_or_zero_ebn.less_than(builder, &new_end_ebn);
+        let mut block_merkle_proof_transfer = Vec::with_capacity(batch_size);
+        for (transfer_info, &enabled) in transfer_info.iter().zip(is_enabled.iter()) {
+            transfer_info.verify(builder);
+            let block_merkle_proof = MerkleProofWithLeavesTarget::new(builder, 32);
+            block_merkle_proof.verify(
+                builder,
+                &transfer_info.block,
+                transfer_info.block.block_number.0,
+                block_tree_pis.block_root,
+            );
+            block_merkle_proof_transfer.push(block_merkle_proof);
 
-        // verify block merkle proof
-        let block_tree_proof = block_tree_circuit.add_proof_target_and_verify(builder);
-        let block_tree_pis = BlockTreePublicInputsTarget::from_pis(&block_tree_proof.public_inputs);
-        let block_merkle_proof_prev = MerkleProofWithLeavesTarget::new(builder, 32);
-        let block_merkle_proof_transfer = MerkleProofWithLeavesTarget::new(builder, 32);
-        block_merkle_proof_prev.verify(
-            builder,
-            &prev_pis.block,
-            prev_pis.block.block_number.0,
-            block_tree_pis.block_root,
-        );
-        block_merkle_proof_transfer.verify(
+            // prev_recipient == transfer_info.transfer.recipient if enabled == true
+            enforce_equal_targets_if_enabled(
+                builder,
+                &prev_pis.recipient.to_vec(),
+                &transfer_info.transfer.recipient.to_vec(),
+                enabled,
+            );
+
+            // new_total_amount += transfer_info.transfer.amount if enabled == true
+            let transfer_amount = AssetsTarget::from_asset(builder, &transfer_info.transfer.amount);
+            let transfer_amount =
+                AssetsTarget(transfer_amount.0.map(|amount| {
+                    U256Target::conditionally_select(builder, amount, zero, enabled)
+                }));
+            new_total_amount = AssetsTarget::add(builder, &new_total_amount, &transfer_amount);
+
+            // new_end_ebn < ebn if enabled == true, where new_end_ebn is zero before the first transfer
+            let ebn = transfer_info.ebn(builder);
+            let lhs = ExtendedBlockNumberTarget::select(
+                builder,
+                enabled,
+                new_end_ebn.clone(),
+                zero_ebn.clone(),
+            );
+            let rhs =
+                ExtendedBlockNumberTarget::select(builder, enabled, ebn.clone(), one_ebn.clone());
+            lhs.less_than(builder, &rhs);
+            new_end_ebn = ExtendedBlockNumberTarget::select(builder, enabled, ebn, new_end_ebn);
+        }
+
+        // new_start_ebn = is_first_step ? first ebn of the batch : prev_start_ebn
+        let first_ebn = transfer_info[0].ebn(builder);
+        let new_start_ebn = ExtendedBlockNumberTarget::select(
             builder,
-            &transfer_info.block,
-            transfer_info.block.block_number.0,
-            block_tree_pis.block_root,
+            is_first_step,
+            first_ebn,
+            prev_pis.start_ebn.clone(),
         );
+        let new_recipient = prev_pis.recipient.clone();
         let new_block = block_tree_pis.block.clone();
 
         let new_pis = WithdrawPublicInputsTarget {
@@ -368,6 +426,7 @@ impl<const D: usize> WithdrawTarget<D> {
             prev_pis,
             new_pis,
             transfer_info,
+            is_enabled,
             block_tree_proof,
             block_merkle_proof_prev,
             block_merkle_proof_transfer,
@@ -384,26 +443,154 @@ impl<const D: usize> WithdrawTarget<D> {
         pw.set_bool_target(self.is_first_step, value.is_first_step);
         self.prev_pis.set_witness(pw, &value.prev_pis);
         self.new_pis.set_witness(pw, &value.new_pis);
-        self.transfer_info.set_witness(pw, &value.transfer_info);
         pw.set_proof_with_pis_target(&self.block_tree_proof, &value.block_tree_proof);
         self.block_merkle_proof_prev
             .set_witness(pw, &value.block_merkle_proof_prev);
-        self.block_merkle_proof_transfer
-            .set_witness(pw, &value.block_merkle_proof_transfer);
+
+        // pad the batch with copies of the last transfer
+        let last = value.transfer_info.len() - 1;
+        for i in 0..self.transfer_info.len() {
+            let j = i.min(last);
+            pw.set_bool_target(self.is_enabled[i], i <= last);
+            self.transfer_info[i].set_witness(pw, &value.transfer_info[j]);
+            self.block_merkle_proof_transfer[i]
+                .set_witness(pw, &value.block_merkle_proof_transfer[j]);
+        }
+    }
+}
+
+const WITHDRAW_PIS_LEN: usize = ADDRESS_VEC_LEN + ASSETS_VEC_LEN + 1 + 1 + BLOCK_VEC_LEN;
+
+/// The public inputs of a proof of `WithdrawStepCircuit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawStepPublicInputs {
+    pub prev_pis: WithdrawPublicInputs,
+    pub new_pis: WithdrawPublicInputs,
+    pub is_first_step: bool,
+}
+
+impl WithdrawStepPublicInputs {
+    pub fn to_vec<F: PrimeField64>(&self) -> Vec<F> {
+        let mut result = Vec::new();
+        result.extend(self.prev_pis.to_vec::<F>());
+        result.extend(self.new_pis.to_vec::<F>());
+        result.push(F::from_bool(self.is_first_step));
+        assert_eq!(result.len(), 2 * WITHDRAW_PIS_LEN + 1);
+        result
+    }
+
+    pub fn from_pis<F: PrimeField64>(input: &[F]) -> Self {
+        let prev_pis = WithdrawPublicInputs::from_pis(&input[0..WITHDRAW_PIS_LEN]);
+        let new_pis =
+            WithdrawPublicInputs::from_pis(&input[WITHDRAW_PIS_LEN..2 * WITHDRAW_PIS_LEN]);
+        let is_first_step = input[2 * WITHDRAW_PIS_LEN] == F::ONE;
+        Self {
+            prev_pis,
+            new_pis,
+            is_first_step,
+        }
     }
 }
 
+/// Absorbs up to `batch_size` transfers of one recipient. There is a step circuit for each size
+/// in `WITHDRAW_BATCH_SIZES`, and `WithdrawCircuit` folds their proofs into one chain.
 #[derive(Debug)]
-pub struct WithdrawCircuit<F, C, const D: usize>
+pub struct WithdrawStepCircuit<F, C, const D: usize>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
+    pub batch_size: usize,
     pub data: CircuitData<F, C, D>,
     pub target: WithdrawTarget<D>,
-    pub is_first_step: BoolTarget,
+}
+
+impl<F, C, const D: usize> WithdrawStepCircuit<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    pub fn new(block_tree_circuit: &BlockTreeCircuit<F, C, D>, batch_size: usize) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let target = WithdrawTarget::new(block_tree_circuit, &mut builder, batch_size);
+        builder.register_public_inputs(&target.prev_pis.to_vec());
+        builder.register_public_inputs(&target.new_pis.to_vec());
+        builder.register_public_input(target.is_first_step.target);
+        let data = builder.build::<C>();
+        Self {
+            batch_size,
+            data,
+            target,
+        }
+    }
+
+    pub fn prove(
+        &self,
+        value: &WithdrawValue<F, C, D>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            value.transfer_info.len() <= self.batch_size,
+            "too many transfers for the step of {} transfers: {}",
+            self.batch_size,
+            value.transfer_info.len()
+        );
+        if cfg!(feature = "mock") {
+            // the witness is checked in `WithdrawValue::new`
+            let pis = WithdrawStepPublicInputs {
+                prev_pis: value.prev_pis.clone(),
+                new_pis: value.new_pis.clone(),
+                is_first_step: value.is_first_step,
+            };
+            return mock_proof(&self.data, pis.to_vec());
+        }
+        let mut pw = PartialWitness::<F>::new();
+        self.set_witness(&mut pw, value);
+        self.data.prove(pw)
+    }
+
+    pub fn set_witness(&self, pw: &mut impl Witness<F>, value: &WithdrawValue<F, C, D>) {
+        self.target.set_witness(pw, value);
+    }
+
+    fn add_proof_target_and_conditionally_verify(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        condition: BoolTarget,
+    ) -> ProofWithPublicInputsTarget<D> {
+        let proof = builder.add_virtual_proof_with_pis(&self.data.common);
+        let vd = builder.constant_verifier_data(&self.data.verifier_only);
+        builder
+            .conditionally_verify_proof_or_dummy::<C>(condition, &proof, &vd, &self.data.common)
+            .unwrap();
+        proof
+    }
+}
+
+#[derive(Debug)]
+struct WithdrawChainTarget<const D: usize> {
+    is_step_selected: Vec<BoolTarget>,
+    step_proofs: Vec<ProofWithPublicInputsTarget<D>>,
+    prev_proof: ProofWithPublicInputsTarget<D>,
+    verifier_data_target: VerifierCircuitTarget,
+}
+
+/// The cyclic circuit of the withdrawal of one recipient. Each step verifies the previous proof
+/// and a proof of the smallest step circuit that absorbs the transfers of the step, so that the
+/// proofs form one chain with one verifier data whatever the sizes of the steps.
+#[derive(Debug)]
+pub struct WithdrawCircuit<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    pub step_circuits: Vec<WithdrawStepCircuit<F, C, D>>, // in the order of `WITHDRAW_BATCH_SIZES`
+    pub is_step_selected: Vec<BoolTarget>,
+    pub step_proofs: Vec<ProofWithPublicInputsTarget<D>>,
     pub prev_proof: ProofWithPublicInputsTarget<D>,
     pub verifier_data_target: VerifierCircuitTarget,
+    dummy_step_proofs: Vec<DummyProof<F, C, D>>,
 }
 
 impl<F, C, const D: usize> WithdrawCircuit<F, C, D>
@@ -412,22 +599,82 @@ where
     C: GenericConfig<D, F = F> + 'static,
     C::Hasher: AlgebraicHasher<F>,
 {
-    pub fn new(block_tree_circuit: &BlockTreeCircuit<F, C, D>) -> Self {
+    pub fn new(block_tree_circuit: &BlockTreeCircuit<F, C, D>) -> anyhow::Result<Self> {
+        let step_circuits = WITHDRAW_BATCH_SIZES
+            .iter()
+            .map(|&batch_size| WithdrawStepCircuit::new(block_tree_circuit, batch_size))
+            .collect::<Vec<_>>();
+        let (data, target) = build_cyclic_circuit(
+            &common_data_for_withdraw::<F, C, D>(),
+            |common_data| Self::build(&step_circuits, common_data),
+            |(data, _)| data,
+        )?;
+        let dummy_step_proofs = step_circuits
+            .iter()
+            .map(|step_circuit| DummyProof::new(&step_circuit.data.common))
+            .collect();
+        Ok(Self {
+            data,
+            step_circuits,
+            is_step_selected: target.is_step_selected,
+            step_proofs: target.step_proofs,
+            prev_proof: target.prev_proof,
+            verifier_data_target: target.verifier_data_target,
+            dummy_step_proofs,
+        })
+    }
+
+    fn build(
+        step_circuits: &[WithdrawStepCircuit<F, C, D>],
+        mut common_data: CommonCircuitData<F, D>,
+    ) -> (CircuitData<F, C, D>, WithdrawChainTarget<D>) {
         let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
-        let target = WithdrawTarget::new(block_tree_circuit, &mut builder);
-        builder.register_public_inputs(&target.new_pis.to_vec());
 
-        let mut common_data = common_data_for_withdraw::<F, C, D>();
+        // exactly one step proof is verified
+        let is_step_selected = step_circuits
+            .iter()
+            .map(|_| builder.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+        let selected = is_step_selected
+            .iter()
+            .map(|is_selected| is_selected.target)
+            .collect::<Vec<_>>();
+        let num_selected = builder.add_many(&selected);
+        builder.assert_one(num_selected);
+        let step_proofs = step_circuits
+            .iter()
+            .zip(is_step_selected.iter())
+            .map(|(step_circuit, &is_selected)| {
+                step_circuit.add_proof_target_and_conditionally_verify(&mut builder, is_selected)
+            })
+            .collect::<Vec<_>>();
+
+        // the public inputs of the selected step proof
+        let zero = builder.zero();
+        let step_pis = (0..2 * WITHDRAW_PIS_LEN + 1)
+            .map(|i| {
+                step_proofs.iter().zip(is_step_selected.iter()).fold(
+                    zero,
+                    |acc, (step_proof, is_selected)| {
+                        builder.mul_add(is_selected.target, step_proof.public_inputs[i], acc)
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        let step_prev_pis = WithdrawPublicInputsTarget::from_pis(&step_pis[0..WITHDRAW_PIS_LEN]);
+        let step_new_pis =
+            WithdrawPublicInputsTarget::from_pis(&step_pis[WITHDRAW_PIS_LEN..2 * WITHDRAW_PIS_LEN]);
+        // a boolean target of the selected step circuit
+        let is_first_step = BoolTarget::new_unsafe(step_pis[2 * WITHDRAW_PIS_LEN]);
+        builder.register_public_inputs(&step_new_pis.to_vec());
+
         let verifier_data_target = builder.add_verifier_data_public_inputs();
         common_data.num_public_inputs = builder.num_public_inputs();
 
-        let is_first_step = builder.add_virtual_bool_target_safe(); // whether this circuit uses recursive proof or not
         let is_not_first_step = builder.not(is_first_step);
-
         let prev_proof = builder.add_virtual_proof_with_pis(&common_data);
         let prev_pis = WithdrawPublicInputsTarget::from_pis(&prev_proof.public_inputs);
-
-        target.prev_pis.connect(&mut builder, &prev_pis);
+        step_prev_pis.connect(&mut builder, &prev_pis);
 
         // Verify a cyclic proof.
         builder
@@ -438,47 +685,123 @@ where
             )
             .unwrap();
         let circuit_data = builder.build::<C>();
-        debug_assert_eq!(circuit_data.common, common_data);
-        Self {
-            data: circuit_data,
-            target,
-            is_first_step,
+        let target = WithdrawChainTarget {
+            is_step_selected,
+            step_proofs,
             prev_proof,
             verifier_data_target,
-        }
+        };
+        (circuit_data, target)
     }
 
+    /// Returns the index of the smallest step circuit which absorbs `num_transfers` transfers.
+    pub fn step_index(&self, num_transfers: usize) -> anyhow::Result<usize> {
+        self.step_circuits
+            .iter()
+            .position(|step_circuit| step_circuit.batch_size >= num_transfers)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "too many transfers in one step: {} > {}",
+                    num_transfers,
+                    MAX_WITHDRAW_BATCH_SIZE
+                )
+            })
+    }
+
+    /// Proves the step with the smallest step circuit for its transfers, and folds the step
+    /// proof into the chain.
     pub fn prove(
         &self,
         value: &WithdrawValue<F, C, D>,
         prev_proof: Option<ProofWithPublicInputs<F, C, D>>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            value.is_first_step == prev_proof.is_none(),
+            "the previous proof must be given unless it is the first step"
+        );
+        let step_index = self.step_index(value.transfer_info.len())?;
+        let step_proof = self.step_circuits[step_index].prove(value)?;
+        if cfg!(feature = "mock") {
+            return self.prove_mock(step_index, &step_proof, prev_proof);
+        }
         let mut pw = PartialWitness::<F>::new();
+        self.set_witness(&mut pw, step_index, &step_proof, prev_proof);
+        self.data.prove(pw)
+    }
+
+    pub fn set_witness(
+        &self,
+        pw: &mut impl Witness<F>,
+        step_index: usize,
+        step_proof: &ProofWithPublicInputs<F, C, D>,
+        prev_proof: Option<ProofWithPublicInputs<F, C, D>>,
+    ) {
         pw.set_verifier_data_target(&self.verifier_data_target, &self.data.verifier_only);
-        self.target.set_witness(&mut pw, value);
+        for (i, (&is_selected, step_proof_target)) in self
+            .is_step_selected
+            .iter()
+            .zip(self.step_proofs.iter())
+            .enumerate()
+        {
+            pw.set_bool_target(is_selected, i == step_index);
+            let proof = if i == step_index {
+                step_proof
+            } else {
+                &self.dummy_step_proofs[i].proof
+            };
+            pw.set_proof_with_pis_target::<C, D>(step_proof_target, proof);
+        }
         if let Some(prev_proof) = prev_proof {
-            pw.set_bool_target(self.is_first_step, false);
             pw.set_proof_with_pis_target::<C, D>(&self.prev_proof, &prev_proof);
         } else {
-            let init_pis = WithdrawPublicInputs {
-                recipient: value.transfer_info.transfer.recipient,
-                total_amount: Assets::default(),
-                start_ebn: ExtendedBlockNumber::default(),
-                end_ebn: ExtendedBlockNumber::default(),
-                block: Block::default(),
-            };
+            let step_pis = WithdrawStepPublicInputs::from_pis(&step_proof.public_inputs);
             let dummy_proof = cyclic_base_proof(
                 &self.data.common,
                 &self.data.verifier_only,
-                init_pis.to_vec().into_iter().enumerate().collect(),
+                step_pis.prev_pis.to_vec().into_iter().enumerate().collect(),
             );
-            pw.set_bool_target(self.is_first_step, true);
             pw.set_proof_with_pis_target::<C, D>(&self.prev_proof, &dummy_proof);
         };
-        self.data.prove(pw)
+    }
+
+    // Checks the step proof and the previous proof natively instead of proving the step.
+    fn prove_mock(
+        &self,
+        step_index: usize,
+        step_proof: &ProofWithPublicInputs<F, C, D>,
+        prev_proof: Option<ProofWithPublicInputs<F, C, D>>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        mock_verify_inner(
+            &self.step_circuits[step_index].data.verifier_data(),
+            step_proof,
+            false,
+        )?;
+        let step_pis = WithdrawStepPublicInputs::from_pis(&step_proof.public_inputs);
+        let prev_pis = match prev_proof {
+            Some(prev_proof) => {
+                self.verify(&prev_proof)?;
+                WithdrawPublicInputs::from_pis(&prev_proof.public_inputs)
+            }
+            None => WithdrawPublicInputs {
+                recipient: step_pis.prev_pis.recipient,
+                total_amount: Assets::default(),
+                start_ebn: ExtendedBlockNumber::default(),
+                end_ebn: ExtendedBlockNumber::default(),
+                block: Block::default(),
+            },
+        };
+        ensure!(prev_pis == step_pis.prev_pis, "prev public inputs mismatch");
+        ensure!(
+            prev_pis.end_ebn < step_pis.new_pis.end_ebn,
+            "prev_end_ebn must be less than new_end_ebn"
+        );
+        mock_cyclic_proof(&self.data, step_pis.new_pis.to_vec())
     }
 
     pub fn verify(&self, proof_with_pis: &ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
+        if cfg!(feature = "mock") {
+            return mock_verify_cyclic(&self.data, proof_with_pis);
+        }
         check_cyclic_proof_verifier_data(
             &proof_with_pis,
             &self.data.verifier_only,
@@ -537,13 +860,6 @@ where
         circuit_digest: builder.add_virtual_hash(),
     };
     builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
-    let zero = builder.zero_u32();
-    let _ = builder.add_many_u32(&[zero, zero, zero]);
-    let _ = builder.sub_u32(zero, zero, zero);
-    let _zero_limbs = [(); 8].map(|_| builder.zero_u32());
-    while builder.num_gates() < 1 << WITHDRAW_PADDING_DEGREE {
-        builder.add_gate(NoopGate, vec![]);
-    }
     builder.build::<C>().common
 }
 
@@ -552,13 +868,19 @@ mod tests {
     use crate::{
         base_circuits::block_tree_circuit::{BlockTreeCircuit, BlockTreeValue},
         common::{
-            address::Address, asset::Assets, block::Block,
-            extended_block_number::ExtendedBlockNumber, transfer::Transfer,
+            address::Address,
+            asset::{Asset, Assets},
+            block::Block,
+            extended_block_number::ExtendedBlockNumber,
+            transfer::Transfer,
             transfer_info::TransferInfo,
         },
-        constants::TRANSFER_TREE_HEIGHT,
+        constants::{MAX_WITHDRAW_BATCH_SIZE, TRANSFER_TREE_HEIGHT},
         random::transfers::generate_random_transfers,
-        utils::trees::merkle_tree_with_leaves::MerkleTreeWithLeaves,
+        utils::{
+            tampering::{assert_tampered_witnesses_fail, tamper_merkle_sibling, Tamper},
+            trees::merkle_tree_with_leaves::MerkleTreeWithLeaves,
+        },
     };
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -576,7 +898,7 @@ mod tests {
         let transfers_vec =
             generate_random_transfers::<F, _>(&mut rng, latest_block_number, 4, &[recipient]);
 
-        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new();
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new().unwrap();
         let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
         block_tree.push(Block::default());
         let mut block_tree_proof = None;
@@ -631,58 +953,179 @@ mod tests {
             prev_block = block;
         }
 
-        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit);
+        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit).unwrap();
 
+        // withdraw the first transfer alone, and then the rest in batches
+        let (first, rest) = transfer_info_vec.split_at(1);
         let mut withdraw_proof = None;
-        for transfer_info in transfer_info_vec {
-            if withdraw_proof.is_none() {
-                let withdraw_pis = WithdrawPublicInputs {
-                    recipient: transfer_info.transfer.recipient,
-                    total_amount: Assets::default(),
-                    start_ebn: ExtendedBlockNumber::default(),
-                    end_ebn: ExtendedBlockNumber::default(),
-                    block: Block::default(),
-                };
-                let block_merkle_proof_prev = block_tree.prove(0);
-                let block_merkle_proof_transfer =
-                    block_tree.prove(transfer_info.block.block_number as usize);
-                let withdraw_value = WithdrawValue::new(
-                    &block_tree_circuit,
-                    true,
-                    withdraw_pis,
-                    transfer_info,
-                    block_tree_proof.clone().unwrap(),
-                    block_merkle_proof_prev,
-                    block_merkle_proof_transfer,
-                )
-                .unwrap();
-                withdraw_proof = Some(
-                    withdraw_circuit
-                        .prove(&withdraw_value, withdraw_proof)
-                        .unwrap(),
-                );
-            } else {
-                let withdraw_pis =
-                    WithdrawPublicInputs::from_pis(&withdraw_proof.clone().unwrap().public_inputs);
-                let block_merkle_proof_prev =
-                    block_tree.prove(withdraw_pis.block.block_number as usize);
-                let block_merkle_proof_transfer =
-                    block_tree.prove(transfer_info.block.block_number as usize);
-                let withdraw_value = WithdrawValue::new(
-                    &block_tree_circuit,
-                    false,
-                    withdraw_pis,
-                    transfer_info,
-                    block_tree_proof.clone().unwrap(),
-                    block_merkle_proof_prev,
-                    block_merkle_proof_transfer,
-                );
-                withdraw_proof = Some(
-                    withdraw_circuit
-                        .prove(&withdraw_value.unwrap(), withdraw_proof)
-                        .unwrap(),
-                );
-            }
+        let mut withdraw_pis = WithdrawPublicInputs {
+            recipient,
+            total_amount: Assets::default(),
+            start_ebn: ExtendedBlockNumber::default(),
+            end_ebn: ExtendedBlockNumber::default(),
+            block: Block::default(),
+        };
+        for batch in std::iter::once(first).chain(rest.chunks(MAX_WITHDRAW_BATCH_SIZE)) {
+            let block_merkle_proof_prev =
+                block_tree.prove(withdraw_pis.block.block_number as usize);
+            let block_merkle_proof_transfer = batch
+                .iter()
+                .map(|transfer_info| block_tree.prove(transfer_info.block.block_number as usize))
+                .collect();
+            let withdraw_value = WithdrawValue::new(
+                &block_tree_circuit,
+                withdraw_proof.is_none(),
+                withdraw_pis,
+                batch.to_vec(),
+                block_tree_proof.clone().unwrap(),
+                block_merkle_proof_prev,
+                block_merkle_proof_transfer,
+            )
+            .unwrap();
+            withdraw_proof = Some(
+                withdraw_circuit
+                    .prove(&withdraw_value, withdraw_proof)
+                    .unwrap(),
+            );
+            withdraw_pis =
+                WithdrawPublicInputs::from_pis(&withdraw_proof.as_ref().unwrap().public_inputs);
+            assert_eq!(withdraw_pis, withdraw_value.new_pis);
+        }
+        let total_amount = transfer_info_vec
+            .iter()
+            .fold(Assets::default(), |acc, t| acc + t.transfer.asset);
+        assert_eq!(withdraw_pis.total_amount, total_amount);
+        assert_eq!(
+            withdraw_pis.end_ebn,
+            transfer_info_vec.last().unwrap().ebn()
+        );
+    }
+
+    #[test]
+    #[cfg_attr(feature = "mock", ignore)]
+    fn test_withdraw_circuit_tampered_witness() {
+        let mut rng = rand::thread_rng();
+        let recipient = Address::rand(&mut rng);
+        let transfers = (0..2)
+            .map(|_| Transfer {
+                recipient,
+                asset: Asset::rand(&mut rng),
+            })
+            .collect::<Vec<_>>();
+
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new().unwrap();
+        let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
+        block_tree.push(Block::default());
+        let mut transfer_tree = MerkleTreeWithLeaves::<F, Transfer>::new(TRANSFER_TREE_HEIGHT);
+        for transfer in transfers.iter() {
+            transfer_tree.push(*transfer);
         }
+        let block = Block {
+            prev_block_hash: Block::default().block_hash(),
+            transfer_tree_root: transfer_tree.get_root().into(),
+            total_deposit: Assets::default(),
+            block_number: 1,
+        };
+        let block_merkle_proof = block_tree.prove(block.block_number as usize);
+        let prev_block_root = block_tree.get_root();
+        block_tree.push(block.clone());
+        let block_tree_value = BlockTreeValue::new(
+            block.clone(),
+            prev_block_root,
+            block_tree.get_root(),
+            block_merkle_proof,
+        );
+        let block_tree_proof = block_tree_circuit.prove(&block_tree_value, &None).unwrap();
+        let transfer_info = transfers
+            .iter()
+            .enumerate()
+            .map(|(transfer_index, transfer)| TransferInfo {
+                transfer: *transfer,
+                transfer_index,
+                transfer_merkle_proof: transfer_tree.prove(transfer_index),
+                block: block.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit).unwrap();
+        let init_pis = WithdrawPublicInputs {
+            recipient,
+            total_amount: Assets::default(),
+            start_ebn: ExtendedBlockNumber::default(),
+            end_ebn: ExtendedBlockNumber::default(),
+            block: Block::default(),
+        };
+        let withdraw_value = |is_first_step: bool,
+                              prev_pis: &WithdrawPublicInputs,
+                              transfer_info: &TransferInfo<F>| {
+            WithdrawValue::new(
+                &block_tree_circuit,
+                is_first_step,
+                prev_pis.clone(),
+                vec![transfer_info.clone()],
+                block_tree_proof.clone(),
+                block_tree.prove(prev_pis.block.block_number as usize),
+                vec![block_tree.prove(transfer_info.block.block_number as usize)],
+            )
+            .unwrap()
+        };
+
+        // withdraw transfer_info[0] and then transfer_info[1]
+        let first_value = withdraw_value(true, &init_pis, &transfer_info[0]);
+        let second_value = withdraw_value(false, &first_value.new_pis, &transfer_info[1]);
+
+        // withdraw transfer_info[1] and then transfer_info[0]
+        let swapped_first_value = withdraw_value(true, &init_pis, &transfer_info[1]);
+        let prev_pis = swapped_first_value.new_pis.clone();
+        let swapped_second_value = WithdrawValue {
+            is_first_step: false,
+            new_pis: WithdrawPublicInputs {
+                recipient,
+                total_amount: prev_pis.total_amount.clone() + transfer_info[0].transfer.asset,
+                start_ebn: prev_pis.start_ebn,
+                end_ebn: transfer_info[0].ebn(),
+                block: block.clone(),
+            },
+            block_merkle_proof_prev: block_tree.prove(prev_pis.block.block_number as usize),
+            prev_pis,
+            transfer_info: vec![transfer_info[0].clone()],
+            ..second_value.clone()
+        };
+
+        // the checks on the transfers are done by the step circuits
+        let step_circuit = &withdraw_circuit.step_circuits[withdraw_circuit.step_index(1).unwrap()];
+        assert_eq!(step_circuit.batch_size, 1);
+        type Value = WithdrawValue<F, C, D>;
+        let wrong_recipient = Address::rand(&mut rng);
+        let tampers = vec![
+            Tamper::value("swapped EBN order", move |value: &mut Value| {
+                *value = swapped_second_value.clone();
+            }),
+            Tamper::value("recipient mismatch", move |value: &mut Value| {
+                value.prev_pis.recipient = wrong_recipient;
+                value.new_pis.recipient = wrong_recipient;
+            }),
+            Tamper::value("wrong merkle sibling", |value: &mut Value| {
+                tamper_merkle_sibling(&mut value.block_merkle_proof_transfer[0]);
+            }),
+            Tamper::value("overspent amount", |value: &mut Value| {
+                value.new_pis.total_amount =
+                    value.new_pis.total_amount.clone() + value.transfer_info[0].transfer.asset;
+            }),
+            Tamper::non_canonical_u32(
+                "non-canonical u32 limb",
+                step_circuit.target.transfer_info[0]
+                    .transfer
+                    .amount
+                    .amount
+                    .0[7],
+            ),
+        ];
+        assert_tampered_witnesses_fail(
+            &step_circuit.data,
+            |pw, value: &Value| step_circuit.set_witness(pw, value),
+            &second_value,
+            &tampers,
+        );
     }
 }
@@ -0,0 +1,202 @@
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use serde::Serialize;
+use stark_verifier::bn254_poseidon::plonky2_config::{
+    standard_inner_stark_verifier_config, standard_stark_verifier_config,
+    Bn254PoseidonGoldilocksConfig,
+};
+use zkp::{
+    base_circuits::{
+        block_tree_circuit::{BlockTreeCircuit, BlockTreeValue},
+        spent_circuit::{SpentCircuit, SpentPublicInputs, SpentValue},
+        validity_circuit::ValidityCircuit,
+        withdraw_circuit::{WithdrawCircuit, WithdrawPublicInputs, WithdrawValue},
+    },
+    common::{
+        address::Address, asset::Assets, block::Block, extended_block_number::ExtendedBlockNumber,
+        transfer::Transfer, transfer_info::TransferInfo,
+    },
+    constants::{NUM_ASSETS, TRANSFER_TREE_HEIGHT},
+    random::transfers::generate_random_transfers,
+    tree_circuits::{
+        settlement_leaf_circuit::SettlementLeafCircuit,
+        settlement_tree_circuit::{common_data_for_settlement_tree_circuit, SettlementTreeCircuit},
+    },
+    utils::{circuit_report::CircuitReport, trees::merkle_tree_with_leaves::MerkleTreeWithLeaves},
+    wrap_circuits::{wrap::WrapCircuit, wrap2::Wrap2Circuit},
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type OuterC = Bn254PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    transfer_tree_height: usize,
+    num_assets: usize,
+    circuits: Vec<CircuitReport>,
+}
+
+// Builds every circuit, proves one block and one settlement through them,
+// and prints the size and the proving cost of each circuit as JSON.
+fn main() -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !cfg!(feature = "mock"),
+        "the circuit report needs real proofs, build it without the mock feature"
+    );
+    let mut rng = rand::thread_rng();
+    let recipient = Address::rand(&mut rng);
+    let transfers = generate_random_transfers::<F, _>(&mut rng, 1, 4, &[recipient])[0].clone();
+    let deposit = Assets::rand_full(&mut rng);
+
+    // spent
+    let spent_circuit = SpentCircuit::<F, C, D>::new();
+    let mut spent_report = CircuitReport::new("spent", &spent_circuit.data)
+        .with_recursive_verifier_gates(&spent_circuit.data);
+    let genesis_block = Block::default();
+    let spent_value = SpentValue::new::<F>(
+        &transfers,
+        &deposit,
+        &genesis_block.block_hash(),
+        genesis_block.block_number + 1,
+    );
+    let spent_proof =
+        spent_report.measure(&spent_circuit.data, || spent_circuit.prove(&spent_value))?;
+    let block = SpentPublicInputs::from_vec(&spent_proof.public_inputs).block;
+
+    // validity
+    let validity_circuit = ValidityCircuit::<F, C, D>::new(&spent_circuit)?;
+    let mut validity_report = CircuitReport::new("validity", &validity_circuit.data)
+        .with_recursive_verifier_gates(&validity_circuit.data);
+    let validity_proof = validity_report.measure(&validity_circuit.data, || {
+        validity_circuit.prove(&spent_proof, &None)
+    })?;
+
+    // block tree
+    let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new()?;
+    let mut block_tree_report = CircuitReport::new("block_tree", &block_tree_circuit.data)
+        .with_recursive_verifier_gates(&block_tree_circuit.data);
+    let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
+    block_tree.push(genesis_block.clone());
+    let block_merkle_proof = block_tree.prove(block.block_number as usize);
+    let prev_block_root = block_tree.get_root();
+    block_tree.push(block.clone());
+    let block_tree_value = BlockTreeValue::new(
+        block.clone(),
+        prev_block_root,
+        block_tree.get_root(),
+        block_merkle_proof,
+    );
+    let block_tree_proof = block_tree_report.measure(&block_tree_circuit.data, || {
+        block_tree_circuit.prove(&block_tree_value, &None)
+    })?;
+
+    // withdraw
+    let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit)?;
+    let mut withdraw_report = CircuitReport::new("withdraw", &withdraw_circuit.data)
+        .with_recursive_verifier_gates(&withdraw_circuit.data);
+    // the sizes of the step circuits, whose proofs are folded into the withdraw proofs
+    let withdraw_step_reports = withdraw_circuit
+        .step_circuits
+        .iter()
+        .map(|step_circuit| {
+            let name = format!("withdraw_step_{}", step_circuit.batch_size);
+            CircuitReport::new(&name, &step_circuit.data)
+                .with_recursive_verifier_gates(&step_circuit.data)
+        })
+        .collect::<Vec<_>>();
+    let mut transfer_tree = MerkleTreeWithLeaves::<F, Transfer>::new(TRANSFER_TREE_HEIGHT);
+    for transfer in transfers.iter() {
+        transfer_tree.push(*transfer);
+    }
+    let transfer_info = TransferInfo {
+        transfer: transfers[0],
+        transfer_index: 0,
+        transfer_merkle_proof: transfer_tree.prove(0),
+        block: block.clone(),
+    };
+    let init_pis = WithdrawPublicInputs {
+        recipient,
+        total_amount: Assets::default(),
+        start_ebn: ExtendedBlockNumber::default(),
+        end_ebn: ExtendedBlockNumber::default(),
+        block: genesis_block.clone(),
+    };
+    let withdraw_value = WithdrawValue::new(
+        &block_tree_circuit,
+        true,
+        init_pis,
+        vec![transfer_info.clone()],
+        block_tree_proof.clone(),
+        block_tree.prove(genesis_block.block_number as usize),
+        vec![block_tree.prove(block.block_number as usize)],
+    )?;
+    let withdraw_proof = withdraw_report.measure(&withdraw_circuit.data, || {
+        withdraw_circuit.prove(&withdraw_value, None)
+    })?;
+
+    // settlement leaf
+    let settlement_leaf_circuit = SettlementLeafCircuit::<F, C, D>::new(&withdraw_circuit);
+    let mut settlement_leaf_report =
+        CircuitReport::new("settlement_leaf", &settlement_leaf_circuit.data)
+            .with_recursive_verifier_gates(&settlement_leaf_circuit.data);
+    let settlement_leaf_proof =
+        settlement_leaf_report.measure(&settlement_leaf_circuit.data, || {
+            settlement_leaf_circuit.prove(
+                &block_tree.get_root(),
+                &block_tree.prove(block.block_number as usize),
+                &block_tree.prove(block.block_number as usize),
+                &withdraw_proof,
+                &transfer_info,
+            )
+        })?;
+
+    // settlement tree
+    let mut common_data = common_data_for_settlement_tree_circuit::<F, C, D>();
+    let settlement_tree_circuit =
+        SettlementTreeCircuit::<F, C, D>::new(&settlement_leaf_circuit, &mut common_data)?;
+    let mut settlement_tree_report =
+        CircuitReport::new("settlement_tree", &settlement_tree_circuit.data)
+            .with_recursive_verifier_gates(&settlement_tree_circuit.data);
+    let settlement_tree_proof = settlement_tree_report
+        .measure(&settlement_tree_circuit.data, || {
+            settlement_tree_circuit.prove(Some(settlement_leaf_proof), None)
+        })?;
+
+    // wrap
+    let wrap_circuit = WrapCircuit::<F, C, D>::new(
+        standard_inner_stark_verifier_config(),
+        &validity_circuit,
+        &block_tree_circuit,
+        &settlement_tree_circuit,
+    );
+    let mut wrap_report = CircuitReport::new("wrap", &wrap_circuit.data)
+        .with_recursive_verifier_gates(&wrap_circuit.data);
+    let wrap_proof = wrap_report.measure(&wrap_circuit.data, || {
+        wrap_circuit.prove(validity_proof, block_tree_proof, settlement_tree_proof)
+    })?;
+
+    // wrap2 is verified by the halo2 verifier, so its recursion overhead is not measured
+    let wrap2_circuit =
+        Wrap2Circuit::<F, C, OuterC, D>::new(standard_stark_verifier_config(), &wrap_circuit);
+    let mut wrap2_report = CircuitReport::new("wrap2", &wrap2_circuit.data);
+    wrap2_report.measure(&wrap2_circuit.data, || wrap2_circuit.prove(&wrap_proof))?;
+
+    let mut circuits = vec![spent_report, validity_report, block_tree_report];
+    circuits.extend(withdraw_step_reports);
+    circuits.extend([
+        withdraw_report,
+        settlement_leaf_report,
+        settlement_tree_report,
+        wrap_report,
+        wrap2_report,
+    ]);
+    let report = Report {
+        transfer_tree_height: TRANSFER_TREE_HEIGHT,
+        num_assets: NUM_ASSETS,
+        circuits,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
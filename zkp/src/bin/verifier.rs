@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use stark_verifier::bn254_poseidon::plonky2_config::{
+    standard_inner_stark_verifier_config, standard_stark_verifier_config,
+    Bn254PoseidonGoldilocksConfig,
+};
+use zkp::{
+    base_circuits::{
+        block_tree_circuit::BlockTreeCircuit, spent_circuit::SpentCircuit,
+        validity_circuit::ValidityCircuit,
+    },
+    processors::{settlement_processor::SettlementProcessor, wrap_processor::WrapProcessor},
+    serialization::serialized_proof::{CircuitKind, SerializedProof},
+    verifier::verifier_data::{
+        describe_public_inputs, export_verifier_data, load_verifier_data, verify_serialized_proof,
+    },
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type OuterC = Bn254PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+const USAGE: &str = "usage:
+  verifier export <dir>
+  verifier verify <dir> <kind> <proof-file>
+
+kind is one of spent, validity, block-tree, withdraw, settlement, wrap2.
+The proof file contains the hex encoded proof, optionally as a JSON string.";
+
+// Builds every circuit and writes the data needed to verify its proofs to `dir`.
+fn export(dir: &Path) -> anyhow::Result<()> {
+    let spent_circuit = SpentCircuit::<F, C, D>::new();
+    export_verifier_data(dir, CircuitKind::Spent, &spent_circuit.data)?;
+    let validity_circuit = ValidityCircuit::<F, C, D>::new(&spent_circuit)?;
+    export_verifier_data(dir, CircuitKind::Validity, &validity_circuit.data)?;
+    let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new()?;
+    export_verifier_data(dir, CircuitKind::BlockTree, &block_tree_circuit.data)?;
+    let settlement_processor = SettlementProcessor::<F, C, D>::new(&block_tree_circuit)?;
+    export_verifier_data(
+        dir,
+        CircuitKind::Withdraw,
+        &settlement_processor.withdraw_circuit.data,
+    )?;
+    export_verifier_data(
+        dir,
+        CircuitKind::Settlement,
+        &settlement_processor
+            .settlement_tree_processor
+            .node_circuit
+            .data,
+    )?;
+    let wrap_processor = WrapProcessor::<F, C, OuterC, D>::new(
+        standard_inner_stark_verifier_config(),
+        standard_stark_verifier_config(),
+        &validity_circuit,
+        &block_tree_circuit,
+        &settlement_processor,
+    );
+    export_verifier_data(dir, CircuitKind::Wrap2, &wrap_processor.wrap2_circuit.data)?;
+    println!("exported the verifier data to {}", dir.display());
+    Ok(())
+}
+
+fn read_proof(path: &Path) -> anyhow::Result<SerializedProof> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let content = content.trim();
+    let json = if content.starts_with('"') {
+        content.to_string()
+    } else {
+        format!("\"{}\"", content)
+    };
+    serde_json::from_str(&json).context("the proof is not hex encoded")
+}
+
+fn verify<InnerC: GenericConfig<D, F = F>>(
+    dir: &Path,
+    kind: CircuitKind,
+    proof: &SerializedProof,
+) -> anyhow::Result<()> {
+    let data = load_verifier_data::<F, InnerC, D>(dir, kind)?;
+    let proof = verify_serialized_proof(kind, &data, proof)?;
+    println!("the {} proof is valid", kind);
+    println!("{}", describe_public_inputs(kind, &proof.public_inputs));
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["export", dir] => export(&PathBuf::from(dir)),
+        ["verify", dir, kind, proof_file] => {
+            let dir = PathBuf::from(dir);
+            let kind: CircuitKind = kind.parse()?;
+            let proof = read_proof(Path::new(proof_file))?;
+            match kind {
+                CircuitKind::Wrap2 => verify::<OuterC>(&dir, kind, &proof),
+                _ => verify::<C>(&dir, kind, &proof),
+            }
+        }
+        _ => bail!("{}", USAGE),
+    }
+}
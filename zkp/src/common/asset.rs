@@ -30,6 +30,8 @@ use crate::utils::{
 
 use crate::constants::NUM_ASSETS;
 
+use super::asset_registry::{AssetDisplay, AssetRegistry, AssetsDisplay};
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Asset {
@@ -37,6 +39,12 @@ pub struct Asset {
     pub amount: U256,
 }
 
+impl Display for Asset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (asset {})", self.amount, self.asset_id)
+    }
+}
+
 impl PartialOrd for Asset {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         if self.asset_id != other.asset_id {
@@ -48,6 +56,15 @@ impl PartialOrd for Asset {
 }
 
 impl Asset {
+    /// Displays the amount with the decimals and the symbol of the token in `registry`,
+    /// e.g. "1.5 ETH". `Display` shows the raw amount, since it has no registry.
+    pub fn display_with<'a>(&'a self, registry: &'a AssetRegistry) -> AssetDisplay<'a> {
+        AssetDisplay {
+            asset: self,
+            registry,
+        }
+    }
+
     pub fn rand<T: Rng>(rng: &mut T) -> Self {
         let mut amount_not_full = U256::rand(rng);
         amount_not_full.0[0] = 0;
@@ -158,6 +175,15 @@ pub const ASSETS_VEC_LEN: usize = 8 * NUM_ASSETS;
 pub struct Assets(pub [U256; NUM_ASSETS]);
 
 impl Assets {
+    /// Displays the non-zero amounts with the decimals and the symbols of the tokens in
+    /// `registry`, e.g. "[1.5 ETH, 3 (asset 1)]".
+    pub fn display_with<'a>(&'a self, registry: &'a AssetRegistry) -> AssetsDisplay<'a> {
+        AssetsDisplay {
+            assets: self,
+            registry,
+        }
+    }
+
     pub fn to_vec<F: Field64>(&self) -> Vec<F> {
         let result = self.0.iter().flat_map(|v| v.to_vec()).collect::<Vec<_>>();
         assert_eq!(result.len(), ASSETS_VEC_LEN);
@@ -200,6 +226,36 @@ impl Assets {
         let amounts = (0..NUM_ASSETS).map(|_| U256::rand(rng)).collect::<Vec<_>>();
         Self(amounts.try_into().unwrap())
     }
+
+    /// Element-wise `self + other`. Returns `None` if any amount overflows.
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let amounts = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| a.checked_add(*b))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self(amounts.try_into().unwrap()))
+    }
+
+    /// Element-wise `self - other`. Returns `None` if any amount underflows.
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        let amounts = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| a.checked_sub(*b))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self(amounts.try_into().unwrap()))
+    }
+
+    pub fn checked_add_asset(&self, asset: &Asset) -> Option<Self> {
+        self.checked_add(&Self::from_asset(asset))
+    }
+
+    pub fn checked_sub_asset(&self, asset: &Asset) -> Option<Self> {
+        self.checked_sub(&Self::from_asset(asset))
+    }
 }
 
 impl PartialOrd for Assets {
@@ -448,3 +504,45 @@ impl AssetsTarget {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Asset, Assets};
+    use crate::utils::u256::U256;
+
+    #[test]
+    fn test_asset_serialization() {
+        let asset = Asset::rand(&mut rand::thread_rng());
+        let json = serde_json::to_string(&asset).unwrap();
+        let recovered: Asset = serde_json::from_str(&json).unwrap();
+        assert_eq!(asset, recovered);
+    }
+
+    #[test]
+    fn test_assets_checked_arithmetic() {
+        let mut rng = rand::thread_rng();
+        let x = Assets::rand(&mut rng);
+        let y = Assets::rand(&mut rng);
+
+        let sum = x.checked_add(&y).unwrap();
+        assert_eq!(sum, &x + &y);
+        assert_eq!(sum.checked_sub(&y).unwrap(), x);
+
+        let mut full = Assets::default();
+        full.0[0] = U256::max();
+        assert_eq!(
+            full.checked_add(&Assets::from_asset(&Asset::default())),
+            Some(full.clone())
+        );
+        let one = Asset {
+            asset_id: 0,
+            amount: U256::from(1u64),
+        };
+        assert_eq!(full.checked_add_asset(&one), None);
+        assert_eq!(Assets::default().checked_sub_asset(&one), None);
+        assert_eq!(
+            full.checked_sub_asset(&one).unwrap().0[0],
+            U256::max() - U256::from(1u64)
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+
+use crate::{constants::NUM_ASSETS, utils::u256::U256};
+
+use super::{
+    address::Address,
+    asset::{Asset, Assets},
+};
+
+/// Metadata of the ERC20 token of an asset id.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub asset_id: u32,
+    pub token_address: Address,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// Maps asset ids to token metadata.
+/// An empty registry accepts every asset id in `0..NUM_ASSETS`, so a deployment without
+/// a config behaves as before.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetRegistry {
+    tokens: BTreeMap<u32, TokenInfo>,
+}
+
+impl AssetRegistry {
+    pub fn new(tokens: Vec<TokenInfo>) -> anyhow::Result<Self> {
+        let mut registry = Self::default();
+        for token in tokens {
+            ensure!(
+                (token.asset_id as usize) < NUM_ASSETS,
+                "asset id {} is out of range 0..{}",
+                token.asset_id,
+                NUM_ASSETS
+            );
+            let asset_id = token.asset_id;
+            ensure!(
+                registry.tokens.insert(asset_id, token).is_none(),
+                "asset id {} is registered twice",
+                asset_id
+            );
+        }
+
+        Ok(registry)
+    }
+
+    /// Loads a JSON array of `TokenInfo`.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let tokens: Vec<TokenInfo> = serde_json::from_str(json)?;
+        Self::new(tokens)
+    }
+
+    pub fn from_file(path: &str) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read asset registry {}: {}", path, e))?;
+        Self::from_json(&json)
+    }
+
+    /// Loads the file at `ASSET_REGISTRY_PATH`, or returns an empty registry if it is not set.
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("ASSET_REGISTRY_PATH") {
+            Ok(path) => Self::from_file(&path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn get(&self, asset_id: u32) -> Option<&TokenInfo> {
+        self.tokens.get(&asset_id)
+    }
+
+    pub fn tokens(&self) -> Vec<TokenInfo> {
+        self.tokens.values().cloned().collect()
+    }
+
+    pub fn validate_asset_id(&self, asset_id: u32) -> anyhow::Result<()> {
+        ensure!(
+            (asset_id as usize) < NUM_ASSETS,
+            "asset id {} is out of range 0..{}",
+            asset_id,
+            NUM_ASSETS
+        );
+        ensure!(
+            self.is_empty() || self.tokens.contains_key(&asset_id),
+            "asset id {} is not registered",
+            asset_id
+        );
+        Ok(())
+    }
+
+    pub fn validate_asset(&self, asset: &Asset) -> anyhow::Result<()> {
+        self.validate_asset_id(asset.asset_id)
+    }
+
+    /// Rejects non-zero amounts of unknown asset ids.
+    pub fn validate_assets(&self, assets: &Assets) -> anyhow::Result<()> {
+        for (asset_id, amount) in assets.0.iter().enumerate() {
+            if *amount != U256::default() {
+                self.validate_asset_id(asset_id as u32)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Formats the amount with the decimals and the symbol of the token, e.g. "1.5 ETH".
+    /// Returns `None` if the asset id is not registered.
+    pub fn format_amount(&self, asset_id: u32, amount: U256) -> Option<String> {
+        self.get(asset_id).map(|token| {
+            format!(
+                "{} {}",
+                amount.to_string_with_decimals(token.decimals as usize),
+                token.symbol
+            )
+        })
+    }
+
+    pub fn format_asset(&self, asset: &Asset) -> FormattedAsset {
+        FormattedAsset {
+            asset_id: asset.asset_id,
+            amount: asset.amount,
+            formatted_amount: self.format_amount(asset.asset_id, asset.amount),
+        }
+    }
+
+    /// Formats the non-zero amounts of `assets`.
+    pub fn format_assets(&self, assets: &Assets) -> Vec<FormattedAsset> {
+        assets
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, amount)| **amount != U256::default())
+            .map(|(asset_id, amount)| {
+                self.format_asset(&Asset {
+                    asset_id: asset_id as u32,
+                    amount: *amount,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Human-readable view of an `Asset`, returned next to the raw values by the API.
+/// `formatted_amount` is `None` if the asset id is not registered.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedAsset {
+    pub asset_id: u32,
+    pub amount: U256,
+    pub formatted_amount: Option<String>,
+}
+
+impl Display for FormattedAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.formatted_amount {
+            Some(s) => write!(f, "{}", s),
+            None => write!(f, "{} (asset {})", self.amount, self.asset_id),
+        }
+    }
+}
+
+/// Displays an `Asset` with the decimals and the symbol of its token. See `Asset::display_with`.
+pub struct AssetDisplay<'a> {
+    pub(super) asset: &'a Asset,
+    pub(super) registry: &'a AssetRegistry,
+}
+
+impl Display for AssetDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.registry.format_asset(self.asset))
+    }
+}
+
+/// Displays the non-zero amounts of `Assets` with the decimals and the symbols of their
+/// tokens. See `Assets::display_with`.
+pub struct AssetsDisplay<'a> {
+    pub(super) assets: &'a Assets,
+    pub(super) registry: &'a AssetRegistry,
+}
+
+impl Display for AssetsDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for (i, asset) in self.registry.format_assets(self.assets).iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", asset)?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AssetRegistry;
+    use crate::{
+        common::{
+            address::Address,
+            asset::{Asset, Assets},
+        },
+        constants::NUM_ASSETS,
+        utils::u256::U256,
+    };
+
+    #[test]
+    fn test_asset_registry() {
+        let token_address = Address::rand(&mut rand::thread_rng());
+        let json = format!(
+            r#"[{{"assetId": 0, "tokenAddress": "{}", "symbol": "ETH", "decimals": 18}}]"#,
+            token_address
+        );
+        let registry = AssetRegistry::from_json(&json).unwrap();
+        assert_eq!(registry.get(0).unwrap().token_address, token_address);
+        assert_eq!(
+            registry.format_amount(0, U256::from(1_500_000_000_000_000_000u64)),
+            Some("1.5 ETH".to_string())
+        );
+        assert_eq!(registry.format_amount(1, U256::from(1u64)), None);
+        let mut assets = Assets::default();
+        assets.0[0] = U256::from(2_000_000_000_000_000_000u64);
+        assets.0[1] = U256::from(3u64);
+        let formatted = registry.format_assets(&assets);
+        assert_eq!(formatted.len(), 2);
+        assert_eq!(formatted[0].to_string(), "2 ETH");
+        assert_eq!(formatted[1].to_string(), "3 (asset 1)");
+        let json = serde_json::to_string(&formatted[0]).unwrap();
+        assert!(json.contains(r#""formattedAmount":"2 ETH""#));
+        assert_eq!(
+            assets.display_with(&registry).to_string(),
+            "[2 ETH, 3 (asset 1)]"
+        );
+        assert_eq!(
+            Asset {
+                asset_id: 0,
+                amount: U256::from(1_500_000_000_000_000_000u64),
+            }
+            .display_with(&registry)
+            .to_string(),
+            "1.5 ETH"
+        );
+
+        registry.validate_asset_id(0).unwrap();
+        assert!(registry.validate_asset_id(1).is_err());
+        let unknown = Asset {
+            asset_id: 1,
+            amount: U256::from(1u64),
+        };
+        assert!(registry.validate_asset(&unknown).is_err());
+        assert!(registry
+            .validate_assets(&Assets::from_asset(&unknown))
+            .is_err());
+        registry
+            .validate_assets(&Assets::from_asset(&Asset {
+                asset_id: 1,
+                amount: U256::default(),
+            }))
+            .unwrap();
+
+        // an empty registry only checks the range
+        let empty = AssetRegistry::default();
+        empty.validate_asset_id(1).unwrap();
+        assert!(empty.validate_asset_id(NUM_ASSETS as u32).is_err());
+
+        let mut tokens = registry.tokens();
+        tokens.push(tokens[0].clone());
+        assert!(AssetRegistry::new(tokens).is_err());
+    }
+}
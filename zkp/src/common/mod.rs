@@ -1,5 +1,6 @@
 pub mod address;
 pub mod asset;
+pub mod asset_registry;
 pub mod block;
 pub mod extended_block_number;
 pub mod transfer;
@@ -1,10 +1,42 @@
+// The parameters are selected at build time by cargo features.
+// The degrees of the cyclic circuits depend on them, and are derived when the circuits are built.
+#[cfg(not(feature = "transfer-tree-height-4"))]
 pub const TRANSFER_TREE_HEIGHT: usize = 11;
+#[cfg(feature = "transfer-tree-height-4")]
+pub const TRANSFER_TREE_HEIGHT: usize = 4;
+
+#[cfg(not(feature = "num-assets-8"))]
 pub const NUM_ASSETS: usize = 4;
+#[cfg(feature = "num-assets-8")]
+pub const NUM_ASSETS: usize = 8;
+
+// The numbers of transfers absorbed by the step circuits of the withdraw circuit, in ascending
+// order. A step is proven by the smallest step circuit that fits its transfers, and the remaining
+// slots hold disabled transfers.
+pub const WITHDRAW_BATCH_SIZES: [usize; 3] = [1, 4, 8];
+
+// The maximum number of transfers absorbed by one step of the withdraw circuit.
+pub const MAX_WITHDRAW_BATCH_SIZE: usize = WITHDRAW_BATCH_SIZES[WITHDRAW_BATCH_SIZES.len() - 1];
+
+#[cfg(test)]
+mod tests {
+    use super::NUM_ASSETS;
 
-// Cyclic proof constants. They depends on the `TRANSFER_TREE_HEIGHT` and `NUM_ASSETS`.
-pub const WITHDRAW_PADDING_DEGREE: usize = 13;
-pub const BALANCE_PROOF_PADDING_DEGREE: usize = 15;
-pub const BLOCK_TREE_PADDING_DEGREE: usize = 15;
-pub const WITHDRAW_TREE_PADDING_DEGREE: usize = 15;
-pub const EVIDENCE_TREE_PADDING_DEGREE: usize = 15;
-pub const SETTLEMENT_TREE_PADDING_DEGREE: usize = 15;
+    // The contracts hold assets in fixed size arrays, so `num-assets-8` fails here until the
+    // contracts are updated.
+    #[test]
+    fn test_num_assets_matches_contracts() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../contracts/contracts/common-interface/IAsset.sol");
+        let source = std::fs::read_to_string(&path).unwrap();
+        for ty in ["uint256", "int256", "address"] {
+            let declaration = format!(" {}[{}] ", ty, NUM_ASSETS);
+            assert!(
+                source.contains(&declaration),
+                "{} does not declare{}, the contracts assume another number of assets",
+                path.display(),
+                declaration.trim_end()
+            );
+        }
+    }
+}
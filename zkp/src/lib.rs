@@ -6,6 +6,7 @@ pub mod random;
 pub mod serialization;
 pub mod tree_circuits;
 pub mod utils;
+pub mod verifier;
 pub mod wrap_circuits;
 
 #[cfg(feature = "api")]
@@ -1,6 +1,9 @@
 use actix_web::{web::Data, App, HttpServer};
 use log::{error, info};
-use zkp::api::{api::api_config, state::ServerState};
+use zkp::{
+    api::{api::api_config, state::ServerState},
+    common::asset_registry::AssetRegistry,
+};
 
 lazy_static::lazy_static! {
     static ref SERVER_HOST: String = std::env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
@@ -28,7 +31,13 @@ async fn main() -> std::io::Result<()> {
         }
     }));
 
-    let state = ServerState::new();
+    let asset_registry = AssetRegistry::from_env().expect("failed to load the asset registry");
+    info!(
+        "Loaded {} tokens into the asset registry",
+        asset_registry.tokens().len()
+    );
+
+    let state = ServerState::new(asset_registry).expect("failed to build the circuits");
     let app_data = Data::new(state);
     let host = SERVER_HOST.clone();
     let port = SERVER_PORT.clone();
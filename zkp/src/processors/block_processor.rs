@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use anyhow::ensure;
 use plonky2::hash::hash_types::HashOut;
 use plonky2::plonk::config::AlgebraicHasher;
@@ -14,6 +16,7 @@ use crate::common::block::Block;
 use crate::common::transfer::Transfer;
 use crate::common::transfer_info::TransferInfo;
 use crate::constants::TRANSFER_TREE_HEIGHT;
+use crate::utils::h256::H256;
 use crate::utils::trees::merkle_tree_with_leaves::MerkleTreeWithLeaves;
 
 use super::block_io::{BlockInfo, BlockStatus, BlockTreeStatus};
@@ -27,6 +30,18 @@ where
     validity_proof: Option<ProofWithPublicInputs<F, C, D>>,
     block_tree_proof: Option<ProofWithPublicInputs<F, C, D>>,
     pub block_tree: MerkleTreeWithLeaves<F, Block>,
+    // block hash -> block number of the blocks in `block_tree`
+    block_numbers: HashMap<H256, u32>,
+}
+
+fn index_block_hashes<F: RichField>(
+    block_tree: &MerkleTreeWithLeaves<F, Block>,
+) -> HashMap<H256, u32> {
+    block_tree
+        .leaves()
+        .iter()
+        .map(|block| (block.block_hash(), block.block_number))
+        .collect()
 }
 
 impl<F, C, const D: usize> BlockProcessor<F, C, D>
@@ -43,6 +58,7 @@ where
             latest_block: genesis_block,
             validity_proof: None,
             block_tree_proof: None,
+            block_numbers: index_block_hashes(&block_tree),
             block_tree,
         }
     }
@@ -130,6 +146,8 @@ where
         );
         let prev_block_root = self.block_tree.get_root();
         self.block_tree.push(new_block.clone());
+        self.block_numbers
+            .insert(new_block.block_hash(), new_block.block_number);
         let new_block_root = self.block_tree.get_root();
         let block_merkle_proof = self.block_tree.prove(new_block.block_number as usize);
         let block_value = BlockTreeValue::new(
@@ -159,6 +177,7 @@ where
     pub fn reset_block_tree(&mut self) {
         let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
         block_tree.push(Block::default());
+        self.block_numbers = index_block_hashes(&block_tree);
         self.block_tree = block_tree;
     }
 
@@ -178,6 +197,28 @@ where
         }
     }
 
+    // whether the block is in the block tree
+    pub fn contains_block(&self, block: &Block) -> bool {
+        let index = block.block_number as usize;
+        index < self.block_tree.len() && self.block_tree.get_leaf(index) == *block
+    }
+
+    // the number of the block in the block tree that has the block hash
+    pub fn get_block_number(&self, block_hash: H256) -> Option<u32> {
+        self.block_numbers.get(&block_hash).copied()
+    }
+
+    // whether a block in the block tree has the block hash
+    pub fn contains_block_hash(&self, block_hash: H256) -> bool {
+        self.block_numbers.contains_key(&block_hash)
+    }
+
+    // whether the block is the next block of the latest block
+    pub fn is_next_block(&self, block: &Block) -> bool {
+        block.block_number == self.latest_block.block_number + 1
+            && block.prev_block_hash == self.latest_block.block_hash()
+    }
+
     // add blocks to the block tree
     pub fn sync_block_tree(
         &mut self,
@@ -194,6 +235,10 @@ where
             block_tree_snapshot.get_root(),
             expected_block_root
         );
+        for block in blocks {
+            self.block_numbers
+                .insert(block.block_hash(), block.block_number);
+        }
         self.block_tree = block_tree_snapshot;
         Ok(())
     }
@@ -211,6 +256,7 @@ where
             self.block_tree.get_root() == status.block_root,
             "block_root mismatch"
         );
+        self.block_numbers = index_block_hashes(&self.block_tree);
         self.latest_block = status.latest_block.clone();
         self.validity_proof = status.validity_proof.clone();
         self.block_tree_proof = status.block_tree_proof.clone();
@@ -246,8 +292,8 @@ mod tests {
 
         let mut block_processor = BlockProcessor::<F, C, D>::new();
         let spent_circuit = SpentCircuit::new();
-        let validity_circuit = ValidityCircuit::new(&spent_circuit);
-        let block_tree_circuit = BlockTreeCircuit::new();
+        let validity_circuit = ValidityCircuit::new(&spent_circuit).unwrap();
+        let block_tree_circuit = BlockTreeCircuit::new().unwrap();
 
         let block_info = block_processor
             .generate_block(&spent_circuit, &transfers, &deposit)
@@ -268,6 +314,8 @@ mod tests {
         let block_info = block_processor
             .generate_block(&spent_circuit, &transfers, &Assets::default())
             .unwrap();
+        assert!(block_processor.is_next_block(&block_info.block));
+        assert!(!block_processor.contains_block(&block_info.block));
         block_processor
             .tick(
                 &validity_circuit,
@@ -279,5 +327,25 @@ mod tests {
             .get_status()
             .verify(&validity_circuit, &block_tree_circuit)
             .unwrap();
+        assert!(block_processor.contains_block(&block_info.block));
+        assert!(block_processor.contains_block_hash(block_info.block.block_hash()));
+        assert_eq!(
+            block_processor.get_block_number(block_info.block.block_hash()),
+            Some(block_info.block.block_number)
+        );
+        assert!(!block_processor.is_next_block(&block_info.block));
+
+        // the synced blocks are indexed, and the index is cleared with the block tree
+        let blocks = block_processor.block_tree.leaves()[1..].to_vec();
+        let block_root = block_processor.block_tree.get_root();
+        block_processor.reset_block_tree();
+        assert!(!block_processor.contains_block_hash(block_info.block.block_hash()));
+        block_processor
+            .sync_block_tree(&blocks, block_root)
+            .unwrap();
+        assert_eq!(
+            block_processor.get_block_number(block_info.block.block_hash()),
+            Some(block_info.block.block_number)
+        );
     }
 }
@@ -17,6 +17,7 @@ use crate::{
         asset::Assets, block::Block, extended_block_number::ExtendedBlockNumber,
         transfer_info::TransferInfo,
     },
+    constants::MAX_WITHDRAW_BATCH_SIZE,
     tree_circuits::{
         settlement_leaf_circuit::{SettlementLeaf, SettlementLeafCircuit},
         settlement_tree_circuit::common_data_for_settlement_tree_circuit,
@@ -48,18 +49,18 @@ where
     C: GenericConfig<D, F = F> + 'static,
     <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
 {
-    pub fn new(block_tree_circuit: &BlockTreeCircuit<F, C, D>) -> Self {
-        let withdraw_circuit = WithdrawCircuit::new(block_tree_circuit);
+    pub fn new(block_tree_circuit: &BlockTreeCircuit<F, C, D>) -> anyhow::Result<Self> {
+        let withdraw_circuit = WithdrawCircuit::new(block_tree_circuit)?;
         let settlement_leaf_circuit = SettlementLeafCircuit::new(&withdraw_circuit);
         let mut common_data = common_data_for_settlement_tree_circuit::<F, C, D>();
         let evidence_tree_processor =
-            SettlementTreeProcessor::new(&settlement_leaf_circuit, &mut common_data);
-        Self {
+            SettlementTreeProcessor::new(&settlement_leaf_circuit, &mut common_data)?;
+        Ok(Self {
             withdraw_circuit,
             settlement_leaf_circuit,
             settlement_tree_processor: evidence_tree_processor,
             block_root: None,
-        }
+        })
     }
 
     pub(crate) fn generate_leaf_proof(
@@ -92,12 +93,13 @@ where
         Ok((leaf, leaf_proof))
     }
 
-    fn append_withdraw_proof_single(
+    // Absorbs a batch of at most `MAX_WITHDRAW_BATCH_SIZE` transfers sorted by ebn in one step.
+    fn append_withdraw_proof_batch(
         &self,
         block_tree_circuit: &BlockTreeCircuit<F, C, D>,
         block_tree: &MerkleTreeWithLeaves<F, Block>,
         block_tree_proof: &ProofWithPublicInputs<F, C, D>,
-        transfer_info: &TransferInfo<F>,
+        transfer_info: &[TransferInfo<F>],
         withdraw_proof: &Option<ProofWithPublicInputs<F, C, D>>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
         block_tree_circuit
@@ -109,9 +111,15 @@ where
             "block root mismatch",
         );
         ensure!(
-            transfer_info.block.block_number <= block_tree_pis.block.block_number,
+            transfer_info
+                .iter()
+                .all(|t| t.block.block_number <= block_tree_pis.block.block_number),
             "block_tree is too old"
         );
+        let block_merkle_proof_transfer = transfer_info
+            .iter()
+            .map(|t| block_tree.prove(t.block.block_number as usize))
+            .collect::<Vec<_>>();
         let new_withdraw_proof = if let Some(withdraw_proof) = withdraw_proof {
             self.withdraw_circuit
                 .verify(&withdraw_proof)
@@ -119,13 +127,11 @@ where
             let withdraw_pis = WithdrawPublicInputs::from_pis(&withdraw_proof.public_inputs);
             let block_merkle_proof_prev =
                 block_tree.prove(withdraw_pis.block.block_number as usize);
-            let block_merkle_proof_transfer =
-                block_tree.prove(transfer_info.block.block_number as usize);
             let withdraw_value = WithdrawValue::new(
                 block_tree_circuit,
                 false,
                 withdraw_pis,
-                transfer_info.clone(),
+                transfer_info.to_vec(),
                 block_tree_proof.clone(),
                 block_merkle_proof_prev,
                 block_merkle_proof_transfer,
@@ -136,20 +142,18 @@ where
                 .map_err(|_| anyhow::anyhow!("failed to prove withdraw"))?
         } else {
             let withdraw_pis = WithdrawPublicInputs {
-                recipient: transfer_info.transfer.recipient,
+                recipient: transfer_info[0].transfer.recipient,
                 total_amount: Assets::default(),
                 start_ebn: ExtendedBlockNumber::default(),
                 end_ebn: ExtendedBlockNumber::default(),
                 block: Block::default(),
             };
             let block_merkle_proof_prev = block_tree.prove(0);
-            let block_merkle_proof_transfer =
-                block_tree.prove(transfer_info.block.block_number as usize);
             let withdraw_value = WithdrawValue::new(
                 block_tree_circuit,
                 true,
                 withdraw_pis,
-                transfer_info.clone(),
+                transfer_info.to_vec(),
                 block_tree_proof.clone(),
                 block_merkle_proof_prev,
                 block_merkle_proof_transfer,
@@ -162,6 +166,9 @@ where
         Ok(new_withdraw_proof)
     }
 
+    /// Appends the transfers to the withdraw proof. The transfers are sorted by ebn
+    /// and absorbed `MAX_WITHDRAW_BATCH_SIZE` at a time, so only the last step may be proven by a
+    /// smaller step circuit.
     pub fn append_withdraw_proof(
         &self,
         block_tree_circuit: &BlockTreeCircuit<F, C, D>,
@@ -174,12 +181,12 @@ where
         let mut transfer_info = transfer_info.to_vec();
         transfer_info.sort_by_key(|t| t.ebn());
         let mut new_withdraw_proof = withdraw_proof.clone();
-        for t in transfer_info {
-            new_withdraw_proof = Some(self.append_withdraw_proof_single(
+        for batch in transfer_info.chunks(MAX_WITHDRAW_BATCH_SIZE) {
+            new_withdraw_proof = Some(self.append_withdraw_proof_batch(
                 block_tree_circuit,
                 block_tree,
                 block_tree_proof,
-                &t,
+                batch,
                 &new_withdraw_proof,
             )?);
         }
@@ -275,8 +282,8 @@ mod tests {
         let transfers_vec =
             generate_random_transfers::<F, _>(&mut rng, latest_block_number, 4, &[recipient]);
         let spent_circuit = SpentCircuit::new();
-        let validity_circuit = ValidityCircuit::new(&spent_circuit);
-        let block_tree_circuit = BlockTreeCircuit::new();
+        let validity_circuit = ValidityCircuit::new(&spent_circuit).unwrap();
+        let block_tree_circuit = BlockTreeCircuit::new().unwrap();
         let mut block_processor = BlockProcessor::<F, C, D>::new();
 
         let mut transfer_info = vec![];
@@ -296,7 +303,8 @@ mod tests {
         let block_tree_snapshot = block_processor.get_block_tree_snapshot();
         let block_tree_proof_snapshot = block_processor.get_block_tree_proof().unwrap();
 
-        let mut settlement_processor = SettlementProcessor::<F, C, D>::new(&block_tree_circuit);
+        let mut settlement_processor =
+            SettlementProcessor::<F, C, D>::new(&block_tree_circuit).unwrap();
         settlement_processor.initialize(&block_tree_snapshot);
 
         let mut settlement_witnesses = vec![];
@@ -313,6 +321,18 @@ mod tests {
             settlement_witnesses.push((withdraw_proof, info.clone()));
         }
 
+        // all the transfers of the recipient in batches
+        let withdraw_proof = settlement_processor
+            .append_withdraw_proof(
+                &block_tree_circuit,
+                &block_tree_snapshot,
+                &block_tree_proof_snapshot,
+                &transfer_info,
+                &None,
+            )
+            .unwrap();
+        settlement_witnesses.push((withdraw_proof, transfer_info[0].clone()));
+
         for w in &settlement_witnesses {
             settlement_processor
                 .add(&block_tree_snapshot, &w.0, &w.1)
@@ -162,7 +162,7 @@ where
 mod tests {
     use plonky2::plonk::{
         circuit_data::CircuitConfig,
-        config::{GenericConfig, PoseidonGoldilocksConfig},
+        config::{GenericConfig, GenericHashOut, PoseidonGoldilocksConfig},
     };
     use rand::seq::SliceRandom;
     use stark_verifier::bn254_poseidon::plonky2_config::Bn254PoseidonGoldilocksConfig;
@@ -199,8 +199,8 @@ mod tests {
         let transfers_vec =
             generate_random_transfers::<F, _>(&mut rng, latest_block_number, 4, &[recipient]);
         let spent_circuit = SpentCircuit::new();
-        let validity_circuit = ValidityCircuit::new(&spent_circuit);
-        let block_tree_circuit = BlockTreeCircuit::new();
+        let validity_circuit = ValidityCircuit::new(&spent_circuit).unwrap();
+        let block_tree_circuit = BlockTreeCircuit::new().unwrap();
         let mut block_processor = BlockProcessor::<F, C, D>::new();
 
         let mut transfer_info = vec![];
@@ -220,7 +220,8 @@ mod tests {
         let block_tree_snapshot = block_processor.get_block_tree_snapshot();
         let block_tree_proof_snapshot = block_processor.get_block_tree_proof().unwrap();
 
-        let mut settlement_processor = SettlementProcessor::<F, C, D>::new(&block_tree_circuit);
+        let mut settlement_processor =
+            SettlementProcessor::<F, C, D>::new(&block_tree_circuit).unwrap();
         settlement_processor.initialize(&block_tree_snapshot);
 
         let mut settlement_witnesses = vec![];
@@ -253,7 +254,7 @@ mod tests {
 
     #[test]
     fn test_wrap_processor() {
-        let block_tree_circuit = BlockTreeCircuit::new();
+        let block_tree_circuit = BlockTreeCircuit::new().unwrap();
         let (block_processor, validity_circuit, settlement_processor, root_proof) =
             generate_settlement_root_proof();
         let inner_config = CircuitConfig::standard_inner_stark_verifier_config();
@@ -283,4 +284,48 @@ mod tests {
         println!("pis hash {:?}", pis.to_solidity_pis::<F>());
         dbg!(wrap_processor.wrap2_circuit.data.common.degree_bits());
     }
+
+    // The deployed verifier contracts are generated from the wrap2 circuit, so a change of
+    // its digest is a breaking change. After an intended change, pin the new digest with
+    // `UPDATE_CIRCUIT_DIGEST=1 cargo test -r test_wrap2_circuit_digest`.
+    #[test]
+    #[cfg(not(any(feature = "transfer-tree-height-4", feature = "num-assets-8")))]
+    fn test_wrap2_circuit_digest() {
+        let spent_circuit = SpentCircuit::<F, C, D>::new();
+        let validity_circuit = ValidityCircuit::new(&spent_circuit).unwrap();
+        let block_tree_circuit = BlockTreeCircuit::new().unwrap();
+        let settlement_processor = SettlementProcessor::new(&block_tree_circuit).unwrap();
+        let wrap_processor = WrapProcessor::<F, C, OuterC, D>::new(
+            CircuitConfig::standard_inner_stark_verifier_config(),
+            CircuitConfig::standard_stark_verifier_config(),
+            &validity_circuit,
+            &block_tree_circuit,
+            &settlement_processor,
+        );
+        let digest = hex::encode(
+            wrap_processor
+                .wrap2_circuit
+                .data
+                .verifier_only
+                .circuit_digest
+                .to_bytes(),
+        );
+
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/processors/wrap2_circuit_digest.txt");
+        if std::env::var("UPDATE_CIRCUIT_DIGEST").is_ok() {
+            std::fs::write(&path, &digest).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "{} is missing, pin the digest with UPDATE_CIRCUIT_DIGEST=1",
+                path.display()
+            )
+        });
+        assert_eq!(
+            digest,
+            expected.trim(),
+            "the wrap2 circuit digest changed, the verifier contracts must be regenerated"
+        );
+    }
 }
@@ -18,7 +18,7 @@ use crate::{
         address::Address, asset::Assets, block::Block, extended_block_number::ExtendedBlockNumber,
         transfer::Transfer, transfer_info::TransferInfo,
     },
-    constants::TRANSFER_TREE_HEIGHT,
+    constants::{MAX_WITHDRAW_BATCH_SIZE, TRANSFER_TREE_HEIGHT},
     utils::trees::merkle_tree_with_leaves::{MerkleProofWithLeaves, MerkleTreeWithLeaves},
 };
 
@@ -135,14 +135,17 @@ where
             block: Block::default(),
         };
         let mut withdraw_proof = None;
-        for t in transfer_info.iter() {
+        for batch in transfer_info.chunks(MAX_WITHDRAW_BATCH_SIZE) {
             let block_merkle_proof_prev = block_tree.prove(pis.block.block_number as usize);
-            let block_merkle_proof_transfer = block_tree.prove(t.block.block_number as usize);
+            let block_merkle_proof_transfer = batch
+                .iter()
+                .map(|t| block_tree.prove(t.block.block_number as usize))
+                .collect();
             let withdraw_value = WithdrawValue::new(
                 block_tree_circuit,
                 is_first_step,
                 pis.clone(),
-                t.clone(),
+                batch.to_vec(),
                 block_tree_proof.clone().unwrap(),
                 block_merkle_proof_prev,
                 block_merkle_proof_transfer,
@@ -203,8 +206,8 @@ mod tests {
         let num_blocks = 4;
         let num_transfers = 3;
         let recipient = Address::rand(&mut rng);
-        let block_tree_circuit = BlockTreeCircuit::new();
-        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit);
+        let block_tree_circuit = BlockTreeCircuit::new().unwrap();
+        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit).unwrap();
 
         let random_settlement_proofs = generate_random_settlement::<F, C, D, _>(
             &block_tree_circuit,
@@ -1,3 +1,4 @@
 pub mod serialized_hashout;
+pub mod serialized_merkle_multiproof;
 pub mod serialized_proof;
 pub mod serialized_transfer_info;
@@ -0,0 +1,79 @@
+use plonky2::{field::goldilocks_field::GoldilocksField, hash::hash_types::HashOut};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    leafable::Leafable, trees::merkle_tree_with_leaves::MerkleMultiproofWithLeaves,
+};
+
+use super::serialized_hashout::SerializedHashOut;
+
+type F = GoldilocksField;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedMerkleMultiproof {
+    pub height: usize,
+    pub siblings: Vec<SerializedHashOut>,
+}
+
+impl<V: Leafable<F, HashOut = HashOut<F>>> From<MerkleMultiproofWithLeaves<F, V>>
+    for SerializedMerkleMultiproof
+{
+    fn from(value: MerkleMultiproofWithLeaves<F, V>) -> Self {
+        Self {
+            height: value.height,
+            siblings: value
+                .siblings
+                .iter()
+                .map(|h| SerializedHashOut(*h))
+                .collect(),
+        }
+    }
+}
+
+impl<V: Leafable<F, HashOut = HashOut<F>>> From<SerializedMerkleMultiproof>
+    for MerkleMultiproofWithLeaves<F, V>
+{
+    fn from(value: SerializedMerkleMultiproof) -> Self {
+        Self {
+            height: value.height,
+            siblings: value.siblings.iter().map(|h| h.0).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::{goldilocks_field::GoldilocksField, types::Sample};
+
+    use crate::utils::trees::merkle_tree_with_leaves::{
+        MerkleMultiproofWithLeaves, MerkleTreeWithLeaves,
+    };
+
+    use super::SerializedMerkleMultiproof;
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_serialize_merkle_multiproof() {
+        let mut tree = MerkleTreeWithLeaves::<F, Vec<F>>::new(10);
+        for _ in 0..10 {
+            tree.push(vec![F::rand()]);
+        }
+        let indices = vec![2, 3, 7];
+        let leaves = indices
+            .iter()
+            .map(|&i| tree.get_leaf(i))
+            .collect::<Vec<_>>();
+        let multiproof = tree.prove_many(&indices);
+
+        let serialized: SerializedMerkleMultiproof = multiproof.into();
+        let s = serde_json::to_string(&serialized).unwrap();
+        let recovered: SerializedMerkleMultiproof = serde_json::from_str(&s).unwrap();
+        assert_eq!(serialized, recovered);
+        let multiproof: MerkleMultiproofWithLeaves<F, Vec<F>> = recovered.into();
+        multiproof
+            .verify(&indices, &leaves, tree.get_root())
+            .unwrap();
+    }
+}
@@ -1,19 +1,230 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, ensure};
 use plonky2::{
     field::extension::Extendable,
     hash::hash_types::RichField,
     plonk::{
-        circuit_data::CircuitData,
-        config::GenericConfig,
+        circuit_data::{CircuitData, CommonCircuitData, VerifierOnlyCircuitData},
+        config::{GenericConfig, GenericHashOut},
         proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs},
     },
 };
 use serde::{Deserialize, Serialize};
 
+/// The magic bytes at the beginning of a serialized proof.
+pub const PROOF_MAGIC: [u8; 4] = *b"PNPF";
+
+/// The version of the container format. Bump it when the layout of the header
+/// or of the body changes.
+pub const PROOF_FORMAT_VERSION: u8 = 1;
+
+/// The circuit that a serialized proof belongs to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CircuitKind {
+    Spent,
+    Validity,
+    BlockTree,
+    Withdraw,
+    Settlement,
+    Wrap2,
+}
+
+impl CircuitKind {
+    pub const ALL: [CircuitKind; 6] = [
+        CircuitKind::Spent,
+        CircuitKind::Validity,
+        CircuitKind::BlockTree,
+        CircuitKind::Withdraw,
+        CircuitKind::Settlement,
+        CircuitKind::Wrap2,
+    ];
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            CircuitKind::Spent => 0,
+            CircuitKind::Validity => 1,
+            CircuitKind::BlockTree => 2,
+            CircuitKind::Withdraw => 3,
+            CircuitKind::Settlement => 4,
+            CircuitKind::Wrap2 => 5,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> anyhow::Result<Self> {
+        Ok(match value {
+            0 => CircuitKind::Spent,
+            1 => CircuitKind::Validity,
+            2 => CircuitKind::BlockTree,
+            3 => CircuitKind::Withdraw,
+            4 => CircuitKind::Settlement,
+            5 => CircuitKind::Wrap2,
+            _ => bail!("unknown circuit kind {}", value),
+        })
+    }
+
+    /// Whether the circuit verifies its own previous proof, in which case its proofs carry
+    /// the verifier data of the circuit in their public inputs.
+    pub fn is_cyclic(self) -> bool {
+        matches!(
+            self,
+            CircuitKind::Validity
+                | CircuitKind::BlockTree
+                | CircuitKind::Withdraw
+                | CircuitKind::Settlement
+        )
+    }
+}
+
+impl Display for CircuitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CircuitKind::Spent => "spent",
+            CircuitKind::Validity => "validity",
+            CircuitKind::BlockTree => "block-tree",
+            CircuitKind::Withdraw => "withdraw",
+            CircuitKind::Settlement => "settlement",
+            CircuitKind::Wrap2 => "wrap2",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for CircuitKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CircuitKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| anyhow::anyhow!("unknown circuit kind {}", s))
+    }
+}
+
+/// The header of a serialized proof.
+///
+/// | magic (4) | version (1) | kind (1) | digest length (1) | circuit digest | public input count (4, LE) |
+///
+/// The body that follows the header is the compressed proof with public inputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofHeader {
+    pub version: u8,
+    pub kind: CircuitKind,
+    pub circuit_digest: Vec<u8>,
+    pub num_public_inputs: u32,
+}
+
+impl ProofHeader {
+    pub fn new<F, C, const D: usize>(kind: CircuitKind, data: &CircuitData<F, C, D>) -> Self
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        Self::from_verifier_data(kind, &data.verifier_only, &data.common)
+    }
+
+    pub fn from_verifier_data<F, C, const D: usize>(
+        kind: CircuitKind,
+        verifier_only: &VerifierOnlyCircuitData<C, D>,
+        common: &CommonCircuitData<F, D>,
+    ) -> Self
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        Self {
+            version: PROOF_FORMAT_VERSION,
+            kind,
+            circuit_digest: verifier_only.circuit_digest.to_bytes(),
+            num_public_inputs: common.num_public_inputs as u32,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = PROOF_MAGIC.to_vec();
+        bytes.push(self.version);
+        bytes.push(self.kind.to_u8());
+        bytes.push(self.circuit_digest.len() as u8);
+        bytes.extend_from_slice(&self.circuit_digest);
+        bytes.extend_from_slice(&self.num_public_inputs.to_le_bytes());
+        bytes
+    }
+
+    /// Parses the header and returns it with the length of the header in bytes.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<(Self, usize)> {
+        ensure!(
+            bytes.len() >= 7 && bytes[0..4] == PROOF_MAGIC,
+            "not a serialized proof: missing magic bytes {:?}",
+            std::str::from_utf8(&PROOF_MAGIC).unwrap()
+        );
+        let version = bytes[4];
+        ensure!(
+            version == PROOF_FORMAT_VERSION,
+            "unsupported proof format version {}, expected {}",
+            version,
+            PROOF_FORMAT_VERSION
+        );
+        let kind = CircuitKind::from_u8(bytes[5])?;
+        let digest_len = bytes[6] as usize;
+        let header_len = 7 + digest_len + 4;
+        ensure!(
+            bytes.len() >= header_len,
+            "truncated proof header: {} bytes, expected at least {}",
+            bytes.len(),
+            header_len
+        );
+        let circuit_digest = bytes[7..7 + digest_len].to_vec();
+        let num_public_inputs =
+            u32::from_le_bytes(bytes[7 + digest_len..header_len].try_into().unwrap());
+
+        Ok((
+            Self {
+                version,
+                kind,
+                circuit_digest,
+                num_public_inputs,
+            },
+            header_len,
+        ))
+    }
+
+    /// Checks that the proof was generated by the circuit of `expected`.
+    pub fn check(&self, expected: &Self) -> anyhow::Result<()> {
+        ensure!(
+            self.kind == expected.kind,
+            "circuit kind mismatch: expected a {} proof, got a {} proof",
+            expected.kind,
+            self.kind
+        );
+        ensure!(
+            self.circuit_digest == expected.circuit_digest,
+            "circuit digest mismatch for the {} proof: expected {}, got {}",
+            self.kind,
+            hex::encode(&expected.circuit_digest),
+            hex::encode(&self.circuit_digest)
+        );
+        ensure!(
+            self.num_public_inputs == expected.num_public_inputs,
+            "public input count mismatch for the {} proof: expected {}, got {}",
+            self.kind,
+            expected.num_public_inputs,
+            self.num_public_inputs
+        );
+        Ok(())
+    }
+}
+
+/// A proof in the versioned container format, i.e. `ProofHeader` followed by
+/// the compressed proof. It is serialized as a hex string in human readable formats.
 #[derive(Debug, Clone, PartialEq)]
 pub struct SerializedProof(pub Vec<u8>);
 
+// Binary formats carry the raw bytes instead of the hex string.
 impl Serialize for SerializedProof {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_bytes(&self.0);
+        }
         let h = hex::encode(&self.0);
         serializer.serialize_str(&h)
     }
@@ -21,6 +232,9 @@ impl Serialize for SerializedProof {
 
 impl<'de> Deserialize<'de> for SerializedProof {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            return Ok(Self(Vec::<u8>::deserialize(deserializer)?));
+        }
         let s = String::deserialize(deserializer)?;
         let h = hex::decode(s).map_err(serde::de::Error::custom)?;
         Ok(Self(h))
@@ -29,6 +243,7 @@ impl<'de> Deserialize<'de> for SerializedProof {
 
 impl SerializedProof {
     pub fn from_proof<F, C, const D: usize>(
+        kind: CircuitKind,
         data: &CircuitData<F, C, D>,
         proof: &ProofWithPublicInputs<F, C, D>,
     ) -> Self
@@ -40,21 +255,58 @@ impl SerializedProof {
             .clone()
             .compress(&data.verifier_only.circuit_digest, &data.common)
             .unwrap();
-        Self(compressed_proof.to_bytes())
+        let mut bytes = ProofHeader::new(kind, data).to_bytes();
+        bytes.extend(compressed_proof.to_bytes());
+        Self(bytes)
+    }
+
+    pub fn header(&self) -> anyhow::Result<ProofHeader> {
+        Ok(ProofHeader::from_bytes(&self.0)?.0)
     }
 
+    /// Decodes the proof after checking that its header matches the circuit of `kind`.
     pub fn to_proof<F, C, const D: usize>(
         &self,
+        kind: CircuitKind,
         data: &CircuitData<F, C, D>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>>
     where
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
+        self.to_proof_with_verifier_data(kind, &data.verifier_only, &data.common)
+    }
+
+    /// Same as `to_proof`, but only needs the verifier data of the circuit.
+    ///
+    /// A proof without the magic bytes is decoded as a bare compressed proof of `kind`,
+    /// the format before the header was introduced, so that older backups can still be
+    /// restored. Such a proof is only checked by decompressing it with the circuit of `kind`.
+    pub fn to_proof_with_verifier_data<F, C, const D: usize>(
+        &self,
+        kind: CircuitKind,
+        verifier_only: &VerifierOnlyCircuitData<C, D>,
+        common: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let header_len = if self.0.starts_with(&PROOF_MAGIC) {
+            let (header, header_len) = ProofHeader::from_bytes(&self.0)?;
+            header.check(&ProofHeader::from_verifier_data(
+                kind,
+                verifier_only,
+                common,
+            ))?;
+            header_len
+        } else {
+            0
+        };
         let compressed_proof =
-            CompressedProofWithPublicInputs::from_bytes(self.0.clone(), &data.common)?;
-        let proof =
-            compressed_proof.decompress(&data.verifier_only.circuit_digest, &data.common)?;
+            CompressedProofWithPublicInputs::from_bytes(self.0[header_len..].to_vec(), common)
+                .map_err(|e| anyhow::anyhow!("failed to decode the {} proof: {}", kind, e))?;
+        let proof = compressed_proof.decompress(&verifier_only.circuit_digest, common)?;
         Ok(proof)
     }
 }
@@ -80,8 +332,10 @@ mod tests {
         let builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
         let data = builder.build::<C>();
         let proof = data.prove(PartialWitness::new()).unwrap();
-        let serialized_proof = SerializedProof::from_proof(&data, &proof);
-        let recovered = serialized_proof.to_proof(&data).unwrap();
+        let serialized_proof = SerializedProof::from_proof(CircuitKind::Spent, &data, &proof);
+        let recovered = serialized_proof
+            .to_proof(CircuitKind::Spent, &data)
+            .unwrap();
         assert_eq!(recovered, proof);
 
         let serialized_proof_str = serde_json::to_string(&serialized_proof).unwrap();
@@ -89,4 +343,55 @@ mod tests {
             serde_json::from_str(&serialized_proof_str).unwrap();
         assert_eq!(serialize_proof_recovered, serialized_proof);
     }
+
+    #[test]
+    fn test_proof_header_mismatch() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let one = builder.one();
+        builder.register_public_input(one);
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new()).unwrap();
+        let serialized_proof = SerializedProof::from_proof(CircuitKind::Withdraw, &data, &proof);
+        let header = serialized_proof.header().unwrap();
+        assert_eq!(header.version, PROOF_FORMAT_VERSION);
+        assert_eq!(header.kind, CircuitKind::Withdraw);
+        assert_eq!(header.num_public_inputs, 1);
+
+        let err = serialized_proof
+            .to_proof(CircuitKind::Spent, &data)
+            .unwrap_err();
+        assert!(err.to_string().contains("circuit kind mismatch"));
+
+        // another circuit has another digest and public input count
+        let other_data = CircuitBuilder::<F, D>::new(CircuitConfig::default()).build::<C>();
+        let err = serialized_proof
+            .to_proof(CircuitKind::Withdraw, &other_data)
+            .unwrap_err();
+        assert!(err.to_string().contains("circuit digest mismatch"));
+
+        // a legacy proof without the header
+        let bare = SerializedProof(
+            proof
+                .clone()
+                .compress(&data.verifier_only.circuit_digest, &data.common)
+                .unwrap()
+                .to_bytes(),
+        );
+        assert!(bare.header().is_err());
+        assert_eq!(bare.to_proof(CircuitKind::Withdraw, &data).unwrap(), proof);
+
+        let mut future = serialized_proof.clone();
+        future.0[4] = PROOF_FORMAT_VERSION + 1;
+        let err = future.to_proof(CircuitKind::Withdraw, &data).unwrap_err();
+        assert!(err.to_string().contains("unsupported proof format version"));
+    }
+
+    #[test]
+    fn test_circuit_kind_names() {
+        for kind in CircuitKind::ALL {
+            assert_eq!(kind.to_string().parse::<CircuitKind>().unwrap(), kind);
+            assert_eq!(CircuitKind::from_u8(kind.to_u8()).unwrap(), kind);
+        }
+        assert!("wrap".parse::<CircuitKind>().is_err());
+    }
 }
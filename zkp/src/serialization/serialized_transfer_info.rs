@@ -1,12 +1,18 @@
+use anyhow::ensure;
 use plonky2::field::goldilocks_field::GoldilocksField;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     common::{block::Block, transfer::Transfer, transfer_info::TransferInfo},
-    utils::trees::{merkle_tree::MerkleProof, merkle_tree_with_leaves::MerkleProofWithLeaves},
+    utils::trees::{
+        merkle_tree::MerkleProof,
+        merkle_tree_with_leaves::{MerkleMultiproofWithLeaves, MerkleProofWithLeaves},
+    },
 };
 
-use super::serialized_hashout::SerializedHashOut;
+use super::{
+    serialized_hashout::SerializedHashOut, serialized_merkle_multiproof::SerializedMerkleMultiproof,
+};
 
 type F = GoldilocksField;
 
@@ -54,6 +60,73 @@ impl From<SerializedTransferInfo> for TransferInfo<F> {
     }
 }
 
+// Transfer infos of the same block, which share one multiproof of the transfer tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedTransferInfoBundle {
+    pub transfers: Vec<Transfer>,
+    pub transfer_indices: Vec<usize>,
+    pub transfer_merkle_multiproof: SerializedMerkleMultiproof,
+    pub block: Block,
+}
+
+impl TryFrom<Vec<TransferInfo<F>>> for SerializedTransferInfoBundle {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<TransferInfo<F>>) -> anyhow::Result<Self> {
+        ensure!(!value.is_empty(), "transfer_info is empty");
+        let block = value[0].block.clone();
+        ensure!(
+            value.iter().all(|t| t.block == block),
+            "transfer_info must be of the same block"
+        );
+        let transfer_indices = value.iter().map(|t| t.transfer_index).collect::<Vec<_>>();
+        let mut sorted_indices = transfer_indices.clone();
+        sorted_indices.sort_unstable();
+        sorted_indices.dedup();
+        ensure!(
+            sorted_indices.len() == transfer_indices.len(),
+            "duplicate transfer_index"
+        );
+        let proofs = value
+            .iter()
+            .map(|t| t.transfer_merkle_proof.clone())
+            .collect::<Vec<_>>();
+        let transfer_merkle_multiproof =
+            MerkleMultiproofWithLeaves::from_proofs(&transfer_indices, &proofs)?.into();
+        Ok(Self {
+            transfers: value.iter().map(|t| t.transfer).collect(),
+            transfer_indices,
+            transfer_merkle_multiproof,
+            block,
+        })
+    }
+}
+
+impl TryFrom<SerializedTransferInfoBundle> for Vec<TransferInfo<F>> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedTransferInfoBundle) -> anyhow::Result<Self> {
+        let multiproof: MerkleMultiproofWithLeaves<F, Transfer> =
+            value.transfer_merkle_multiproof.into();
+        let proofs = multiproof.to_proofs(&value.transfer_indices, &value.transfers)?;
+        Ok(value
+            .transfers
+            .iter()
+            .zip(value.transfer_indices.iter())
+            .zip(proofs)
+            .map(
+                |((transfer, transfer_index), transfer_merkle_proof)| TransferInfo {
+                    transfer: *transfer,
+                    transfer_index: *transfer_index,
+                    transfer_merkle_proof,
+                    block: value.block.clone(),
+                },
+            )
+            .collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use plonky2::{
@@ -61,11 +134,16 @@ mod tests {
     };
 
     use crate::{
-        common::{address::Address, transfer_info::TransferInfo},
-        random::etmp::generate_random_etmps,
+        common::{
+            address::Address, asset::Assets, block::Block, transfer::Transfer,
+            transfer_info::TransferInfo,
+        },
+        constants::TRANSFER_TREE_HEIGHT,
+        random::{etmp::generate_random_etmps, transfers::generate_random_transfers},
+        utils::trees::merkle_tree_with_leaves::MerkleTreeWithLeaves,
     };
 
-    use super::SerializedTransferInfo;
+    use super::{SerializedTransferInfo, SerializedTransferInfoBundle};
 
     const D: usize = 2;
     type F = GoldilocksField;
@@ -82,4 +160,44 @@ mod tests {
         let serialized_transfer_info2: SerializedTransferInfo = transfer_info_recovered.into();
         assert_eq!(serialized_transfer_info, serialized_transfer_info2);
     }
+
+    #[test]
+    fn test_convert_transfer_info_bundle() {
+        let mut rng = rand::thread_rng();
+        let recipient = Address::rand(&mut rng);
+        let transfers = generate_random_transfers::<F, _>(&mut rng, 1, 8, &[recipient])[0].clone();
+        let mut transfer_tree = MerkleTreeWithLeaves::<F, Transfer>::new(TRANSFER_TREE_HEIGHT);
+        for transfer in transfers.iter() {
+            transfer_tree.push(*transfer);
+        }
+        let block = Block {
+            prev_block_hash: Block::default().block_hash(),
+            transfer_tree_root: transfer_tree.get_root().into(),
+            total_deposit: Assets::default(),
+            block_number: 1,
+        };
+        let transfer_info = transfers
+            .iter()
+            .enumerate()
+            .map(|(transfer_index, transfer)| TransferInfo::<F> {
+                transfer: *transfer,
+                transfer_index,
+                transfer_merkle_proof: transfer_tree.prove(transfer_index),
+                block: block.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let bundle = SerializedTransferInfoBundle::try_from(transfer_info.clone()).unwrap();
+        let s = serde_json::to_string(&bundle).unwrap();
+        let recovered: SerializedTransferInfoBundle = serde_json::from_str(&s).unwrap();
+        assert_eq!(bundle, recovered);
+        let transfer_info_recovered: Vec<TransferInfo<F>> = recovered.try_into().unwrap();
+        for (t, t_recovered) in transfer_info.into_iter().zip(transfer_info_recovered) {
+            t_recovered.verify().unwrap();
+            assert_eq!(
+                SerializedTransferInfo::from(t),
+                SerializedTransferInfo::from(t_recovered)
+            );
+        }
+    }
 }
@@ -5,7 +5,8 @@ use plonky2::{
     hash::hash_types::RichField,
     iop::target::BoolTarget,
     plonk::{
-        circuit_builder::CircuitBuilder, config::GenericConfig, proof::ProofWithPublicInputsTarget,
+        circuit_builder::CircuitBuilder, circuit_data::VerifierCircuitData, config::GenericConfig,
+        proof::ProofWithPublicInputsTarget,
     },
 };
 
@@ -23,6 +24,9 @@ where
     ) -> ProofWithPublicInputsTarget<D>;
 
     fn dummy_leaf(&self) -> DummyProof<F, C, D>;
+
+    /// The verifier data of the leaf circuit, with which the mock prover checks leaf proofs.
+    fn verifier_data(&self) -> VerifierCircuitData<F, C, D>;
 }
 
 pub trait DynamicLeafable: Clone + Display {
@@ -1,8 +1,9 @@
 use std::marker::PhantomData;
 
+use anyhow::{ensure, Context};
 use plonky2::{
     field::extension::Extendable,
-    gates::{noop::NoopGate, random_access::RandomAccessGate},
+    gates::random_access::RandomAccessGate,
     hash::hash_types::{HashOut, HashOutTarget, RichField},
     iop::{
         target::{BoolTarget, Target},
@@ -10,7 +11,10 @@ use plonky2::{
     },
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget},
+        circuit_data::{
+            CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitData,
+            VerifierCircuitTarget,
+        },
         config::{AlgebraicHasher, GenericConfig},
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
@@ -19,9 +23,12 @@ use plonky2::{
 use starky_keccak::builder::CircuitBuilderWithKeccak;
 
 use crate::utils::{
+    cyclic::build_cyclic_circuit,
     dummy::DummyProof,
     h256::{H256Target, H256},
+    keccak::{keccak256_two_to_one, keccak256_two_to_one_circuit},
     logic::enforce_equal_if_enabled,
+    mock::{mock_cyclic_proof, mock_verify_cyclic, mock_verify_inner},
 };
 
 use super::dynamic_leafable::DynamicLeafableCircuit;
@@ -83,6 +90,7 @@ where
     pub dummy_leaf: DummyProof<F, C, D>,
     pub dummy_node: DummyProof<F, C, D>,
     pub vd: VerifierCircuitTarget,
+    leaf_verifier_data: VerifierCircuitData<F, C, D>,
     _phantom: PhantomData<InnerCircuit>,
 }
 
@@ -93,7 +101,22 @@ where
     C::Hasher: AlgebraicHasher<F>,
     InnerCircuit: DynamicLeafableCircuit<F, C, D>,
 {
-    pub fn new(inner_circuit: &InnerCircuit, common_data: &mut CommonCircuitData<F, D>) -> Self {
+    /// Builds the circuit from `common_data` of the padding circuit, and replaces `common_data`
+    /// by the common data of the built circuit.
+    pub fn new(
+        inner_circuit: &InnerCircuit,
+        common_data: &mut CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let circuit = build_cyclic_circuit(
+            common_data,
+            |common_data| Self::build(inner_circuit, common_data),
+            |circuit| &circuit.data,
+        )?;
+        *common_data = circuit.data.common.clone();
+        Ok(circuit)
+    }
+
+    fn build(inner_circuit: &InnerCircuit, mut common_data: CommonCircuitData<F, D>) -> Self {
         let mut builder = CircuitBuilderWithKeccak::<F, D>::new(CircuitConfig::default());
         let cur_pis = DynamicTreePublicInputsTarget::new(&mut builder);
         builder.register_public_inputs(&cur_pis.to_vec());
@@ -141,9 +164,7 @@ where
             right_block_root,
             is_not_first_step,
         );
-        let node_hash = H256Target::from_vec(
-            &builder.keccak256(vec![left_hash.to_vec(), right_hash.to_vec()].concat()),
-        );
+        let node_hash = keccak256_two_to_one_circuit(&mut builder, &left_hash, &right_hash);
         let node_block_root = left_block_root;
 
         let next_hash = H256Target::select(&mut builder, is_first_step, leaf_hash, node_hash);
@@ -152,7 +173,6 @@ where
         builder.connect_hashes(cur_pis.block_root, next_block_root);
 
         let data = builder.build::<C>();
-        assert_eq!(&data.common, common_data);
         let dummy_leaf = inner_circuit.dummy_leaf();
         let dummy_node = DummyProof::<F, C, D>::new_cyclic(&data);
 
@@ -165,6 +185,7 @@ where
             dummy_leaf,
             dummy_node,
             vd,
+            leaf_verifier_data: inner_circuit.verifier_data(),
             _phantom: PhantomData,
         }
     }
@@ -177,6 +198,9 @@ where
             ProofWithPublicInputs<F, C, D>,
         )>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        if cfg!(feature = "mock") {
+            return self.prove_mock(leaf_proof, left_and_right_proof);
+        }
         let mut pw = PartialWitness::new();
         pw.set_verifier_data_target(&self.vd, &self.data.verifier_only);
         if leaf_proof.is_some() {
@@ -196,7 +220,48 @@ where
         self.data.prove(pw)
     }
 
+    // Checks the witness natively instead of proving it.
+    fn prove_mock(
+        &self,
+        leaf_proof: Option<ProofWithPublicInputs<F, C, D>>,
+        left_and_right_proof: Option<(
+            ProofWithPublicInputs<F, C, D>,
+            ProofWithPublicInputs<F, C, D>,
+        )>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let pis = if let Some(leaf_proof) = leaf_proof {
+            ensure!(
+                left_and_right_proof.is_none(),
+                "either a leaf proof or left and right proofs must be given, not both"
+            );
+            mock_verify_inner(&self.leaf_verifier_data, &leaf_proof, false)?;
+            DynamicTreePublicInputs::from_pis(&leaf_proof.public_inputs)
+        } else {
+            let (left_proof, right_proof) = left_and_right_proof
+                .context("either a leaf proof or left and right proofs must be given")?;
+            self.verify(left_proof.clone())?;
+            self.verify(right_proof.clone())?;
+            let left_pis = DynamicTreePublicInputs::<F>::from_pis(&left_proof.public_inputs);
+            let right_pis = DynamicTreePublicInputs::<F>::from_pis(&right_proof.public_inputs);
+            ensure!(
+                left_pis.block_root == right_pis.block_root,
+                "block root mismatch"
+            );
+            let hash = keccak256_two_to_one(&left_pis.hash, &right_pis.hash);
+            DynamicTreePublicInputs {
+                hash,
+                block_root: left_pis.block_root,
+            }
+        };
+        let mut pis_vec = pis.hash.to_vec::<F>();
+        pis_vec.extend(pis.block_root.elements);
+        mock_cyclic_proof(&self.data, pis_vec)
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
+        if cfg!(feature = "mock") {
+            return mock_verify_cyclic(&self.data, &proof_with_pis);
+        }
         check_cyclic_proof_verifier_data(
             &proof_with_pis,
             &self.data.verifier_only,
@@ -238,9 +303,6 @@ where
 
     let random_access_gate = RandomAccessGate::<F, D>::new_from_config(&config, 1);
     builder.add_gate(random_access_gate, vec![]);
-    while builder.num_gates() < 1 << 14 {
-        builder.add_gate(NoopGate, vec![]);
-    }
     builder.build::<C>().common
 }
 
@@ -258,7 +320,7 @@ mod tests {
         },
         plonk::{
             circuit_builder::CircuitBuilder,
-            circuit_data::{CircuitConfig, CircuitData},
+            circuit_data::{CircuitConfig, CircuitData, VerifierCircuitData},
             config::{AlgebraicHasher, GenericConfig, PoseidonGoldilocksConfig},
             proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
         },
@@ -339,6 +401,10 @@ mod tests {
         fn dummy_leaf(&self) -> crate::utils::dummy::DummyProof<F, C, D> {
             DummyProof::<F, C, D>::new(&self.data.common)
         }
+
+        fn verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+            self.data.verifier_data()
+        }
     }
 
     #[test]
@@ -346,7 +412,7 @@ mod tests {
         let a_circuit = Acircuit::<F, C, D>::new();
         let mut common_data = super::common_data_for_dynamic_tree_circuit::<F, C, D>();
         let dynamic_tree_circuit =
-            DynamicTreeCircuit::<F, C, D, _>::new(&a_circuit, &mut common_data);
+            DynamicTreeCircuit::<F, C, D, _>::new(&a_circuit, &mut common_data).unwrap();
 
         let leaf_proof0 = a_circuit.prove(F::ZERO);
         let leaf_proof1 = a_circuit.prove(F::ZERO);
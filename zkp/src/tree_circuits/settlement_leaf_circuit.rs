@@ -7,7 +7,7 @@ use plonky2::{
     },
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitConfig, CircuitData},
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitData},
         config::{AlgebraicHasher, GenericConfig},
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
@@ -19,7 +19,9 @@ use starky_keccak::{builder::CircuitBuilderWithKeccak, keccak256_circuit::solidi
 use std::fmt::Display;
 
 use crate::{
-    base_circuits::withdraw_circuit::{WithdrawCircuit, WithdrawPublicInputsTarget},
+    base_circuits::withdraw_circuit::{
+        WithdrawCircuit, WithdrawPublicInputs, WithdrawPublicInputsTarget,
+    },
     common::{
         block::{Block, BlockTarget},
         transfer_info::{TransferInfo, TransferInfoTarget},
@@ -28,6 +30,7 @@ use crate::{
     utils::{
         dummy::DummyProof,
         h256::{H256Target, H256},
+        mock::{mock_proof, mock_verify_inner},
         trees::merkle_tree_with_leaves::{MerkleProofWithLeaves, MerkleProofWithLeavesTarget},
     },
 };
@@ -164,6 +167,7 @@ where
     pub block_merkle_proof_for_evidence: MerkleProofWithLeavesTarget<BlockTarget>,
     pub withdraw_proof: ProofWithPublicInputsTarget<D>,
     pub transfer_info: TransferInfoTarget,
+    withdraw_verifier_data: VerifierCircuitData<F, C, D>,
 }
 
 impl<F, C, const D: usize> SettlementLeafCircuit<F, C, D>
@@ -232,6 +236,7 @@ where
             block_merkle_proof_for_evidence,
             withdraw_proof,
             transfer_info,
+            withdraw_verifier_data: withdraw_circuit.data.verifier_data(),
         }
     }
 
@@ -243,15 +248,76 @@ where
         withdraw_proof: &ProofWithPublicInputs<F, C, D>,
         transfer_info: &TransferInfo<F>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        if cfg!(feature = "mock") {
+            return self.prove_mock(
+                block_root,
+                block_merkle_proof_for_withdraw,
+                block_merkle_proof_for_evidence,
+                withdraw_proof,
+                transfer_info,
+            );
+        }
         let mut pw = PartialWitness::new();
+        self.set_witness(
+            &mut pw,
+            block_root,
+            block_merkle_proof_for_withdraw,
+            block_merkle_proof_for_evidence,
+            withdraw_proof,
+            transfer_info,
+        );
+        self.data.prove(pw)
+    }
+
+    pub fn set_witness(
+        &self,
+        pw: &mut impl Witness<F>,
+        block_root: &HashOut<F>,
+        block_merkle_proof_for_withdraw: &MerkleProofWithLeaves<F, Block>,
+        block_merkle_proof_for_evidence: &MerkleProofWithLeaves<F, Block>,
+        withdraw_proof: &ProofWithPublicInputs<F, C, D>,
+        transfer_info: &TransferInfo<F>,
+    ) {
         pw.set_hash_target(self.block_root, block_root.clone());
         self.block_merkle_proof_for_withdraw
-            .set_witness(&mut pw, block_merkle_proof_for_withdraw);
+            .set_witness(pw, block_merkle_proof_for_withdraw);
         self.block_merkle_proof_for_evidence
-            .set_witness(&mut pw, block_merkle_proof_for_evidence);
+            .set_witness(pw, block_merkle_proof_for_evidence);
         pw.set_proof_with_pis_target(&self.withdraw_proof, withdraw_proof);
-        self.transfer_info.set_witness(&mut pw, transfer_info);
-        self.data.prove(pw)
+        self.transfer_info.set_witness(pw, transfer_info);
+    }
+
+    // Checks the witness natively instead of proving it.
+    fn prove_mock(
+        &self,
+        block_root: &HashOut<F>,
+        block_merkle_proof_for_withdraw: &MerkleProofWithLeaves<F, Block>,
+        block_merkle_proof_for_evidence: &MerkleProofWithLeaves<F, Block>,
+        withdraw_proof: &ProofWithPublicInputs<F, C, D>,
+        transfer_info: &TransferInfo<F>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        mock_verify_inner(&self.withdraw_verifier_data, withdraw_proof, true)?;
+        let withdraw_pis = WithdrawPublicInputs::from_pis(&withdraw_proof.public_inputs);
+        block_merkle_proof_for_withdraw.verify(
+            &withdraw_pis.block,
+            withdraw_pis.block.block_number as usize,
+            *block_root,
+        )?;
+        let withdraw_leaf = WithdrawLeaf {
+            recipient: withdraw_pis.recipient,
+            amount: withdraw_pis.total_amount,
+            start_ebn: withdraw_pis.start_ebn,
+            end_ebn: withdraw_pis.end_ebn,
+        };
+        let evidence_leaf =
+            EvidenceLeaf::new(block_root, block_merkle_proof_for_evidence, transfer_info)?;
+        let settlement_leaf = SettlementLeaf {
+            withdraw_leaf,
+            evidence_leaf,
+        };
+        let mut pis = settlement_leaf.hash().to_vec::<F>();
+        pis.extend(block_root.elements);
+        mock_proof(&self.data, pis)
     }
 }
 
@@ -277,4 +343,86 @@ where
     fn dummy_leaf(&self) -> crate::utils::dummy::DummyProof<F, C, D> {
         DummyProof::<F, C, D>::new(&self.data.common)
     }
+
+    fn verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.data.verifier_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    use crate::{
+        base_circuits::{block_tree_circuit::BlockTreeCircuit, withdraw_circuit::WithdrawCircuit},
+        common::address::Address,
+        random::withdraw::{generate_random_settlement, RandomSettlementProof},
+        utils::tampering::{assert_tampered_witnesses_fail, tamper_merkle_sibling, Tamper},
+    };
+
+    use super::SettlementLeafCircuit;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    #[cfg_attr(feature = "mock", ignore)]
+    fn test_settlement_leaf_circuit_tampered_witness() {
+        let mut rng = rand::thread_rng();
+        let recipient = Address::rand(&mut rng);
+
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new().unwrap();
+        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit).unwrap();
+        let random_settlement_proof = generate_random_settlement(
+            &block_tree_circuit,
+            &withdraw_circuit,
+            &mut rng,
+            2,
+            2,
+            &[recipient],
+        )[0]
+        .clone();
+
+        let settlement_leaf_circuit = SettlementLeafCircuit::<F, C, D>::new(&withdraw_circuit);
+        type Value = RandomSettlementProof<F, C, D>;
+        let tampers = vec![
+            Tamper::value("wrong block root", |value: &mut Value| {
+                value.block_root.elements[0] += F::ONE;
+            }),
+            Tamper::value("wrong withdraw merkle sibling", |value: &mut Value| {
+                tamper_merkle_sibling(&mut value.block_merkle_proof_for_withdraw);
+            }),
+            Tamper::value("wrong evidence merkle sibling", |value: &mut Value| {
+                tamper_merkle_sibling(&mut value.block_merkle_proof_for_evidence);
+            }),
+            Tamper::non_canonical_u32(
+                "non-canonical u32 limb",
+                settlement_leaf_circuit
+                    .transfer_info
+                    .transfer
+                    .amount
+                    .amount
+                    .0[7],
+            ),
+        ];
+        assert_tampered_witnesses_fail(
+            &settlement_leaf_circuit.data,
+            |pw, value: &Value| {
+                settlement_leaf_circuit.set_witness(
+                    pw,
+                    &value.block_root,
+                    &value.block_merkle_proof_for_withdraw,
+                    &value.block_merkle_proof_for_evidence,
+                    &value.withdraw_proof,
+                    &value.transfer_info,
+                )
+            },
+            &random_settlement_proof,
+            &tampers,
+        );
+    }
 }
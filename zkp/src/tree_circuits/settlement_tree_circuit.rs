@@ -1,6 +1,6 @@
 use plonky2::{
     field::extension::Extendable,
-    gates::{noop::NoopGate, random_access::RandomAccessGate},
+    gates::random_access::RandomAccessGate,
     hash::hash_types::RichField,
     plonk::{
         circuit_builder::CircuitBuilder,
@@ -10,8 +10,6 @@ use plonky2::{
     },
 };
 
-use crate::constants::SETTLEMENT_TREE_PADDING_DEGREE;
-
 use super::{
     dynamic_tree_circuit::DynamicTreeCircuit, settlement_leaf_circuit::SettlementLeafCircuit,
 };
@@ -76,9 +74,6 @@ where
 
     let random_access_gate = RandomAccessGate::<F, D>::new_from_config(&config, 1);
     builder.add_gate(random_access_gate, vec![]);
-    while builder.num_gates() < 1 << SETTLEMENT_TREE_PADDING_DEGREE {
-        builder.add_gate(NoopGate, vec![]);
-    }
     let common_data = builder.build::<C>().common;
     // dbg!(&common_data);
     common_data
@@ -111,8 +106,8 @@ mod tests {
         let num_transfers = 2;
         let recipient = Address::rand(&mut rng);
 
-        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new();
-        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit);
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new().unwrap();
+        let withdraw_circuit = WithdrawCircuit::<F, C, D>::new(&block_tree_circuit).unwrap();
 
         let random_settlement_proof = generate_random_settlement(
             &block_tree_circuit,
@@ -137,7 +132,8 @@ mod tests {
 
         let mut common_data = common_data_for_settlement_tree_circuit::<F, C, D>();
         let settlemet_tree_circuit =
-            SettlementTreeCircuit::<F, C, D>::new(&settlement_leaf_circuit, &mut common_data);
+            SettlementTreeCircuit::<F, C, D>::new(&settlement_leaf_circuit, &mut common_data)
+                .unwrap();
         let node_proof = settlemet_tree_circuit
             .prove(Some(settlement_leaf_proof), None)
             .unwrap();
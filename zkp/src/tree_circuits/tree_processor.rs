@@ -1,8 +1,8 @@
 use std::fmt::Display;
 
-use anyhow::{ensure, Ok};
+use anyhow::Ok;
 use plonky2::{
-    field::extension::Extendable,
+    field::{extension::Extendable, goldilocks_field::GoldilocksField},
     hash::hash_types::RichField,
     plonk::{
         circuit_data::CommonCircuitData,
@@ -11,14 +11,18 @@ use plonky2::{
     },
 };
 use serde::{Deserialize, Serialize};
-use starky_keccak::keccak256_circuit::solidity_keccak256;
 
 use crate::{
     tree_circuits::{
         dynamic_leafable::{DynamicLeafable, DynamicLeafableCircuit},
         dynamic_tree_circuit::{DynamicTreeCircuit, DynamicTreePublicInputs},
     },
-    utils::{display::join_str_with_separator, h256::H256},
+    utils::{
+        display::join_str_with_separator,
+        h256::H256,
+        keccak::KeccakLeaf,
+        trees::{merkle_tree::MerkleProof, merkle_tree_with_leaves::MerkleProofWithLeaves},
+    },
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -52,13 +56,16 @@ where
     Leaf: DynamicLeafable,
     LeafCircuit: DynamicLeafableCircuit<F, C, D>,
 {
-    pub fn new(leaf_circuit: &LeafCircuit, common_data: &mut CommonCircuitData<F, D>) -> Self {
-        let node_circuit = DynamicTreeCircuit::new(leaf_circuit, common_data);
-        Self {
+    pub fn new(
+        leaf_circuit: &LeafCircuit,
+        common_data: &mut CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let node_circuit = DynamicTreeCircuit::new(leaf_circuit, common_data)?;
+        Ok(Self {
             nodes: vec![],
             leaves: vec![],
             node_circuit,
-        }
+        })
     }
 
     pub fn initialize(&mut self) {
@@ -144,28 +151,13 @@ impl<Leaf: DynamicLeafable> Display for DynamicMerkleProofWithLeaf<Leaf> {
 
 impl<Leaf: DynamicLeafable> DynamicMerkleProofWithLeaf<Leaf> {
     pub fn verify(&self, root: H256) -> anyhow::Result<()> {
-        let mut hash = self.leaf.hash();
-        let mut index = self.index;
-        for sibling in self.siblings.iter() {
-            hash = if index % 2 == 0 {
-                H256::from_u32_digits(
-                    solidity_keccak256(
-                        vec![hash.to_u32_digits(), sibling.to_u32_digits()].concat(),
-                    )
-                    .0,
-                )
-            } else {
-                H256::from_u32_digits(
-                    solidity_keccak256(
-                        vec![sibling.to_u32_digits(), hash.to_u32_digits()].concat(),
-                    )
-                    .0,
-                )
-            };
-            index >>= 1;
-        }
-        ensure!(hash == root, "merkle proof verification failed");
-        Ok(())
+        // The field is not used by keccak hashing.
+        let merkle_proof = MerkleProofWithLeaves::<GoldilocksField, KeccakLeaf>(MerkleProof {
+            siblings: self.siblings.clone(),
+        });
+        merkle_proof
+            .verify(&KeccakLeaf(self.leaf.hash()), self.index, root)
+            .map_err(|_| anyhow::anyhow!("merkle proof verification failed"))
     }
 }
 
@@ -0,0 +1,186 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use plonky2::{
+    field::{extension::Extendable, types::PrimeField64},
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget},
+        config::{AlgebraicHasher, GenericConfig},
+        proof::ProofWithPublicInputs,
+    },
+};
+use serde::Serialize;
+
+// The value of a selector polynomial at rows whose gate belongs to another selector group.
+// It is the same as `plonky2::gates::selectors::UNUSED_SELECTOR`, which is not public.
+const UNUSED_SELECTOR: u64 = u32::MAX as u64;
+
+const NOOP_GATE_ID: &str = "NoopGate";
+
+/// Size and proving cost of a circuit.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitReport {
+    pub name: String,
+    pub degree_bits: usize,
+    pub num_gates: usize,
+    pub num_used_gates: usize, // number of gates which are not `NoopGate`
+    pub gate_counts: BTreeMap<String, usize>,
+    pub num_public_inputs: usize,
+    pub num_constants: usize,
+    pub recursive_verifier_gates: Option<usize>, // gates needed to verify a proof of this circuit in a recursion circuit
+    pub prove_time_ms: Option<u64>,
+    pub verify_time_ms: Option<u64>,
+}
+
+impl CircuitReport {
+    pub fn new<F, C, const D: usize>(name: &str, data: &CircuitData<F, C, D>) -> Self
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let gate_counts = gate_counts(data);
+        let num_gates = data.common.degree();
+        let num_noop_gates = gate_counts.get(NOOP_GATE_ID).copied().unwrap_or(0);
+        Self {
+            name: name.to_string(),
+            degree_bits: data.common.degree_bits(),
+            num_gates,
+            num_used_gates: num_gates - num_noop_gates,
+            gate_counts,
+            num_public_inputs: data.common.num_public_inputs,
+            num_constants: data.common.num_constants,
+            recursive_verifier_gates: None,
+            prove_time_ms: None,
+            verify_time_ms: None,
+        }
+    }
+
+    /// Measures the recursion overhead of the circuit.
+    pub fn with_recursive_verifier_gates<F, C, const D: usize>(
+        mut self,
+        data: &CircuitData<F, C, D>,
+    ) -> Self
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        self.recursive_verifier_gates = Some(recursive_verifier_gates::<F, C, D>(&data.common));
+        self
+    }
+
+    /// Runs `prove` and verifies the resulting proof, recording the time taken by each.
+    pub fn measure<F, C, const D: usize>(
+        &mut self,
+        data: &CircuitData<F, C, D>,
+        prove: impl FnOnce() -> anyhow::Result<ProofWithPublicInputs<F, C, D>>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let start = Instant::now();
+        let proof = prove()?;
+        self.prove_time_ms = Some(start.elapsed().as_millis() as u64);
+
+        let start = Instant::now();
+        data.verify(proof.clone())?;
+        self.verify_time_ms = Some(start.elapsed().as_millis() as u64);
+        Ok(proof)
+    }
+}
+
+/// Counts the gates of the circuit by gate type, including the `NoopGate`s used for padding.
+/// The gate of each row is recovered from the selector polynomials.
+pub fn gate_counts<F, C, const D: usize>(data: &CircuitData<F, C, D>) -> BTreeMap<String, usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let num_selectors = data.common.selectors_info.num_selectors();
+    let selectors = data.prover_only.constants_sigmas_commitment.polynomials[..num_selectors]
+        .iter()
+        .map(|poly| poly.clone().fft())
+        .collect::<Vec<_>>();
+    let mut counts = BTreeMap::new();
+    for row in 0..data.common.degree() {
+        let gate_index = selectors
+            .iter()
+            .map(|selector| selector.values[row].to_canonical_u64())
+            .find(|&index| index != UNUSED_SELECTOR)
+            .expect("every row must have a gate");
+        let gate_id = data.common.gates[gate_index as usize].0.id();
+        *counts.entry(gate_id).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Returns the number of gates that a circuit with `standard_recursion_config`
+/// needs to verify a proof with `common`.
+pub fn recursive_verifier_gates<F, C, const D: usize>(common: &CommonCircuitData<F, D>) -> usize
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let proof = builder.add_virtual_proof_with_pis(common);
+    let verifier_data = VerifierCircuitTarget {
+        constants_sigmas_cap: builder.add_virtual_cap(common.config.fri_config.cap_height),
+        circuit_digest: builder.add_virtual_hash(),
+    };
+    builder.verify_proof::<C>(&proof, &verifier_data, common);
+    builder.num_gates()
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+
+    use super::CircuitReport;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_circuit_report() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let a = builder.add_virtual_target();
+        let b = builder.add_virtual_target();
+        let c = builder.mul(a, b);
+        builder.register_public_inputs(&[a, b, c]);
+        let data = builder.build::<C>();
+
+        let mut report = CircuitReport::new("test", &data).with_recursive_verifier_gates(&data);
+        assert_eq!(report.num_gates, 1 << report.degree_bits);
+        assert_eq!(report.gate_counts.values().sum::<usize>(), report.num_gates);
+        assert!(report.num_used_gates < report.num_gates);
+        assert_eq!(report.num_public_inputs, 3);
+        assert!(report.recursive_verifier_gates.unwrap() > 0);
+
+        report
+            .measure(&data, || {
+                let mut pw = PartialWitness::new();
+                pw.set_target(a, F::TWO);
+                pw.set_target(b, F::from_canonical_u64(3));
+                data.prove(pw)
+            })
+            .unwrap();
+        assert!(report.prove_time_ms.is_some() && report.verify_time_ms.is_some());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["degreeBits"], report.degree_bits);
+    }
+}
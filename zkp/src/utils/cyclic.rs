@@ -0,0 +1,88 @@
+use anyhow::{bail, ensure};
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_data::{CircuitData, CommonCircuitData},
+        config::GenericConfig,
+    },
+};
+
+// The degree of a cyclic circuit usually converges in one or two rebuilds.
+const MAX_REBUILDS: usize = 8;
+
+/// Returns `common_data` of a circuit with `2^degree_bits` gates.
+pub fn with_degree_bits<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &CommonCircuitData<F, D>,
+    degree_bits: usize,
+) -> CommonCircuitData<F, D> {
+    let mut common_data = common_data.clone();
+    common_data.fri_params = common_data
+        .config
+        .fri_config
+        .fri_params(degree_bits, common_data.config.zero_knowledge);
+    common_data
+}
+
+/// Builds a cyclic circuit without knowing its degree in advance.
+/// `build` builds the circuit assuming that its own proofs have the given common data.
+/// Starting from the degree of `common_data`, the circuit is rebuilt with the degree of
+/// the previous build until both agree, so the padding degree never has to be kept by hand.
+/// Fails if the degree does not converge or the gates of the circuit differ from `common_data`.
+pub fn build_cyclic_circuit<F, C, const D: usize, T>(
+    common_data: &CommonCircuitData<F, D>,
+    build: impl Fn(CommonCircuitData<F, D>) -> T,
+    circuit_data: impl Fn(&T) -> &CircuitData<F, C, D>,
+) -> anyhow::Result<T>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let mut degree_bits = common_data.degree_bits();
+    for _ in 0..MAX_REBUILDS {
+        let circuit = build(with_degree_bits(common_data, degree_bits));
+        let built_common_data = &circuit_data(&circuit).common;
+        if built_common_data.degree_bits() == degree_bits {
+            let mut expected = with_degree_bits(common_data, degree_bits);
+            expected.num_public_inputs = built_common_data.num_public_inputs;
+            ensure!(
+                &expected == built_common_data,
+                "the gates of the cyclic circuit do not match its common data"
+            );
+            return Ok(circuit);
+        }
+        degree_bits = built_common_data.degree_bits();
+    }
+    bail!("the degree of the cyclic circuit does not converge");
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::CircuitConfig,
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    use super::with_degree_bits;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_with_degree_bits() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let a = builder.add_virtual_target();
+        let b = builder.mul(a, a);
+        builder.register_public_input(b);
+        let common_data = builder.build::<C>().common;
+
+        let degree_bits = common_data.degree_bits();
+        assert_eq!(with_degree_bits(&common_data, degree_bits), common_data);
+
+        let padded_common_data = with_degree_bits(&common_data, degree_bits + 3);
+        assert_eq!(padded_common_data.degree_bits(), degree_bits + 3);
+        assert_eq!(padded_common_data.gates, common_data.gates);
+    }
+}
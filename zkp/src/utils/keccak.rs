@@ -1,6 +1,50 @@
 use core::fmt::Debug;
 use plonky2::{field::extension::Extendable, hash::hash_types::RichField, iop::target::BoolTarget};
-use starky_keccak::builder::CircuitBuilderWithKeccak;
+use serde::{Deserialize, Serialize};
+use starky_keccak::{builder::CircuitBuilderWithKeccak, keccak256_circuit::solidity_keccak256};
+
+use super::{
+    h256::{H256Target, H256},
+    leafable::Leafable,
+};
+
+/// Returns `keccak256(abi.encodePacked(left, right))`, the node hash of `MerkleProofLib`.
+pub fn keccak256_two_to_one(left: &H256, right: &H256) -> H256 {
+    H256::from_u32_digits(
+        solidity_keccak256(vec![left.to_u32_digits(), right.to_u32_digits()].concat()).0,
+    )
+}
+
+pub fn keccak256_two_to_one_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilderWithKeccak<F, D>,
+    left: &H256Target,
+    right: &H256Target,
+) -> H256Target {
+    H256Target::from_vec(&builder.keccak256(vec![left.to_vec(), right.to_vec()].concat()))
+}
+
+/// A `bytes32` leaf of the keccak Merkle trees verified by the contracts, which is already hashed.
+/// `MerkleTreeWithLeaves<F, KeccakLeaf>` is the native counterpart of
+/// `get_merkle_root_from_leaves_keccak_circuit` with `H256Target` leaves.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeccakLeaf(pub H256);
+
+impl<F: RichField> Leafable<F> for KeccakLeaf {
+    type HashOut = H256;
+
+    fn empty_leaf() -> Self {
+        Self::default()
+    }
+
+    // Output as is in the case of a hash.
+    fn hash(&self) -> H256 {
+        self.0
+    }
+
+    fn two_to_one(left: &H256, right: &H256) -> H256 {
+        keccak256_two_to_one(left, right)
+    }
+}
 
 pub trait LeafableTargetKeccak: Clone {
     type HashOutTarget: Clone + Debug;
@@ -58,3 +102,116 @@ pub fn get_merkle_root_from_leaves_keccak_circuit<
     }
     layer[0].clone()
 }
+
+impl LeafableTargetKeccak for H256Target {
+    type HashOutTarget = H256Target;
+
+    fn empty_leaf<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilderWithKeccak<F, D>,
+    ) -> Self {
+        H256Target::constant(builder, H256::default())
+    }
+
+    fn hash<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        _builder: &mut CircuitBuilderWithKeccak<F, D>,
+    ) -> H256Target {
+        *self
+    }
+
+    fn connect_hash<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilderWithKeccak<F, D>,
+        x: &Self::HashOutTarget,
+        y: &Self::HashOutTarget,
+    ) {
+        x.connect(builder, *y)
+    }
+
+    fn two_to_one<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilderWithKeccak<F, D>,
+        left: &Self::HashOutTarget,
+        right: &Self::HashOutTarget,
+    ) -> Self::HashOutTarget {
+        keccak256_two_to_one_circuit(builder, left, right)
+    }
+
+    fn two_to_one_swapped<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilderWithKeccak<F, D>,
+        left: &Self::HashOutTarget,
+        right: &Self::HashOutTarget,
+        swap: BoolTarget,
+    ) -> Self::HashOutTarget {
+        let new_left = H256Target::select(builder, swap, *right, *left);
+        let new_right = H256Target::select(builder, swap, *left, *right);
+        keccak256_two_to_one_circuit(builder, &new_left, &new_right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        iop::witness::PartialWitness,
+        plonk::{
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+    use rand::Rng;
+    use starky_keccak::builder::CircuitBuilderWithKeccak;
+
+    use crate::utils::{
+        h256::{H256Target, H256},
+        trees::{
+            merkle_tree::MerkleProof,
+            merkle_tree_with_leaves::{MerkleProofWithLeaves, MerkleTreeWithLeaves},
+        },
+    };
+
+    use super::{get_merkle_root_from_leaves_keccak_circuit, KeccakLeaf};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    // The same test vector as `MerkleProofLib` in the contracts.
+    #[test]
+    fn test_keccak_merkle_proof_solidity() {
+        let test_hash1 =
+            H256::from_hex("e30703a88ca1d002bf2d26b7a5773e9163ce5bc583637565ac374a6c41c5fa62");
+        let proof = MerkleProofWithLeaves::<F, KeccakLeaf>(MerkleProof {
+            siblings: vec![test_hash1, test_hash1],
+        });
+        let root =
+            H256::from_hex("e6383f3dc437d84c9cb80a919cf82d728755be99be291e85ce24dfe7de3325e2");
+        proof.verify(&KeccakLeaf(test_hash1), 1, root).unwrap();
+    }
+
+    #[test]
+    fn test_keccak_merkle_tree_circuit() {
+        let mut rng = rand::thread_rng();
+        let height = 3;
+        let leaves = (0..1 << height)
+            .map(|_| KeccakLeaf(H256::rand(&mut rng)))
+            .collect::<Vec<_>>();
+        let mut tree = MerkleTreeWithLeaves::<F, KeccakLeaf>::new(height);
+        for leaf in leaves.iter() {
+            tree.push(*leaf);
+        }
+        let index = rng.gen_range(0..1 << height);
+        tree.prove(index)
+            .verify(&leaves[index], index, tree.get_root())
+            .unwrap();
+
+        let mut builder = CircuitBuilderWithKeccak::<F, D>::new(CircuitConfig::default());
+        let leaves_t = leaves
+            .iter()
+            .map(|leaf| H256Target::constant(&mut builder, leaf.0))
+            .collect::<Vec<_>>();
+        let root_t = get_merkle_root_from_leaves_keccak_circuit(&mut builder, height, &leaves_t);
+        let expected_root_t = H256Target::constant(&mut builder, tree.get_root());
+        root_t.connect(&mut builder, expected_root_t);
+
+        let data = builder.build::<C>();
+        data.prove(PartialWitness::new()).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::ensure;
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_data::{CircuitData, CommonCircuitData, VerifierCircuitData},
+        config::{AlgebraicHasher, GenericConfig, GenericHashOut},
+        proof::ProofWithPublicInputs,
+    },
+    recursion::{
+        cyclic_recursion::check_cyclic_proof_verifier_data,
+        dummy_circuit::{dummy_circuit, dummy_proof},
+    },
+};
+
+// Placeholder proofs are generated once per circuit and cached by circuit digest,
+// because proving the dummy circuit is the only slow part of the mock prover.
+lazy_static::lazy_static! {
+    static ref PLACEHOLDER_PROOFS: Mutex<HashMap<Vec<u8>, Vec<u8>>> = Mutex::new(HashMap::new());
+}
+
+/// Returns a proof which has the same shape as a proof of `data` and carries `public_inputs`.
+/// The proof itself is not valid, so it is only accepted by the mock verifiers.
+pub fn mock_proof<F, C, const D: usize>(
+    data: &CircuitData<F, C, D>,
+    public_inputs: Vec<F>,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    ensure!(
+        public_inputs.len() == data.common.num_public_inputs,
+        "public inputs length mismatch: expected {}, got {}",
+        data.common.num_public_inputs,
+        public_inputs.len()
+    );
+    let key = data.verifier_only.circuit_digest.to_bytes();
+    let mut placeholder_proofs = PLACEHOLDER_PROOFS.lock().unwrap();
+    let bytes = match placeholder_proofs.get(&key) {
+        Some(bytes) => bytes.clone(),
+        None => {
+            let dummy_data = dummy_circuit::<F, C, D>(&data.common);
+            let bytes = dummy_proof(&dummy_data, HashMap::new())?.to_bytes();
+            placeholder_proofs.insert(key, bytes.clone());
+            bytes
+        }
+    };
+    let mut proof = ProofWithPublicInputs::from_bytes(bytes, &data.common)?;
+    proof.public_inputs = public_inputs;
+    Ok(proof)
+}
+
+/// Same as `mock_proof`, but appends the verifier data of `data` to `public_inputs`
+/// as `add_verifier_data_public_inputs` does for cyclic circuits.
+pub fn mock_cyclic_proof<F, C, const D: usize>(
+    data: &CircuitData<F, C, D>,
+    public_inputs: Vec<F>,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let mut public_inputs = public_inputs;
+    public_inputs.extend(data.verifier_only.circuit_digest.to_vec());
+    for hash in data.verifier_only.constants_sigmas_cap.0.iter() {
+        public_inputs.extend(hash.to_vec());
+    }
+    mock_proof(data, public_inputs)
+}
+
+/// Checks that `proof` has the shape of a proof of `data`.
+pub fn mock_verify<F, C, const D: usize>(
+    data: &CircuitData<F, C, D>,
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    check_shape(&data.common, proof)
+}
+
+fn check_shape<F, C, const D: usize>(
+    common: &CommonCircuitData<F, D>,
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    ensure!(
+        proof.public_inputs.len() == common.num_public_inputs,
+        "public inputs length mismatch: expected {}, got {}",
+        common.num_public_inputs,
+        proof.public_inputs.len()
+    );
+    Ok(())
+}
+
+/// Checks that `proof` has the shape of a proof of the cyclic circuit `data`
+/// and carries its verifier data.
+pub fn mock_verify_cyclic<F, C, const D: usize>(
+    data: &CircuitData<F, C, D>,
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    mock_verify(data, proof)?;
+    check_cyclic_proof_verifier_data(proof, &data.verifier_only, &data.common)
+}
+
+/// Checks a proof of an inner circuit in a mock prover as the `verify` of the inner circuit
+/// does under the `mock` feature, given only the verifier data of the inner circuit. Set
+/// `cyclic` for a cyclic inner circuit, whose proofs must carry its verifier data.
+pub fn mock_verify_inner<F, C, const D: usize>(
+    data: &VerifierCircuitData<F, C, D>,
+    proof: &ProofWithPublicInputs<F, C, D>,
+    cyclic: bool,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    check_shape(&data.common, proof)?;
+    if cyclic {
+        check_cyclic_proof_verifier_data(proof, &data.verifier_only, &data.common)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+
+    use crate::serialization::serialized_proof::{CircuitKind, SerializedProof};
+
+    use super::{mock_proof, mock_verify, mock_verify_inner};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_mock_proof() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let a = builder.add_virtual_target();
+        let b = builder.add_virtual_target();
+        let c = builder.add(a, b);
+        builder.register_public_inputs(&[a, b, c]);
+        let data = builder.build::<C>();
+
+        let public_inputs = vec![F::ONE, F::TWO, F::from_canonical_u64(3)];
+        let proof = mock_proof(&data, public_inputs.clone()).unwrap();
+        assert_eq!(proof.public_inputs, public_inputs);
+        mock_verify(&data, &proof).unwrap();
+        assert!(data.verify(proof.clone()).is_err());
+
+        // the placeholder survives the proof serialization
+        let recovered = SerializedProof::from_proof(CircuitKind::Spent, &data, &proof)
+            .to_proof(CircuitKind::Spent, &data)
+            .unwrap();
+        assert_eq!(recovered.public_inputs, public_inputs);
+
+        assert!(mock_proof(&data, vec![F::ONE]).is_err());
+
+        let verifier_data = data.verifier_data();
+        mock_verify_inner(&verifier_data, &proof, false).unwrap();
+        let mut short_proof = proof.clone();
+        short_proof.public_inputs.pop();
+        assert!(mock_verify_inner(&verifier_data, &short_proof, false).is_err());
+    }
+}
@@ -1,8 +1,13 @@
+pub mod circuit_report;
+pub mod cyclic;
 pub mod display;
 pub mod dummy;
 pub mod h256;
 pub mod keccak;
 pub mod leafable;
 pub mod logic;
+pub mod mock;
+#[cfg(test)]
+pub(crate) mod tampering;
 pub mod trees;
 pub mod u256;
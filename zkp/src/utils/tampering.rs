@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use plonky2::{
+    field::{extension::Extendable, types::Field},
+    hash::hash_types::{HashOut, RichField},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, Witness, WitnessWrite},
+    },
+    plonk::{circuit_data::CircuitData, config::GenericConfig},
+};
+use plonky2_u32::gadgets::arithmetic_u32::U32Target;
+
+use super::{leafable::Leafable, trees::merkle_tree_with_leaves::MerkleProofWithLeaves};
+
+/// A witness which shifts the values of some targets when they are set.
+/// This is used to inject values that native types cannot represent, e.g. non-canonical u32 limbs.
+pub(crate) struct TamperedWitness<F: Field> {
+    pub(crate) pw: PartialWitness<F>,
+    shifts: HashMap<Target, F>,
+}
+
+impl<F: Field> TamperedWitness<F> {
+    pub(crate) fn new() -> Self {
+        Self {
+            pw: PartialWitness::new(),
+            shifts: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn shift_target(&mut self, target: Target, shift: F) {
+        self.shifts.insert(target, shift);
+    }
+}
+
+impl<F: Field> WitnessWrite<F> for TamperedWitness<F> {
+    fn set_target(&mut self, target: Target, value: F) {
+        let shift = self.shifts.get(&target).copied().unwrap_or(F::ZERO);
+        self.pw.set_target(target, value + shift);
+    }
+}
+
+impl<F: Field> Witness<F> for TamperedWitness<F> {
+    fn try_get_target(&self, target: Target) -> Option<F> {
+        self.pw.try_get_target(target)
+    }
+}
+
+/// A mutation applied to the witness of a circuit before proving.
+pub(crate) struct Tamper<'a, F: Field, V> {
+    pub(crate) name: &'static str,
+    apply: Box<dyn Fn(&mut V, &mut TamperedWitness<F>) + 'a>,
+}
+
+impl<'a, F: Field, V> Tamper<'a, F, V> {
+    /// Mutates the value passed to `set_witness`.
+    pub(crate) fn value(name: &'static str, mutate: impl Fn(&mut V) + 'a) -> Self {
+        Self {
+            name,
+            apply: Box::new(move |value, _| mutate(value)),
+        }
+    }
+
+    /// Replaces the limb `x` set to `target` by `x + 2^32`, which is the same u32 value
+    /// if the limb is not range checked.
+    pub(crate) fn non_canonical_u32(name: &'static str, target: U32Target) -> Self {
+        Self {
+            name,
+            apply: Box::new(move |_, pw| {
+                pw.shift_target(target.0, F::from_canonical_u64(1 << 32));
+            }),
+        }
+    }
+}
+
+/// Replaces the first sibling of `proof` so that it no longer opens to the same root.
+pub(crate) fn tamper_merkle_sibling<F, V>(proof: &mut MerkleProofWithLeaves<F, V>)
+where
+    F: RichField,
+    V: Leafable<F, HashOut = HashOut<F>>,
+{
+    proof.0.siblings[0].elements[0] += F::ONE;
+}
+
+/// Checks that the honest `value` is accepted by the circuit, and that every tampered witness
+/// is rejected, i.e. either proving or verifying fails. Proving fails either with an error or
+/// with a panic of the witness generation, while a panic elsewhere is a bug of the test and is
+/// propagated.
+pub(crate) fn assert_tampered_witnesses_fail<F, C, const D: usize, V: Clone>(
+    data: &CircuitData<F, C, D>,
+    set_witness: impl Fn(&mut TamperedWitness<F>, &V),
+    value: &V,
+    tampers: &[Tamper<F, V>],
+) where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let is_accepted = |tamper: Option<&Tamper<F, V>>| {
+        let mut value = value.clone();
+        let mut pw = TamperedWitness::new();
+        if let Some(tamper) = tamper {
+            (tamper.apply)(&mut value, &mut pw);
+        }
+        set_witness(&mut pw, &value);
+        let proof = match catch_unwind(AssertUnwindSafe(|| data.prove(pw.pw))) {
+            Ok(Ok(proof)) => proof,
+            Ok(Err(_)) | Err(_) => return false,
+        };
+        data.verify(proof).is_ok()
+    };
+    assert!(is_accepted(None), "honest witness is rejected");
+    for tamper in tampers {
+        assert!(
+            !is_accepted(Some(tamper)),
+            "tampered witness is accepted: {}",
+            tamper.name
+        );
+    }
+}
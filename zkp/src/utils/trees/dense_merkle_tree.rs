@@ -0,0 +1,163 @@
+use plonky2::hash::hash_types::RichField;
+
+use super::merkle_tree::MerkleProof;
+use crate::utils::leafable::Leafable;
+
+// DenseMerkleTree is a structure of Merkle Tree used for `MerkleTreeWithLeaves`.
+// Leaves are filled from the left, so the nodes of each level are stored in a vec
+// indexed by their position. Only the nodes up to the last non-empty one are stored,
+// and the other nodes are zero hashes.
+#[derive(Clone, Debug)]
+pub(crate) struct DenseMerkleTree<F: RichField, V: Leafable<F>> {
+    height: usize,
+    layers: Vec<Vec<V::HashOut>>, // layers[0] is the leaf hashes and layers[height] is the root
+    zero_hashes: Vec<V::HashOut>, // zero_hashes[level] is the hash of an empty node at the level
+}
+
+impl<F: RichField, V: Leafable<F>> DenseMerkleTree<F, V> {
+    pub(crate) fn new(height: usize, empty_leaf_hash: V::HashOut) -> Self {
+        // zero_hashes = [H(zero_leaf), H(H(zero_leaf), H(zero_leaf)), ...]
+        let mut zero_hashes = vec![];
+        let mut h = empty_leaf_hash;
+        zero_hashes.push(h.clone());
+        for _ in 0..height {
+            h = V::two_to_one(&h, &h);
+            zero_hashes.push(h.clone());
+        }
+
+        Self {
+            height,
+            layers: vec![vec![]; height + 1],
+            zero_hashes,
+        }
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub(crate) fn get_node_hash(&self, level: usize, index: usize) -> V::HashOut {
+        assert!(level <= self.height);
+        match self.layers[level].get(index) {
+            Some(h) => h.clone(),
+            None => self.zero_hashes[level].clone(),
+        }
+    }
+
+    pub(crate) fn get_root(&self) -> V::HashOut {
+        self.get_node_hash(self.height, 0)
+    }
+
+    fn set_node_hash(&mut self, level: usize, index: usize, h: V::HashOut) {
+        let layer = &mut self.layers[level];
+        if index < layer.len() {
+            layer[index] = h;
+        } else {
+            assert_eq!(index, layer.len(), "nodes must be filled from the left");
+            layer.push(h);
+        }
+    }
+
+    // Updates the leaf at `index`, which is an existing leaf or the next one.
+    pub(crate) fn update_leaf(&mut self, index: usize, leaf_hash: V::HashOut) {
+        assert!(index <= self.len());
+        let mut index = index;
+        let mut h = leaf_hash;
+        for level in 0..self.height {
+            self.set_node_hash(level, index, h.clone());
+            let sibling = self.get_node_hash(level, index ^ 1);
+            h = if index & 1 == 1 {
+                V::two_to_one(&sibling, &h)
+            } else {
+                V::two_to_one(&h, &sibling)
+            };
+            index >>= 1;
+        }
+        self.set_node_hash(self.height, 0, h);
+    }
+
+    pub(crate) fn push(&mut self, leaf_hash: V::HashOut) {
+        self.update_leaf(self.len(), leaf_hash);
+    }
+
+    pub(crate) fn pop(&mut self) {
+        assert!(self.len() > 0);
+        let len = self.len() - 1;
+        self.update_leaf(len, self.zero_hashes[0].clone());
+        // the nodes whose leaves are all empty are zero hashes
+        for (level, layer) in self.layers.iter_mut().enumerate() {
+            let num_nodes = (len + (1 << level) - 1) >> level;
+            layer.truncate(num_nodes);
+        }
+    }
+
+    pub(crate) fn prove(&self, index: usize) -> MerkleProof<F, V> {
+        let siblings = (0..self.height)
+            .map(|level| self.get_node_hash(level, (index >> level) ^ 1))
+            .collect();
+        MerkleProof { siblings }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Sample,
+        hash::poseidon::PoseidonHash,
+        plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig},
+    };
+    use rand::Rng;
+
+    use super::DenseMerkleTree;
+    use crate::utils::trees::merkle_tree::{usize_le_bits, MerkleTree};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_dense_merkle_tree() {
+        let mut rng = rand::thread_rng();
+        let height = 10;
+        let empty_leaf_hash = PoseidonHash::hash_no_pad(&[]);
+
+        // compare with the sparse representation
+        let mut tree = DenseMerkleTree::<F, Vec<F>>::new(height, empty_leaf_hash);
+        let mut sparse_tree = MerkleTree::<F, Vec<F>>::new(height, empty_leaf_hash);
+        for _ in 0..100 {
+            let leaf = vec![F::rand()];
+            let leaf_hash = PoseidonHash::hash_no_pad(&leaf);
+            sparse_tree.update_leaf(usize_le_bits(tree.len(), height), leaf_hash);
+            tree.push(leaf_hash);
+            assert_eq!(tree.get_root(), sparse_tree.get_root());
+        }
+
+        for _ in 0..100 {
+            let index = rng.gen_range(0..tree.len());
+            let leaf = vec![F::rand()];
+            let leaf_hash = PoseidonHash::hash_no_pad(&leaf);
+            tree.update_leaf(index, leaf_hash);
+            sparse_tree.update_leaf(usize_le_bits(index, height), leaf_hash);
+            assert_eq!(tree.get_root(), sparse_tree.get_root());
+
+            let index = rng.gen_range(0..1 << height);
+            let proof = tree.prove(index);
+            let sparse_proof = sparse_tree.prove(usize_le_bits(index, height));
+            assert_eq!(proof.siblings, sparse_proof.siblings);
+        }
+
+        while tree.len() > 0 {
+            tree.pop();
+            sparse_tree.update_leaf(usize_le_bits(tree.len(), height), empty_leaf_hash);
+            assert_eq!(tree.get_root(), sparse_tree.get_root());
+        }
+        assert_eq!(
+            tree.get_root(),
+            DenseMerkleTree::<F, Vec<F>>::new(height, empty_leaf_hash).get_root()
+        );
+    }
+}
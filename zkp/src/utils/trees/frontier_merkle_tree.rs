@@ -0,0 +1,124 @@
+use plonky2::hash::hash_types::RichField;
+
+use super::{merkle_tree::MerkleProof, merkle_tree_with_leaves::MerkleProofWithLeaves};
+use crate::utils::leafable::Leafable;
+
+// Append-only Merkle Tree that only holds the frontier, i.e. the last left node of each level.
+// It is suitable for trees where only the root and the proof of the latest leaf are needed,
+// and uses `O(height)` memory regardless of the number of leaves.
+#[derive(Debug, Clone)]
+pub struct FrontierMerkleTree<F: RichField, V: Leafable<F>> {
+    height: usize,
+    len: usize,
+    frontier: Vec<V::HashOut>, // frontier[level] is the last node at the level which is a left child
+    zero_hashes: Vec<V::HashOut>, // zero_hashes[level] is the hash of an empty node at the level
+    root: V::HashOut,
+}
+
+impl<F: RichField, V: Leafable<F>> FrontierMerkleTree<F, V> {
+    pub fn new(height: usize) -> Self {
+        // zero_hashes = [H(zero_leaf), H(H(zero_leaf), H(zero_leaf)), ...]
+        let mut zero_hashes = vec![];
+        let mut h = V::empty_leaf().hash();
+        zero_hashes.push(h.clone());
+        for _ in 0..height {
+            h = V::two_to_one(&h, &h);
+            zero_hashes.push(h.clone());
+        }
+        let frontier = zero_hashes[..height].to_vec();
+
+        Self {
+            height,
+            len: 0,
+            frontier,
+            zero_hashes,
+            root: h,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get_root(&self) -> V::HashOut {
+        self.root.clone()
+    }
+
+    pub fn push(&mut self, leaf: V) {
+        let index = self.len;
+        assert!(index < (1 << self.height));
+        let mut h = leaf.hash();
+        for level in 0..self.height {
+            h = if (index >> level) & 1 == 1 {
+                V::two_to_one(&self.frontier[level], &h)
+            } else {
+                self.frontier[level] = h.clone();
+                V::two_to_one(&h, &self.zero_hashes[level])
+            };
+        }
+        self.root = h;
+        self.len += 1;
+    }
+
+    /// Returns the merkle proof of the latest leaf.
+    /// All the leaves on its right are empty, so the frontier has all its siblings.
+    pub fn prove_latest(&self) -> MerkleProofWithLeaves<F, V> {
+        assert!(!self.is_empty());
+        let index = self.len - 1;
+        let siblings = (0..self.height)
+            .map(|level| {
+                if (index >> level) & 1 == 1 {
+                    self.frontier[level].clone()
+                } else {
+                    self.zero_hashes[level].clone()
+                }
+            })
+            .collect();
+        MerkleProofWithLeaves(MerkleProof { siblings })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Sample,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    use super::FrontierMerkleTree;
+    use crate::utils::trees::merkle_tree_with_leaves::MerkleTreeWithLeaves;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_frontier_merkle_tree() {
+        let height = 10;
+
+        type V = Vec<F>;
+        let mut tree = FrontierMerkleTree::<F, V>::new(height);
+        let mut full_tree = MerkleTreeWithLeaves::<F, V>::new(height);
+        assert_eq!(tree.get_root(), full_tree.get_root());
+
+        for _ in 0..100 {
+            let leaf = vec![F::rand()];
+            tree.push(leaf.clone());
+            full_tree.push(leaf.clone());
+            assert_eq!(tree.get_root(), full_tree.get_root());
+
+            let index = tree.len() - 1;
+            let proof = tree.prove_latest();
+            assert_eq!(proof.0.siblings, full_tree.prove(index).0.siblings);
+            proof.verify(&leaf, index, tree.get_root()).unwrap();
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use plonky2::{
     field::extension::Extendable,
     hash::hash_types::{HashOut, HashOutTarget, RichField},
-    iop::target::BoolTarget,
+    iop::target::{BoolTarget, Target},
     plonk::circuit_builder::CircuitBuilder,
 };
 use plonky2_u32::gadgets::arithmetic_u32::U32Target;
@@ -98,11 +98,28 @@ impl KeyLikeTarget for HashOutTarget {
     ) -> Vec<BoolTarget> {
         self.elements
             .iter()
-            .flat_map(|e| builder.split_le(*e, 64))
+            .flat_map(|e| canonical_split_le(builder, *e))
             .collect::<Vec<_>>()
     }
 }
 
+// Splits a Goldilocks element into its 64 bits. `split_le` alone also accepts `x + p` for small
+// `x`, which would give the same key two indices. The bits are canonical if and only if the low
+// 32 bits are zero whenever the high 32 bits are all one.
+fn canonical_split_le<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: Target,
+) -> Vec<BoolTarget> {
+    let bits = builder.split_le(x, 64);
+    let lo = builder.le_sum(bits[..32].iter());
+    let hi = builder.le_sum(bits[32..].iter());
+    let max_hi = builder.constant(F::from_canonical_u32(u32::MAX));
+    let is_max_hi = builder.is_equal(hi, max_hi);
+    let lo_if_max_hi = builder.mul(is_max_hi.target, lo);
+    builder.assert_zero(lo_if_max_hi);
+    bits
+}
+
 fn u8_to_le_bits(num: u8) -> Vec<bool> {
     let mut result = Vec::with_capacity(8);
     let mut n = num;
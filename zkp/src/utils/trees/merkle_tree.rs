@@ -9,8 +9,8 @@ use plonky2::{
 
 use crate::utils::leafable::{Leafable, LeafableTarget};
 
-// MekleTree is a structure of Merkle Tree used for `MerkleTreeWithLeaves`
-// and `SparseMerkleTreeWithLeaves`. It only holds non-zero nodes.
+// MekleTree is a structure of Merkle Tree used for `SparseMerkleTreeWithLeaves`.
+// It only holds non-zero nodes. Append-only trees use `DenseMerkleTree` instead.
 // All nodes are specified by path: Vec<bool>. The path is big endian.
 // Note that this is different from the original plonky2 Merkle Tree which
 // uses little endian path.
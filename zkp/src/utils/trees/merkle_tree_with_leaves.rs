@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use anyhow::ensure;
 use plonky2::{
     field::extension::Extendable,
     hash::hash_types::{HashOut, HashOutTarget, RichField},
@@ -5,7 +8,10 @@ use plonky2::{
     plonk::circuit_builder::CircuitBuilder,
 };
 
-use super::merkle_tree::{MerkleProof, MerkleProofTarget, MerkleTree};
+use super::{
+    dense_merkle_tree::DenseMerkleTree,
+    merkle_tree::{MerkleProof, MerkleProofTarget},
+};
 use crate::utils::{
     leafable::{Leafable, LeafableTarget},
     trees::merkle_tree::usize_le_bits,
@@ -14,13 +20,13 @@ use crate::utils::{
 // Merkle Tree that holds leaves as a vec. It is suitable for handling indexed leaves.
 #[derive(Debug, Clone)]
 pub struct MerkleTreeWithLeaves<F: RichField, V: Leafable<F>> {
-    merkle_tree: MerkleTree<F, V>,
+    merkle_tree: DenseMerkleTree<F, V>,
     leaves: Vec<V>,
 }
 
 impl<F: RichField, V: Leafable<F>> MerkleTreeWithLeaves<F, V> {
     pub fn new(height: usize) -> Self {
-        let merkle_tree = MerkleTree::new(height, V::empty_leaf().hash());
+        let merkle_tree = DenseMerkleTree::new(height, V::empty_leaf().hash());
         let leaves = vec![];
 
         Self {
@@ -62,22 +68,29 @@ impl<F: RichField, V: Leafable<F>> MerkleTreeWithLeaves<F, V> {
         assert!(index < (1 << self.height()));
         let leaf_hash = leaf.hash();
         self.leaves.push(leaf);
-        let index_bits = usize_le_bits(index, self.height());
-        self.merkle_tree.update_leaf(index_bits, leaf_hash);
+        self.merkle_tree.push(leaf_hash);
     }
 
     pub fn pop(&mut self) {
         assert!(!self.leaves.is_empty());
         self.leaves.pop();
-        let index = self.leaves.len();
-        let leaf = V::empty_leaf();
-        let index_bits = usize_le_bits(index, self.height());
-        self.merkle_tree.update_leaf(index_bits, leaf.hash());
+        self.merkle_tree.pop();
     }
 
     pub fn prove(&self, index: usize) -> MerkleProofWithLeaves<F, V> {
-        let index_bits = usize_le_bits(index, self.height());
-        MerkleProofWithLeaves(self.merkle_tree.prove(index_bits))
+        MerkleProofWithLeaves(self.merkle_tree.prove(index))
+    }
+
+    // Proves all the leaves of `indices` at once. The siblings shared by them are included only once.
+    pub fn prove_many(&self, indices: &[usize]) -> MerkleMultiproofWithLeaves<F, V> {
+        let siblings = multiproof_sibling_indices(self.height(), indices)
+            .into_iter()
+            .map(|(level, index)| self.merkle_tree.get_node_hash(level, index))
+            .collect();
+        MerkleMultiproofWithLeaves {
+            height: self.height(),
+            siblings,
+        }
     }
 }
 
@@ -97,6 +110,173 @@ impl<F: RichField, V: Leafable<F>> MerkleProofWithLeaves<F, V> {
     }
 }
 
+// Returns the (level, index) of the nodes that a multiproof of `indices` has to include,
+// in the order of level and then index. The other nodes are computed from the leaves.
+fn multiproof_sibling_indices(height: usize, indices: &[usize]) -> Vec<(usize, usize)> {
+    let mut nodes = indices.to_vec();
+    nodes.sort_unstable();
+    nodes.dedup();
+    let mut result = vec![];
+    for level in 0..height {
+        let mut parents = Vec::with_capacity(nodes.len());
+        let mut i = 0;
+        while i < nodes.len() {
+            let node = nodes[i];
+            if node & 1 == 0 && nodes.get(i + 1) == Some(&(node + 1)) {
+                i += 2;
+            } else {
+                result.push((level, node ^ 1));
+                i += 1;
+            }
+            parents.push(node >> 1);
+        }
+        nodes = parents;
+    }
+    result
+}
+
+#[derive(Debug, Clone)]
+pub struct MerkleMultiproofWithLeaves<F: RichField, V: Leafable<F>> {
+    pub(crate) height: usize,
+    pub(crate) siblings: Vec<V::HashOut>, // ordered by level and then by index
+}
+
+impl<F: RichField, V: Leafable<F>> MerkleMultiproofWithLeaves<F, V> {
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn num_siblings(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Merges the merkle proofs of `indices` into a multiproof.
+    pub fn from_proofs(
+        indices: &[usize],
+        proofs: &[MerkleProofWithLeaves<F, V>],
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            indices.len() == proofs.len(),
+            "the number of indices and proofs must be the same"
+        );
+        ensure!(!proofs.is_empty(), "no proofs to merge");
+        let height = proofs[0].0.height();
+        ensure!(
+            proofs.iter().all(|proof| proof.0.height() == height),
+            "the proofs must have the same height"
+        );
+        let siblings = multiproof_sibling_indices(height, indices)
+            .into_iter()
+            .map(|(level, index)| {
+                let i = indices
+                    .iter()
+                    .position(|&leaf_index| (leaf_index >> level) ^ 1 == index)
+                    .ok_or(anyhow::anyhow!("no proof contains the sibling"))?;
+                Ok(proofs[i].0.siblings[level].clone())
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { height, siblings })
+    }
+
+    /// Splits the multiproof into the merkle proof of each leaf.
+    pub fn to_proofs(
+        &self,
+        indices: &[usize],
+        leaves: &[V],
+    ) -> anyhow::Result<Vec<MerkleProofWithLeaves<F, V>>> {
+        let nodes = self.compute_nodes(indices, leaves)?;
+        let proofs = indices
+            .iter()
+            .map(|&index| {
+                let siblings = (0..self.height)
+                    .map(|level| nodes[&(level, (index >> level) ^ 1)].clone())
+                    .collect();
+                MerkleProofWithLeaves(MerkleProof { siblings })
+            })
+            .collect();
+        Ok(proofs)
+    }
+
+    pub fn get_root(&self, indices: &[usize], leaves: &[V]) -> anyhow::Result<V::HashOut> {
+        let nodes = self.compute_nodes(indices, leaves)?;
+        Ok(nodes[&(self.height, 0)].clone())
+    }
+
+    pub fn verify(
+        &self,
+        indices: &[usize],
+        leaves: &[V],
+        merkle_root: V::HashOut,
+    ) -> anyhow::Result<()> {
+        let root = self.get_root(indices, leaves)?;
+        ensure!(root == merkle_root, "Merkle multiproof verification failed");
+        Ok(())
+    }
+
+    // Returns the hashes of the nodes on the paths of the leaves and their siblings,
+    // keyed by (level, index).
+    fn compute_nodes(
+        &self,
+        indices: &[usize],
+        leaves: &[V],
+    ) -> anyhow::Result<HashMap<(usize, usize), V::HashOut>> {
+        ensure!(!indices.is_empty(), "no leaves to verify");
+        ensure!(
+            indices.len() == leaves.len(),
+            "the number of indices and leaves must be the same"
+        );
+        let mut current = indices
+            .iter()
+            .zip(leaves.iter())
+            .map(|(&index, leaf)| {
+                ensure!(
+                    self.height >= usize::BITS as usize || index < (1 << self.height),
+                    "index out of range"
+                );
+                Ok((index, leaf.hash()))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        current.sort_by_key(|(index, _)| *index);
+        ensure!(
+            current.windows(2).all(|w| w[0].0 != w[1].0),
+            "duplicate indices"
+        );
+
+        let mut nodes = HashMap::new();
+        let mut siblings = self.siblings.iter();
+        for level in 0..self.height {
+            let mut parents = Vec::with_capacity(current.len());
+            let mut i = 0;
+            while i < current.len() {
+                let (index, h) = current[i].clone();
+                nodes.insert((level, index), h.clone());
+                let sibling = match current.get(i + 1) {
+                    Some((next_index, next_h)) if index & 1 == 0 && *next_index == index + 1 => {
+                        i += 1;
+                        next_h.clone()
+                    }
+                    _ => siblings
+                        .next()
+                        .ok_or(anyhow::anyhow!("too few siblings"))?
+                        .clone(),
+                };
+                nodes.insert((level, index ^ 1), sibling.clone());
+                let parent = if index & 1 == 1 {
+                    V::two_to_one(&sibling, &h)
+                } else {
+                    V::two_to_one(&h, &sibling)
+                };
+                parents.push((index >> 1, parent));
+                i += 1;
+            }
+            current = parents;
+        }
+        ensure!(siblings.next().is_none(), "too many siblings");
+        nodes.insert((self.height, 0), current[0].1.clone());
+        Ok(nodes)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MerkleProofWithLeavesTarget<VT: LeafableTarget>(MerkleProofTarget<VT>);
 
@@ -191,6 +371,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_merkle_multiproof_with_leaves() {
+        let mut rng = rand::thread_rng();
+        let height = 10;
+
+        type V = Vec<F>;
+        let mut tree = MerkleTreeWithLeaves::<F, V>::new(height);
+        for _ in 0..100 {
+            tree.push(vec![F::rand()]);
+        }
+
+        // neighbouring indices share most of the siblings
+        let mut indices = (0..8).map(|_| rng.gen_range(0..120)).collect::<Vec<_>>();
+        indices.sort();
+        indices.dedup();
+        let leaves = indices
+            .iter()
+            .map(|&i| tree.get_leaf(i))
+            .collect::<Vec<_>>();
+        let multiproof = tree.prove_many(&indices);
+        assert!(multiproof.num_siblings() < indices.len() * height);
+        multiproof
+            .verify(&indices, &leaves, tree.get_root())
+            .unwrap();
+
+        // the order of the leaves does not matter
+        let reversed_indices = indices.iter().rev().cloned().collect::<Vec<_>>();
+        let reversed_leaves = leaves.iter().rev().cloned().collect::<Vec<_>>();
+        multiproof
+            .verify(&reversed_indices, &reversed_leaves, tree.get_root())
+            .unwrap();
+
+        // conversion from and to the single proofs
+        let proofs = indices.iter().map(|&i| tree.prove(i)).collect::<Vec<_>>();
+        let merged = MerkleMultiproofWithLeaves::from_proofs(&indices, &proofs).unwrap();
+        assert_eq!(merged.siblings, multiproof.siblings);
+        assert!(MerkleMultiproofWithLeaves::from_proofs(&indices, &proofs[1..]).is_err());
+        assert!(MerkleMultiproofWithLeaves::<F, V>::from_proofs(&[], &[]).is_err());
+        let split = multiproof.to_proofs(&indices, &leaves).unwrap();
+        for ((proof, &index), leaf) in split.iter().zip(indices.iter()).zip(leaves.iter()) {
+            assert_eq!(proof.0.siblings, tree.prove(index).0.siblings);
+            proof.verify(leaf, index, tree.get_root()).unwrap();
+        }
+
+        // a wrong leaf
+        let mut wrong_leaves = leaves.clone();
+        wrong_leaves[0] = vec![F::rand()];
+        assert!(multiproof
+            .verify(&indices, &wrong_leaves, tree.get_root())
+            .is_err());
+    }
+
     #[test]
     fn test_merkle_tree_with_leaves_circuit() {
         let mut rng = rand::thread_rng();
@@ -1,4 +1,7 @@
+pub mod dense_merkle_tree;
+pub mod frontier_merkle_tree;
 pub mod get_root;
 pub mod keylike;
 pub mod merkle_tree;
 pub mod merkle_tree_with_leaves;
+pub mod sparse_merkle_tree_with_leaves;
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use anyhow::ensure;
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::{HashOut, HashOutTarget, RichField},
+    iop::{target::BoolTarget, witness::Witness},
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use super::{
+    keylike::{KeyLike, KeyLikeTarget},
+    merkle_tree::{MerkleProof, MerkleProofTarget, MerkleTree},
+};
+use crate::utils::leafable::{Leafable, LeafableTarget};
+
+// Merkle Tree that holds leaves in a map. It is suitable for handling keyed leaves
+// such as nullifiers or account states. All bits of a key are its index, so the height
+// of the tree is the bit length of the key.
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTreeWithLeaves<F: RichField, K: KeyLike, V: Leafable<F>> {
+    merkle_tree: MerkleTree<F, V>,
+    leaves: HashMap<K, V>,
+}
+
+impl<F: RichField, K: KeyLike, V: Leafable<F>> SparseMerkleTreeWithLeaves<F, K, V> {
+    pub fn new() -> Self {
+        let height = K::default().to_bits().len();
+        let merkle_tree = MerkleTree::new(height, V::empty_leaf().hash());
+        let leaves = HashMap::new();
+
+        Self {
+            merkle_tree,
+            leaves,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.merkle_tree.height()
+    }
+
+    // NOTICE: `None` and `V::empty_leaf()` are treated equivalently.
+    pub fn get_leaf(&self, key: K) -> V {
+        match self.leaves.get(&key) {
+            Some(leaf) => leaf.clone(),
+            None => V::empty_leaf(),
+        }
+    }
+
+    pub fn get_root(&self) -> V::HashOut {
+        self.merkle_tree.get_root()
+    }
+
+    pub fn leaves(&self) -> Vec<(K, V)> {
+        self.leaves
+            .iter()
+            .map(|(key, leaf)| (*key, leaf.clone()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn contains(&self, key: K) -> bool {
+        self.leaves.contains_key(&key)
+    }
+
+    pub fn update(&mut self, key: K, leaf: V) {
+        let leaf_hash = leaf.hash();
+        if leaf_hash == V::empty_leaf().hash() {
+            self.leaves.remove(&key);
+        } else {
+            self.leaves.insert(key, leaf);
+        }
+        self.merkle_tree.update_leaf(key.to_bits(), leaf_hash);
+    }
+
+    pub fn remove(&mut self, key: K) {
+        self.update(key, V::empty_leaf());
+    }
+
+    // The proof of a key that is not in the tree proves its non-inclusion.
+    pub fn prove(&self, key: K) -> SparseMerkleProofWithLeaves<F, V> {
+        SparseMerkleProofWithLeaves(self.merkle_tree.prove(key.to_bits()))
+    }
+}
+
+impl<F: RichField, K: KeyLike, V: Leafable<F>> Default for SparseMerkleTreeWithLeaves<F, K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SparseMerkleProofWithLeaves<F: RichField, V: Leafable<F>>(pub(crate) MerkleProof<F, V>);
+
+impl<F: RichField, V: Leafable<F>> SparseMerkleProofWithLeaves<F, V> {
+    pub fn verify<K: KeyLike>(
+        &self,
+        leaf_data: &V,
+        key: K,
+        merkle_root: V::HashOut,
+    ) -> anyhow::Result<()> {
+        let index_bits = key.to_bits();
+        ensure!(
+            index_bits.len() == self.0.height(),
+            "the height of the proof must be the bit length of the key"
+        );
+        self.0.verify(leaf_data, index_bits, merkle_root)
+    }
+
+    /// Verifies that the leaf of `key` is empty.
+    pub fn verify_non_inclusion<K: KeyLike>(
+        &self,
+        key: K,
+        merkle_root: V::HashOut,
+    ) -> anyhow::Result<()> {
+        self.verify(&V::empty_leaf(), key, merkle_root)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SparseMerkleProofWithLeavesTarget<VT: LeafableTarget>(MerkleProofTarget<VT>);
+
+impl<VT: LeafableTarget<HashOutTarget = HashOutTarget>> SparseMerkleProofWithLeavesTarget<VT> {
+    pub fn new<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+        height: usize,
+    ) -> Self {
+        Self(MerkleProofTarget::new(builder, height))
+    }
+
+    pub fn constant<
+        F: RichField + Extendable<D>,
+        const D: usize,
+        V: Leafable<F, HashOut = HashOut<F>>,
+    >(
+        builder: &mut CircuitBuilder<F, D>,
+        value: &SparseMerkleProofWithLeaves<F, V>,
+    ) -> Self {
+        Self(MerkleProofTarget::constant(builder, &value.0))
+    }
+
+    pub fn set_witness<F: RichField, V: Leafable<F, HashOut = HashOut<F>>>(
+        &self,
+        pw: &mut impl Witness<F>,
+        merkle_proof: &SparseMerkleProofWithLeaves<F, V>,
+    ) {
+        self.0.set_witness(pw, &merkle_proof.0)
+    }
+}
+
+impl<VT: LeafableTarget> SparseMerkleProofWithLeavesTarget<VT> {
+    pub fn get_root<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        leaf_data: &VT,
+        key: &impl KeyLikeTarget,
+    ) -> VT::HashOutTarget {
+        let index_bits = self.index_bits(builder, key);
+        self.0.get_root(builder, leaf_data, index_bits)
+    }
+
+    pub fn verify<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        leaf_data: &VT,
+        key: &impl KeyLikeTarget,
+        merkle_root: VT::HashOutTarget,
+    ) {
+        let index_bits = self.index_bits(builder, key);
+        self.0.verify(builder, leaf_data, index_bits, merkle_root)
+    }
+
+    /// Constrains the leaf of `key` to be empty.
+    pub fn verify_non_inclusion<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        key: &impl KeyLikeTarget,
+        merkle_root: VT::HashOutTarget,
+    ) {
+        let empty_leaf = VT::empty_leaf(builder);
+        self.verify(builder, &empty_leaf, key, merkle_root)
+    }
+
+    fn index_bits<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        key: &impl KeyLikeTarget,
+    ) -> Vec<BoolTarget> {
+        let index_bits = key.to_bits(builder);
+        assert_eq!(
+            index_bits.len(),
+            self.0.height(),
+            "the height of the proof must be the bit length of the key"
+        );
+        index_bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::{
+        field::types::Sample,
+        iop::{
+            target::Target,
+            witness::{PartialWitness, WitnessWrite},
+        },
+        plonk::{
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+
+    use crate::utils::u256::{U256Target, U256};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_sparse_merkle_tree_with_leaves() {
+        let mut rng = rand::thread_rng();
+
+        type V = Vec<F>;
+        let mut tree = SparseMerkleTreeWithLeaves::<F, U256, V>::new();
+        assert_eq!(tree.height(), 256);
+
+        let keys = (0..100).map(|_| U256::rand(&mut rng)).collect::<Vec<_>>();
+        for key in keys.iter() {
+            tree.update(*key, vec![F::rand()]);
+        }
+        assert_eq!(tree.len(), keys.len());
+
+        for key in keys.iter() {
+            let leaf = tree.get_leaf(*key);
+            let proof = tree.prove(*key);
+            proof.verify(&leaf, *key, tree.get_root()).unwrap();
+            assert!(proof.verify_non_inclusion(*key, tree.get_root()).is_err());
+        }
+
+        let absent_key = U256::rand(&mut rng);
+        assert!(!tree.contains(absent_key));
+        let proof = tree.prove(absent_key);
+        proof
+            .verify_non_inclusion(absent_key, tree.get_root())
+            .unwrap();
+
+        // removing a key restores the root
+        let root = tree.get_root();
+        tree.update(absent_key, vec![F::rand()]);
+        assert_ne!(tree.get_root(), root);
+        tree.remove(absent_key);
+        assert_eq!(tree.get_root(), root);
+        assert_eq!(tree.len(), keys.len());
+
+        // a key which shares all but the highest bit with an included key is not included
+        let key = keys[0];
+        let mut bytes = key.to_le_bytes();
+        bytes[31] ^= 0x80;
+        let other_key = U256::from_le_bytes(bytes);
+        assert!(!tree.contains(other_key));
+        let proof = tree.prove(other_key);
+        proof
+            .verify_non_inclusion(other_key, tree.get_root())
+            .unwrap();
+        assert!(proof
+            .verify(&tree.get_leaf(key), other_key, tree.get_root())
+            .is_err());
+        tree.update(other_key, vec![F::rand()]);
+        assert_ne!(tree.get_leaf(key), tree.get_leaf(other_key));
+        assert_eq!(tree.len(), keys.len() + 1);
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree_with_leaves_circuit() {
+        let mut rng = rand::thread_rng();
+
+        type V = Vec<F>;
+        type VT = Vec<Target>;
+        let mut tree = SparseMerkleTreeWithLeaves::<F, U256, V>::new();
+        let height = tree.height();
+        for _ in 0..10 {
+            tree.update(U256::rand(&mut rng), vec![F::rand()]);
+        }
+        let key = tree.leaves()[0].0;
+        let leaf = tree.get_leaf(key);
+        let proof = tree.prove(key);
+        let absent_key = U256::rand(&mut rng);
+        let non_inclusion_proof = tree.prove(absent_key);
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let root_t = builder.add_virtual_hash();
+        let proof_t = SparseMerkleProofWithLeavesTarget::<VT>::new(&mut builder, height);
+        let leaf_t = vec![builder.add_virtual_target()];
+        let key_t = U256Target::new_unsafe(&mut builder);
+        proof_t.verify(&mut builder, &leaf_t, &key_t, root_t);
+        let non_inclusion_proof_t =
+            SparseMerkleProofWithLeavesTarget::<VT>::new(&mut builder, height);
+        let absent_key_t = U256Target::new_unsafe(&mut builder);
+        non_inclusion_proof_t.verify_non_inclusion(&mut builder, &absent_key_t, root_t);
+
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_hash_target(root_t, tree.get_root());
+        pw.set_target_arr(&leaf_t, &leaf);
+        key_t.set_witness(&mut pw, key);
+        proof_t.set_witness(&mut pw, &proof);
+        absent_key_t.set_witness(&mut pw, absent_key);
+        non_inclusion_proof_t.set_witness(&mut pw, &non_inclusion_proof);
+        data.prove(pw).unwrap();
+    }
+}
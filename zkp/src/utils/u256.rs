@@ -1,5 +1,4 @@
 use num_bigint::BigUint;
-use num_traits::Num;
 use plonky2::{
     field::{
         extension::Extendable,
@@ -44,9 +43,7 @@ impl Serialize for U256 {
 impl<'de> Deserialize<'de> for U256 {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let s = String::deserialize(deserializer)?;
-        let b = BigUint::from_str_radix(&s, 10).map_err(serde::de::Error::custom)?;
-        let u: U256 = b.try_into().unwrap();
-        Ok(u)
+        U256::from_dec_str(&s).map_err(serde::de::Error::custom)
     }
 }
 
@@ -98,8 +95,7 @@ impl U256 {
     }
 
     pub fn does_overflow_after_add(&self, other: &Self) -> bool {
-        let max_minus_self = Self::max() - *self;
-        max_minus_self < *other
+        self.checked_add(*other).is_none()
     }
 
     /// NOTICE: without 0x-prefix
@@ -179,25 +175,272 @@ impl num::Zero for U256 {
     }
 }
 
-impl std::ops::Add for U256 {
-    type Output = Self;
+impl num::One for U256 {
+    fn one() -> Self {
+        Self::from(1u64)
+    }
+}
 
-    fn add(self, rhs: Self) -> Self::Output {
-        let mut result_limbs = vec![];
+impl std::str::FromStr for U256 {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Self::from_dec_str(s)
+    }
+}
+
+// Arithmetic on the big endian limbs.
+impl U256 {
+    /// Returns `self + rhs` modulo 2^256 and whether it overflowed.
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let mut limbs = [0u32; 8];
         let mut carry = 0u64;
-        for (a, b) in self.0.iter().rev().zip(rhs.0.iter().rev()) {
-            let c = carry + *a as u64 + *b as u64;
-            let result = c as u32;
+        for (limb, (a, b)) in limbs.iter_mut().zip(self.0.iter().zip(rhs.0.iter())).rev() {
+            let c = *a as u64 + *b as u64 + carry;
+            *limb = c as u32;
             carry = c >> 32;
-            result_limbs.push(result);
         }
 
-        // Carry should be zero here.
-        assert_eq!(carry, 0, "U256 addition overflow occured");
+        (Self(limbs), carry != 0)
+    }
 
-        result_limbs.reverse();
+    /// Returns `self - rhs` modulo 2^256 and whether it underflowed.
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let mut limbs = [0u32; 8];
+        let mut borrow = false;
+        for (limb, (a, b)) in limbs.iter_mut().zip(self.0.iter().zip(rhs.0.iter())).rev() {
+            let (d, b1) = a.overflowing_sub(*b);
+            let (d, b2) = d.overflowing_sub(borrow as u32);
+            *limb = d;
+            borrow = b1 || b2;
+        }
 
-        Self(result_limbs.try_into().unwrap())
+        (Self(limbs), borrow)
+    }
+
+    /// Returns `self * rhs` modulo 2^256 and whether it overflowed.
+    pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        // schoolbook multiplication on little endian limbs
+        let lhs_limbs = self.0.iter().rev().copied().collect::<Vec<_>>();
+        let rhs_limbs = rhs.0.iter().rev().copied().collect::<Vec<_>>();
+        let mut product = [0u32; 16];
+        for (i, a) in lhs_limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in rhs_limbs.iter().enumerate() {
+                let t = *a as u64 * *b as u64 + product[i + j] as u64 + carry;
+                product[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            product[i + 8] = carry as u32;
+        }
+
+        let mut limbs: [u32; 8] = product[0..8].try_into().unwrap();
+        limbs.reverse();
+        let overflow = product[8..].iter().any(|v| *v != 0);
+
+        (Self(limbs), overflow)
+    }
+
+    /// Returns `(self / rhs, self % rhs)`.
+    /// Panics if `rhs` is zero.
+    pub fn div_rem(self, rhs: Self) -> (Self, Self) {
+        assert!(!num::Zero::is_zero(&rhs), "U256 division by zero");
+
+        // binary long division
+        let mut quotient = Self::default();
+        let mut remainder = Self::default();
+        for i in (0..256).rev() {
+            let (shifted, overflow) = remainder.overflowing_shl1(self.bit(i));
+            // `2 * remainder + bit < 2 * rhs`, so subtracting `rhs` once is enough.
+            if overflow || shifted >= rhs {
+                remainder = shifted.overflowing_sub(rhs).0;
+                quotient.set_bit(i);
+            } else {
+                remainder = shifted;
+            }
+        }
+
+        (quotient, remainder)
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_add(rhs) {
+            (result, false) => Some(result),
+            (_, true) => None,
+        }
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_sub(rhs) {
+            (result, false) => Some(result),
+            (_, true) => None,
+        }
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_mul(rhs) {
+            (result, false) => Some(result),
+            (_, true) => None,
+        }
+    }
+
+    /// Returns `None` if `rhs` is zero.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        if num::Zero::is_zero(&rhs) {
+            return None;
+        }
+
+        Some(self.div_rem(rhs).0)
+    }
+
+    /// Returns `None` if `rhs` is zero.
+    pub fn checked_rem(self, rhs: Self) -> Option<Self> {
+        if num::Zero::is_zero(&rhs) {
+            return None;
+        }
+
+        Some(self.div_rem(rhs).1)
+    }
+
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        self.overflowing_add(rhs).0
+    }
+
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        self.overflowing_sub(rhs).0
+    }
+
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        self.overflowing_mul(rhs).0
+    }
+
+    /// Division never overflows, so this is the same as `self / rhs`.
+    pub fn wrapping_div(self, rhs: Self) -> Self {
+        self / rhs
+    }
+
+    /// Division never overflows, so this is the same as `self % rhs`.
+    pub fn wrapping_rem(self, rhs: Self) -> Self {
+        self % rhs
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).unwrap_or(Self::max())
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).unwrap_or_default()
+    }
+
+    pub fn saturating_mul(self, rhs: Self) -> Self {
+        self.checked_mul(rhs).unwrap_or(Self::max())
+    }
+
+    /// Division never overflows, so this is the same as `self / rhs`.
+    pub fn saturating_div(self, rhs: Self) -> Self {
+        self / rhs
+    }
+
+    /// Division never overflows, so this is the same as `self % rhs`.
+    pub fn saturating_rem(self, rhs: Self) -> Self {
+        self % rhs
+    }
+
+    /// Returns `|self - other|`.
+    pub fn abs_diff(self, other: Self) -> Self {
+        if self >= other {
+            self - other
+        } else {
+            other - self
+        }
+    }
+
+    /// Returns the `i`-th bit from the least significant one.
+    fn bit(&self, i: usize) -> bool {
+        (self.0[7 - i / 32] >> (i % 32)) & 1 == 1
+    }
+
+    fn set_bit(&mut self, i: usize) {
+        self.0[7 - i / 32] |= 1 << (i % 32);
+    }
+
+    /// Returns `2 * self + bit` modulo 2^256 and whether it overflowed.
+    fn overflowing_shl1(self, bit: bool) -> (Self, bool) {
+        let mut limbs = [0u32; 8];
+        let mut carry = bit as u32;
+        for (limb, a) in limbs.iter_mut().zip(self.0.iter()).rev() {
+            *limb = (a << 1) | carry;
+            carry = a >> 31;
+        }
+
+        (Self(limbs), carry != 0)
+    }
+}
+
+// Decimal strings.
+impl U256 {
+    /// Parses a decimal string without sign, separators or leading `+`.
+    pub fn from_dec_str(s: &str) -> anyhow::Result<Self> {
+        anyhow::ensure!(!s.is_empty(), "empty decimal string");
+        let ten = Self::from(10u64);
+        let mut result = Self::default();
+        for c in s.chars() {
+            let digit = c
+                .to_digit(10)
+                .ok_or_else(|| anyhow::anyhow!("invalid decimal digit: {:?}", c))?;
+            result = result
+                .checked_mul(ten)
+                .and_then(|v| v.checked_add(Self::from(digit as u64)))
+                .ok_or_else(|| anyhow::anyhow!("decimal string overflows U256: {}", s))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Parses an amount with `decimals` fractional digits, e.g. "1.5" with 18 decimals
+    /// is `1500000000000000000`. More fractional digits than `decimals` are rejected.
+    pub fn from_str_with_decimals(s: &str, decimals: usize) -> anyhow::Result<Self> {
+        let (integer, fraction) = match s.split_once('.') {
+            Some((integer, fraction)) => {
+                anyhow::ensure!(!fraction.is_empty(), "empty fractional part: {}", s);
+                (integer, fraction)
+            }
+            None => (s, ""),
+        };
+        anyhow::ensure!(!integer.is_empty(), "empty integer part: {}", s);
+        anyhow::ensure!(
+            fraction.len() <= decimals,
+            "{} has more than {} fractional digits",
+            s,
+            decimals
+        );
+        let padding = "0".repeat(decimals - fraction.len());
+
+        Self::from_dec_str(&format!("{}{}{}", integer, fraction, padding))
+    }
+
+    /// Formats the amount with `decimals` fractional digits, e.g. `1500000000000000000`
+    /// with 18 decimals is "1.5". Trailing zeros of the fractional part are omitted.
+    pub fn to_string_with_decimals(&self, decimals: usize) -> String {
+        let digits = format!("{:0>width$}", self.to_string(), width = decimals + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            integer.to_string()
+        } else {
+            format!("{}.{}", integer, fraction)
+        }
+    }
+}
+
+impl std::ops::Add for U256 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let (result, overflow) = self.overflowing_add(rhs);
+        assert!(!overflow, "U256 addition overflow occured");
+
+        result
     }
 }
 
@@ -211,22 +454,10 @@ impl std::ops::Sub for U256 {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        let mut result_limbs = vec![];
-
-        let mut borrow = 0i64;
-        for (a, b) in self.0.iter().rev().zip(rhs.0.iter().rev()) {
-            let c = *a as i64 - *b as i64 + borrow;
-            let result = c as u32;
-            borrow = (c >> 32) as i32 as i64;
-            result_limbs.push(result);
-        }
+        let (result, underflow) = self.overflowing_sub(rhs);
+        assert!(!underflow, "U256 sub underflow occured");
 
-        // Borrow should be zero here.
-        assert_eq!(borrow, 0, "U256 sub underflow occured");
-
-        result_limbs.reverse();
-
-        Self(result_limbs.try_into().unwrap())
+        result
     }
 }
 
@@ -236,6 +467,51 @@ impl std::ops::SubAssign for U256 {
     }
 }
 
+impl std::ops::Mul for U256 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (result, overflow) = self.overflowing_mul(rhs);
+        assert!(!overflow, "U256 multiplication overflow occured");
+
+        result
+    }
+}
+
+impl std::ops::MulAssign for U256 {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl std::ops::Div for U256 {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.div_rem(rhs).0
+    }
+}
+
+impl std::ops::DivAssign for U256 {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl std::ops::Rem for U256 {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.div_rem(rhs).1
+    }
+}
+
+impl std::ops::RemAssign for U256 {
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
 /// Solidity uint256
 /// big endian
 #[derive(Copy, Clone, Debug)]
@@ -446,7 +722,7 @@ mod tests {
             config::{GenericConfig, PoseidonGoldilocksConfig},
         },
     };
-    use rand::thread_rng;
+    use rand::{thread_rng, Rng};
 
     use crate::utils::{
         h256::{H256Target, H256},
@@ -536,6 +812,98 @@ mod tests {
         _ = b - a;
     }
 
+    // random value whose upper limbs are zero with some probability
+    fn rand_u256<R: Rng>(rng: &mut R) -> U256 {
+        let mut v = U256::rand(rng);
+        let num_zero_limbs = rng.gen_range(0..8);
+        for limb in v.0.iter_mut().take(num_zero_limbs) {
+            *limb = 0;
+        }
+        v
+    }
+
+    #[test]
+    fn test_u256_arithmetic() {
+        let mut rng = thread_rng();
+        let modulus = BigUint::from(1u8) << 256;
+        for _ in 0..1000 {
+            let a = rand_u256(&mut rng);
+            let b = rand_u256(&mut rng);
+            let a_b: BigUint = a.into();
+            let b_b: BigUint = b.into();
+
+            let sum = &a_b + &b_b;
+            assert_eq!(a.checked_add(b).is_none(), sum >= modulus);
+            let expected: U256 = (sum % &modulus).try_into().unwrap();
+            assert_eq!(a.wrapping_add(b), expected);
+
+            let product = &a_b * &b_b;
+            assert_eq!(a.checked_mul(b).is_none(), product >= modulus);
+            let expected: U256 = (product % &modulus).try_into().unwrap();
+            assert_eq!(a.wrapping_mul(b), expected);
+
+            let (large, small) = if a >= b { (a, b) } else { (b, a) };
+            let diff: U256 = (BigUint::from(large) - BigUint::from(small))
+                .try_into()
+                .unwrap();
+            assert_eq!(large.checked_sub(small), Some(diff));
+            assert_eq!(small.saturating_sub(large), U256::default());
+            assert_eq!(a.abs_diff(b), diff);
+
+            if num::Zero::is_zero(&b) {
+                assert_eq!(a.checked_div(b), None);
+                continue;
+            }
+            let quotient: U256 = (&a_b / &b_b).try_into().unwrap();
+            let remainder: U256 = (&a_b % &b_b).try_into().unwrap();
+            assert_eq!(a / b, quotient);
+            assert_eq!(a % b, remainder);
+        }
+
+        assert_eq!(U256::max().saturating_add(U256::from(1u64)), U256::max());
+        assert_eq!(U256::max().wrapping_add(U256::from(1u64)), U256::default());
+        assert_eq!(U256::default().wrapping_sub(U256::from(1u64)), U256::max());
+        assert_eq!(U256::max().checked_rem(U256::default()), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_u256_mul_overflow() {
+        _ = U256::max() * U256::from(2u64);
+    }
+
+    #[test]
+    fn test_u256_decimal_string() {
+        let x = U256::rand(&mut thread_rng());
+        assert_eq!(U256::from_dec_str(&x.to_string()).unwrap(), x);
+        assert_eq!("123".parse::<U256>().unwrap(), U256::from(123u64));
+        assert!(U256::from_dec_str("").is_err());
+        assert!(U256::from_dec_str("-1").is_err());
+        assert!(U256::from_dec_str(&format!("{}0", U256::max())).is_err());
+
+        let one_and_half = U256::from(1_500_000_000_000_000_000u64);
+        assert_eq!(
+            U256::from_str_with_decimals("1.5", 18).unwrap(),
+            one_and_half
+        );
+        assert_eq!(one_and_half.to_string_with_decimals(18), "1.5");
+        assert_eq!(
+            U256::from(5u64).to_string_with_decimals(18),
+            "0.000000000000000005"
+        );
+        assert_eq!(U256::from(100u64).to_string_with_decimals(2), "1");
+        assert_eq!(U256::default().to_string_with_decimals(6), "0");
+        assert_eq!(U256::from(42u64).to_string_with_decimals(0), "42");
+        assert_eq!(
+            U256::from_str_with_decimals("42", 0).unwrap(),
+            U256::from(42u64)
+        );
+        assert!(U256::from_str_with_decimals("1.1234567", 6).is_err());
+        assert!(U256::from_str_with_decimals(".5", 6).is_err());
+        assert!(U256::from_str_with_decimals("1.", 6).is_err());
+        assert!(U256::from_str_with_decimals("1.2.3", 6).is_err());
+    }
+
     #[test]
     fn test_assert_u32_target() {
         const D: usize = 2;
@@ -609,4 +977,16 @@ mod tests {
         assert_eq!(x, x_recovered);
         println!("{}", x_str);
     }
+
+    #[test]
+    fn test_deserialize_u256_out_of_range() {
+        let max: U256 = serde_json::from_str(&format!("\"{}\"", U256::max())).unwrap();
+        assert_eq!(max, U256::max());
+        // 2^256
+        let result = serde_json::from_str::<U256>(
+            "\"115792089237316195423570985008687907853269984665640564039457584007913129639936\"",
+        );
+        assert!(result.unwrap_err().to_string().contains("overflows U256"));
+        assert!(serde_json::from_str::<U256>("\"-1\"").is_err());
+    }
 }
@@ -0,0 +1,123 @@
+use plonky2::{
+    field::extension::Extendable,
+    gates::{
+        arithmetic_base::ArithmeticGate, arithmetic_extension::ArithmeticExtensionGate,
+        base_sum::BaseSumGate, constant::ConstantGate, coset_interpolation::CosetInterpolationGate,
+        exponentiation::ExponentiationGate, multiplication_extension::MulExtensionGate,
+        noop::NoopGate, poseidon::PoseidonGate, poseidon_mds::PoseidonMdsGate,
+        public_input::PublicInputGate, random_access::RandomAccessGate, reducing::ReducingGate,
+        reducing_extension::ReducingExtensionGate,
+    },
+    get_gate_tag_impl,
+    hash::hash_types::RichField,
+    impl_gate_serializer, read_gate_impl,
+    util::serialization::GateSerializer,
+};
+use plonky2_u32::gates::{
+    add_many_u32::U32AddManyGate, arithmetic_u32::U32ArithmeticGate, comparison::ComparisonGate,
+    range_check_u32::U32RangeCheckGate, subtraction_u32::U32SubtractionGate,
+};
+
+/// Serializes the gates used by the circuits of this crate, i.e. the default gates of plonky2
+/// and the u32 gates. It is needed to write and read `CommonCircuitData`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlasmaGateSerializer;
+
+impl<F: RichField + Extendable<D>, const D: usize> GateSerializer<F, D> for PlasmaGateSerializer {
+    impl_gate_serializer! {
+        PlasmaGateSerializer,
+        ArithmeticGate,
+        ArithmeticExtensionGate<D>,
+        BaseSumGate<2>,
+        ConstantGate,
+        CosetInterpolationGate<F, D>,
+        ExponentiationGate<F, D>,
+        MulExtensionGate<D>,
+        NoopGate,
+        PoseidonMdsGate<F, D>,
+        PoseidonGate<F, D>,
+        PublicInputGate,
+        RandomAccessGate<F, D>,
+        ReducingExtensionGate<D>,
+        ReducingGate<D>,
+        U32AddManyGate<F, D>,
+        U32ArithmeticGate<F, D>,
+        ComparisonGate<F, D>,
+        U32RangeCheckGate<F, D>,
+        U32SubtractionGate<F, D>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    use crate::{
+        base_circuits::block_tree_circuit::{BlockTreeCircuit, BlockTreeValue},
+        common::block::Block,
+        serialization::serialized_proof::{CircuitKind, SerializedProof},
+        utils::trees::merkle_tree_with_leaves::MerkleTreeWithLeaves,
+        verifier::verifier_data::{
+            export_verifier_data, load_verifier_data, verify_serialized_proof,
+        },
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    // The block tree circuit is cyclic, so its common data contains the gates of the
+    // recursive verifier in addition to the u32 gates.
+    #[test]
+    #[cfg_attr(feature = "mock", ignore)]
+    fn test_export_recursive_circuit() {
+        let block_tree_circuit = BlockTreeCircuit::<F, C, D>::new().unwrap();
+
+        let dir = std::env::temp_dir().join(format!("verifier_data_{}", rand::random::<u64>()));
+        export_verifier_data(&dir, CircuitKind::BlockTree, &block_tree_circuit.data).unwrap();
+        let verifier_data = load_verifier_data::<F, C, D>(&dir, CircuitKind::BlockTree).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        assert_eq!(verifier_data.common, block_tree_circuit.data.common);
+        assert_eq!(
+            verifier_data.verifier_only,
+            block_tree_circuit.data.verifier_only
+        );
+
+        let mut block_tree = MerkleTreeWithLeaves::<F, Block>::new(32);
+        let block0 = Block::default();
+        block_tree.push(block0.clone());
+        let block_root0 = block_tree.get_root();
+        let block1 = Block {
+            prev_block_hash: block0.block_hash(),
+            block_number: 1,
+            ..Block::default()
+        };
+        block_tree.push(block1.clone());
+        let block_root1 = block_tree.get_root();
+        let merkle_proof = block_tree.prove(1);
+        let value = BlockTreeValue::new(block1, block_root0, block_root1, merkle_proof);
+        let proof = block_tree_circuit.prove(&value, &None).unwrap();
+        let serialized_proof =
+            SerializedProof::from_proof(CircuitKind::BlockTree, &block_tree_circuit.data, &proof);
+        let recovered =
+            verify_serialized_proof(CircuitKind::BlockTree, &verifier_data, &serialized_proof)
+                .unwrap();
+        assert_eq!(recovered.public_inputs, proof.public_inputs);
+
+        // the verifier data is appended to the public inputs of a cyclic proof
+        let mut foreign_proof = proof.clone();
+        *foreign_proof.public_inputs.last_mut().unwrap() += F::ONE;
+        let serialized_proof = SerializedProof::from_proof(
+            CircuitKind::BlockTree,
+            &block_tree_circuit.data,
+            &foreign_proof,
+        );
+        let err =
+            verify_serialized_proof(CircuitKind::BlockTree, &verifier_data, &serialized_proof)
+                .unwrap_err();
+        assert!(err.to_string().contains("verifier data"));
+    }
+}
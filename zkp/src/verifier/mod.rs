@@ -0,0 +1,2 @@
+pub mod gate_serializer;
+pub mod verifier_data;
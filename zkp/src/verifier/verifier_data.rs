@@ -0,0 +1,258 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, ensure};
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_data::{
+            CircuitData, CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+        },
+        config::{GenericConfig, GenericHashOut},
+        proof::ProofWithPublicInputs,
+    },
+};
+
+use crate::{
+    base_circuits::{
+        block_tree_circuit::BlockTreePublicInputs, spent_circuit::SpentPublicInputs,
+        validity_circuit::ValidityPublicInputs, withdraw_circuit::WithdrawPublicInputs,
+    },
+    serialization::serialized_proof::{CircuitKind, SerializedProof},
+    tree_circuits::dynamic_tree_circuit::DynamicTreePublicInputs,
+};
+
+use super::gate_serializer::PlasmaGateSerializer;
+
+fn verifier_only_path(dir: &Path, kind: CircuitKind) -> PathBuf {
+    dir.join(format!("{}.verifier_only.bin", kind))
+}
+
+fn common_path(dir: &Path, kind: CircuitKind) -> PathBuf {
+    dir.join(format!("{}.common.bin", kind))
+}
+
+/// Writes the `VerifierOnlyCircuitData` and the `CommonCircuitData` of the circuit to
+/// `<kind>.verifier_only.bin` and `<kind>.common.bin` in `dir`.
+pub fn export_verifier_data<F, C, const D: usize>(
+    dir: &Path,
+    kind: CircuitKind,
+    data: &CircuitData<F, C, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    write_verifier_data(dir, kind, &data.verifier_only, &data.common)
+}
+
+/// Same as `export_verifier_data`, but only needs the verifier data of the circuit.
+pub fn export_verifier_circuit_data<F, C, const D: usize>(
+    dir: &Path,
+    kind: CircuitKind,
+    data: &VerifierCircuitData<F, C, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    write_verifier_data(dir, kind, &data.verifier_only, &data.common)
+}
+
+fn write_verifier_data<F, C, const D: usize>(
+    dir: &Path,
+    kind: CircuitKind,
+    verifier_only: &VerifierOnlyCircuitData<C, D>,
+    common: &CommonCircuitData<F, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let verifier_only_bytes = verifier_only.to_bytes().map_err(|_| {
+        anyhow!(
+            "failed to serialize the verifier data of the {} circuit",
+            kind
+        )
+    })?;
+    let common_bytes = common.to_bytes(&PlasmaGateSerializer).map_err(|_| {
+        let gate_ids = common
+            .gates
+            .iter()
+            .map(|gate| gate.0.id())
+            .collect::<Vec<_>>();
+        anyhow!(
+            "failed to serialize the common data of the {} circuit with gates {:?}",
+            kind,
+            gate_ids
+        )
+    })?;
+    fs::create_dir_all(dir)?;
+    fs::write(verifier_only_path(dir, kind), verifier_only_bytes)?;
+    fs::write(common_path(dir, kind), common_bytes)?;
+    Ok(())
+}
+
+/// Returns the kinds of the circuits whose verifier data has been exported to `dir`.
+pub fn exported_kinds(dir: &Path) -> Vec<CircuitKind> {
+    CircuitKind::ALL
+        .into_iter()
+        .filter(|&kind| verifier_only_path(dir, kind).exists() && common_path(dir, kind).exists())
+        .collect()
+}
+
+/// Reads the verifier data written by `export_verifier_data`.
+pub fn load_verifier_data<F, C, const D: usize>(
+    dir: &Path,
+    kind: CircuitKind,
+) -> anyhow::Result<VerifierCircuitData<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let read = |path: PathBuf| {
+        fs::read(&path).map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))
+    };
+    let verifier_only = VerifierOnlyCircuitData::from_bytes(read(verifier_only_path(dir, kind))?)
+        .map_err(|_| {
+        anyhow!(
+            "failed to deserialize the verifier data of the {} circuit",
+            kind
+        )
+    })?;
+    let common =
+        CommonCircuitData::from_bytes(read(common_path(dir, kind))?, &PlasmaGateSerializer)
+            .map_err(|_| {
+                anyhow!(
+                    "failed to deserialize the common data of the {} circuit",
+                    kind
+                )
+            })?;
+    Ok(VerifierCircuitData {
+        verifier_only,
+        common,
+    })
+}
+
+/// Decodes and verifies a proof of the circuit of `kind` with its verifier data only. Proofs of
+/// cyclic circuits must also carry the verifier data of the circuit in their public inputs.
+pub fn verify_serialized_proof<F, C, const D: usize>(
+    kind: CircuitKind,
+    data: &VerifierCircuitData<F, C, D>,
+    proof: &SerializedProof,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let proof = proof.to_proof_with_verifier_data(kind, &data.verifier_only, &data.common)?;
+    if kind.is_cyclic() {
+        check_verifier_data_public_inputs(kind, &proof, &data.verifier_only)?;
+    }
+    data.verify(proof.clone())
+        .map_err(|e| anyhow!("the {} proof is invalid: {}", kind, e))?;
+    Ok(proof)
+}
+
+// Same as `check_cyclic_proof_verifier_data`, which needs an algebraic hasher: the public
+// inputs of a cyclic proof end with the circuit digest and the constants sigmas cap.
+fn check_verifier_data_public_inputs<F, C, const D: usize>(
+    kind: CircuitKind,
+    proof: &ProofWithPublicInputs<F, C, D>,
+    verifier_only: &VerifierOnlyCircuitData<C, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let verifier_data_pis = verifier_only
+        .circuit_digest
+        .to_vec()
+        .into_iter()
+        .chain(
+            verifier_only
+                .constants_sigmas_cap
+                .0
+                .iter()
+                .flat_map(|hash| hash.to_vec()),
+        )
+        .collect::<Vec<_>>();
+    ensure!(
+        proof.public_inputs.ends_with(&verifier_data_pis),
+        "the {} proof does not carry the verifier data of the circuit",
+        kind
+    );
+    Ok(())
+}
+
+/// Formats the public inputs of a proof of the circuit of `kind`.
+pub fn describe_public_inputs<F: RichField>(kind: CircuitKind, public_inputs: &[F]) -> String {
+    match kind {
+        CircuitKind::Spent => format!("{:#?}", SpentPublicInputs::from_vec(public_inputs)),
+        CircuitKind::Validity => format!("{:#?}", ValidityPublicInputs::from_pis(public_inputs)),
+        CircuitKind::BlockTree => {
+            format!("{:#?}", BlockTreePublicInputs::from_pis(public_inputs))
+        }
+        CircuitKind::Withdraw => format!("{:#?}", WithdrawPublicInputs::from_pis(public_inputs)),
+        CircuitKind::Settlement => {
+            let pis = DynamicTreePublicInputs::from_pis(public_inputs);
+            format!(
+                "settlement root: {}\nblock root: {:?}",
+                pis.hash, pis.block_root
+            )
+        }
+        // the keccak hash of `WrapPublicInputs` reduced to field elements
+        CircuitKind::Wrap2 => format!("wrap public inputs hash: {:?}", public_inputs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+    use plonky2_u32::gadgets::arithmetic_u32::CircuitBuilderU32;
+
+    use super::{
+        export_verifier_data, exported_kinds, load_verifier_data, verify_serialized_proof,
+    };
+    use crate::serialization::serialized_proof::{CircuitKind, SerializedProof};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_export_and_verify() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let a = builder.add_virtual_u32_target();
+        let b = builder.add_virtual_u32_target();
+        let (c, _) = builder.add_u32(a, b);
+        builder.register_public_input(c.0);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(a.0, F::from_canonical_u32(1));
+        pw.set_target(b.0, F::from_canonical_u32(2));
+        let proof = data.prove(pw).unwrap();
+        let serialized_proof = SerializedProof::from_proof(CircuitKind::Spent, &data, &proof);
+
+        let dir = std::env::temp_dir().join(format!("verifier_data_{}", rand::random::<u64>()));
+        export_verifier_data(&dir, CircuitKind::Spent, &data).unwrap();
+        let verifier_data = load_verifier_data::<F, C, D>(&dir, CircuitKind::Spent).unwrap();
+        let recovered =
+            verify_serialized_proof(CircuitKind::Spent, &verifier_data, &serialized_proof).unwrap();
+        assert_eq!(recovered.public_inputs, proof.public_inputs);
+        assert!(load_verifier_data::<F, C, D>(&dir, CircuitKind::Validity).is_err());
+        assert_eq!(exported_kinds(&dir), vec![CircuitKind::Spent]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
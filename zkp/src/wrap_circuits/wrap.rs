@@ -1,5 +1,6 @@
 use std::fmt::Display;
 
+use anyhow::ensure;
 use plonky2::{
     field::{extension::Extendable, types::Field},
     hash::hash_types::{HashOut, RichField},
@@ -9,7 +10,7 @@ use plonky2::{
     },
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitConfig, CircuitData},
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitData},
         config::{AlgebraicHasher, GenericConfig},
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
@@ -19,14 +20,19 @@ use starky_keccak::{builder::CircuitBuilderWithKeccak, keccak256_circuit::solidi
 
 use crate::{
     base_circuits::{
-        block_tree_circuit::{BlockTreeCircuit, BlockTreePublicInputsTarget},
-        validity_circuit::{ValidityCircuit, ValidityPublicInputTargets},
+        block_tree_circuit::{
+            BlockTreeCircuit, BlockTreePublicInputs, BlockTreePublicInputsTarget,
+        },
+        validity_circuit::{ValidityCircuit, ValidityPublicInputTargets, ValidityPublicInputs},
     },
     tree_circuits::{
-        dynamic_tree_circuit::DynamicTreePublicInputsTarget,
+        dynamic_tree_circuit::{DynamicTreePublicInputs, DynamicTreePublicInputsTarget},
         settlement_tree_circuit::SettlementTreeCircuit,
     },
-    utils::h256::{H256Target, H256},
+    utils::{
+        h256::{H256Target, H256},
+        mock::{mock_proof, mock_verify_inner},
+    },
 };
 
 pub struct WrapCircuit<F, C, const D: usize>
@@ -38,6 +44,9 @@ where
     pub validity_proof: ProofWithPublicInputsTarget<D>,
     pub block_tree_proof: ProofWithPublicInputsTarget<D>,
     pub settlement_tree_proof: ProofWithPublicInputsTarget<D>,
+    validity_verifier_data: VerifierCircuitData<F, C, D>,
+    block_tree_verifier_data: VerifierCircuitData<F, C, D>,
+    settlement_tree_verifier_data: VerifierCircuitData<F, C, D>,
 }
 
 impl<F, C, const D: usize> WrapCircuit<F, C, D>
@@ -84,6 +93,9 @@ where
             validity_proof,
             block_tree_proof,
             settlement_tree_proof,
+            validity_verifier_data: validity_circuit.data.verifier_data(),
+            block_tree_verifier_data: block_tree_circuit.data.verifier_data(),
+            settlement_tree_verifier_data: settlement_tree_circuit.data.verifier_data(),
         }
     }
 
@@ -93,6 +105,9 @@ where
         block_tree_proof: ProofWithPublicInputs<F, C, D>,
         settlement_tree_proof: ProofWithPublicInputs<F, C, D>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        if cfg!(feature = "mock") {
+            return self.prove_mock(validity_proof, block_tree_proof, settlement_tree_proof);
+        }
         let mut pw = PartialWitness::new();
         pw.set_proof_with_pis_target(&self.validity_proof, &validity_proof);
         pw.set_proof_with_pis_target(&self.block_tree_proof, &block_tree_proof);
@@ -100,6 +115,39 @@ where
         self.data.prove(pw)
     }
 
+    // Checks the witness natively instead of proving it.
+    fn prove_mock(
+        &self,
+        validity_proof: ProofWithPublicInputs<F, C, D>,
+        block_tree_proof: ProofWithPublicInputs<F, C, D>,
+        settlement_tree_proof: ProofWithPublicInputs<F, C, D>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        mock_verify_inner(&self.validity_verifier_data, &validity_proof, true)?;
+        mock_verify_inner(&self.block_tree_verifier_data, &block_tree_proof, true)?;
+        mock_verify_inner(
+            &self.settlement_tree_verifier_data,
+            &settlement_tree_proof,
+            true,
+        )?;
+        let validity_pis = ValidityPublicInputs::from_pis(&validity_proof.public_inputs);
+        let block_tree_pis = BlockTreePublicInputs::from_pis(&block_tree_proof.public_inputs);
+        let settlement_tree_pis =
+            DynamicTreePublicInputs::from_pis(&settlement_tree_proof.public_inputs);
+        ensure!(
+            validity_pis.block_hash == block_tree_pis.block_hash,
+            "block hash mismatch"
+        );
+        ensure!(
+            settlement_tree_pis.block_root == block_tree_pis.block_root,
+            "block root mismatch"
+        );
+        let pis = WrapPublicInputs {
+            block_hash: validity_pis.block_hash,
+            settlement_root: settlement_tree_pis.hash,
+        };
+        mock_proof(&self.data, pis.to_solidity_pis::<F>().elements.to_vec())
+    }
+
     pub fn add_proof_target_and_verify(
         &self,
         builder: &mut CircuitBuilder<F, D>,
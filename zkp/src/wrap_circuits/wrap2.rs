@@ -6,12 +6,14 @@ use plonky2::{
     iop::witness::{PartialWitness, WitnessWrite},
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitConfig, CircuitData},
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitData},
         config::{AlgebraicHasher, GenericConfig},
         proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
     },
 };
 
+use crate::utils::mock::{mock_proof, mock_verify, mock_verify_inner};
+
 use super::wrap::WrapCircuit;
 
 // By further wrapping the wrap_circuit, we reduce the degree_bits.
@@ -23,6 +25,7 @@ where
 {
     pub data: CircuitData<F, OuterC, D>,
     pub wrap_proof: ProofWithPublicInputsTarget<D>,
+    wrap_verifier_data: VerifierCircuitData<F, C, D>,
     _maker: PhantomData<C>,
 }
 
@@ -42,6 +45,7 @@ where
         Self {
             data,
             wrap_proof,
+            wrap_verifier_data: wrap_circuit.data.verifier_data(),
             _maker: PhantomData,
         }
     }
@@ -50,8 +54,22 @@ where
         &self,
         wrap_proof: &ProofWithPublicInputs<F, C, D>,
     ) -> anyhow::Result<ProofWithPublicInputs<F, OuterC, D>> {
+        if cfg!(feature = "mock") {
+            mock_verify_inner(&self.wrap_verifier_data, wrap_proof, false)?;
+            return mock_proof(&self.data, wrap_proof.public_inputs.clone());
+        }
         let mut pw = PartialWitness::new();
         pw.set_proof_with_pis_target(&self.wrap_proof, wrap_proof);
         self.data.prove(pw)
     }
+
+    pub fn verify(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, OuterC, D>,
+    ) -> anyhow::Result<()> {
+        if cfg!(feature = "mock") {
+            return mock_verify(&self.data, &proof_with_pis);
+        }
+        self.data.verify(proof_with_pis)
+    }
 }